@@ -0,0 +1,135 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, LitStr};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "EncodeLabelSet can only be derived for structs",
+        ));
+    };
+
+    for attr in &input.attrs {
+        if !attr.path().is_ident("serde") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                meta.value()?.parse::<LitStr>()?;
+                Ok(())
+            } else {
+                Err(meta.error("unsupported serde attribute for EncodeLabelSet"))
+            }
+        })?;
+    }
+
+    let fields = match &data.fields {
+        Fields::Named(fields) => &fields.named,
+        Fields::Unit => return Ok(implementation(input, quote! { Ok(()) })),
+        Fields::Unnamed(_) => {
+            return Err(Error::new_spanned(
+                input,
+                "EncodeLabelSet can only be derived for structs with named fields",
+            ))
+        }
+    };
+
+    let mut writes = vec![];
+    let mut separator = "";
+
+    for field in fields {
+        let ident = field.ident.as_ref().expect("named field");
+
+        let mut name = None;
+        let mut skip = false;
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("serde") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("rename") {
+                    name = Some(meta.value()?.parse::<LitStr>()?);
+                } else if meta.path.is_ident("skip") || meta.path.is_ident("skip_serializing") {
+                    skip = true;
+                } else {
+                    return Err(meta.error("unsupported serde attribute for EncodeLabelSet"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let name = match name {
+            Some(name) => name.value(),
+            None => {
+                let name = ident.to_string();
+                name.strip_prefix("r#").unwrap_or(&name).to_owned()
+            }
+        };
+
+        if !is_label_name(&name) {
+            return Err(Error::new_spanned(
+                ident,
+                format!("invalid label name {name:?}"),
+            ));
+        }
+
+        let prefix = format!("{separator}{name}=\"");
+        separator = "\",";
+
+        writes.push(quote! {
+            writer.write_all(#prefix.as_bytes())?;
+            ::prometools::serde::encode_label_value(&self.#ident, writer, options)?;
+        });
+    }
+
+    let end = if writes.is_empty() {
+        quote! { Ok(()) }
+    } else {
+        quote! { writer.write_all(b"\"") }
+    };
+
+    Ok(implementation(
+        input,
+        quote! {
+            #(#writes)*
+            #end
+        },
+    ))
+}
+
+fn implementation(input: &DeriveInput, body: TokenStream) -> TokenStream {
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics ::prometools::serde::EncodeLabelSet for #ident #ty_generics
+        #where_clause
+        {
+            fn encode_label_set(
+                &self,
+                writer: &mut dyn ::std::io::Write,
+                options: &::prometools::serde::Options,
+            ) -> ::std::io::Result<()> {
+                #body
+            }
+        }
+    }
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
@@ -0,0 +1,46 @@
+use syn::{Attribute, Expr, ExprLit, Lit, Meta};
+
+/// Returns the first paragraph of the doc comments in `attrs`, joined on a
+/// single line and without its final period, which `prometheus-client`
+/// appends itself.
+pub(crate) fn from_docs(attrs: &[Attribute]) -> Option<String> {
+    let mut help = String::new();
+
+    for attr in attrs {
+        let Meta::NameValue(meta) = &attr.meta else {
+            continue;
+        };
+
+        if !meta.path.is_ident("doc") {
+            continue;
+        }
+
+        let Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) = &meta.value
+        else {
+            continue;
+        };
+
+        let value = lit.value();
+        let line = value.trim();
+
+        if line.is_empty() {
+            if help.is_empty() {
+                continue;
+            }
+
+            break;
+        }
+
+        if !help.is_empty() {
+            help.push(' ');
+        }
+
+        help.push_str(line);
+    }
+
+    let help = help.strip_suffix('.').unwrap_or(&help);
+
+    (!help.is_empty()).then(|| help.to_owned())
+}
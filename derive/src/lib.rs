@@ -0,0 +1,52 @@
+//! Derive macros for [prometools](https://docs.rs/prometools).
+//!
+//! This crate is not meant to be used directly, enable the `derive` feature
+//! of `prometools` instead.
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemFn};
+
+mod encode_label_set;
+mod help;
+mod register_metric;
+mod registrant;
+mod timed;
+
+#[proc_macro_derive(Registrant, attributes(registrant))]
+pub fn derive_registrant(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    registrant::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_derive(EncodeLabelSet, attributes(serde))]
+pub fn derive_encode_label_set(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    encode_label_set::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn register_metric(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as register_metric::Input);
+
+    register_metric::expand(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro_attribute]
+pub fn timed(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut timed_args = timed::Args::default();
+    let parser = syn::meta::parser(|meta| timed_args.parse(meta));
+    parse_macro_input!(args with parser);
+    let item = parse_macro_input!(item as ItemFn);
+
+    timed::expand(timed_args, item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
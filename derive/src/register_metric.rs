@@ -0,0 +1,66 @@
+use crate::help;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    Attribute, Error, Expr, Ident, Token,
+};
+
+pub(crate) struct Input {
+    registry: Expr,
+    attrs: Vec<Attribute>,
+    name: Ident,
+    metric: Expr,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let registry = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let attrs = input.call(Attribute::parse_outer)?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let metric = input.parse()?;
+        input.parse::<Option<Token![,]>>()?;
+
+        Ok(Self {
+            registry,
+            attrs,
+            name,
+            metric,
+        })
+    }
+}
+
+pub(crate) fn expand(input: &Input) -> Result<TokenStream, Error> {
+    let Input {
+        registry,
+        attrs,
+        name,
+        metric,
+    } = input;
+
+    if let Some(attr) = attrs.iter().find(|attr| !attr.path().is_ident("doc")) {
+        return Err(Error::new_spanned(
+            attr,
+            "only doc comments are allowed here",
+        ));
+    }
+
+    let Some(help) = help::from_docs(attrs) else {
+        return Err(Error::new_spanned(name, "missing help, add a doc comment"));
+    };
+
+    let name = name.to_string();
+    let name = name.strip_prefix("r#").unwrap_or(&name);
+
+    Ok(quote! {{
+        let metric = #metric;
+        (#registry).register(
+            #name,
+            #help,
+            ::std::boxed::Box::new(::std::clone::Clone::clone(&metric)),
+        );
+        metric
+    }})
+}
@@ -0,0 +1,117 @@
+use crate::help;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Data, DeriveInput, Error, Fields, LitStr};
+
+pub(crate) fn expand(input: &DeriveInput) -> Result<TokenStream, Error> {
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "Registrant can only be derived for structs",
+        ));
+    };
+
+    let Fields::Named(fields) = &data.fields else {
+        return Err(Error::new_spanned(
+            input,
+            "Registrant can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut prefix = None;
+
+    for attr in &input.attrs {
+        if !attr.path().is_ident("registrant") {
+            continue;
+        }
+
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                prefix = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("unknown registrant attribute"))
+            }
+        })?;
+    }
+
+    let mut registrations = vec![];
+
+    for field in &fields.named {
+        let ident = field.ident.as_ref().expect("named field");
+
+        let mut name = None;
+        let mut help = None;
+        let mut skip = false;
+
+        for attr in &field.attrs {
+            if !attr.path().is_ident("registrant") {
+                continue;
+            }
+
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("help") {
+                    help = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("skip") {
+                    skip = true;
+                } else {
+                    return Err(meta.error("unknown registrant attribute"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if skip {
+            continue;
+        }
+
+        let name = name.unwrap_or_else(|| {
+            let name = ident.to_string();
+            name.strip_prefix("r#").unwrap_or(&name).to_owned()
+        });
+
+        let Some(help) = help.or_else(|| help::from_docs(&field.attrs)) else {
+            return Err(Error::new_spanned(
+                ident,
+                "missing help, add a doc comment or #[registrant(help = \"...\")]",
+            ));
+        };
+
+        registrations.push(quote! {
+            registry.register(
+                #name,
+                #help,
+                ::std::boxed::Box::new(::std::clone::Clone::clone(&self.#ident)),
+            );
+        });
+    }
+
+    let registry = match prefix {
+        Some(prefix) => quote! { let registry = registry.sub_registry_with_prefix(#prefix); },
+        None => quote! {},
+    };
+
+    if registrations.is_empty() {
+        registrations.push(quote! { let _ = registry; });
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::prometools::registrant::Registrant for #ident #ty_generics
+        #where_clause
+        {
+            fn register(
+                &self,
+                registry: &mut ::prometools::__private::prometheus_client::registry::Registry,
+            ) {
+                #registry
+                #(#registrations)*
+            }
+        }
+    })
+}
@@ -0,0 +1,65 @@
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{meta::ParseNestedMeta, Error, Expr, ItemFn, ReturnType};
+
+#[derive(Default)]
+pub(crate) struct Args {
+    histogram: Option<Expr>,
+}
+
+impl Args {
+    pub(crate) fn parse(&mut self, meta: ParseNestedMeta<'_>) -> syn::Result<()> {
+        if meta.path.is_ident("histogram") {
+            self.histogram = Some(meta.value()?.parse()?);
+            Ok(())
+        } else {
+            Err(meta.error("unknown timed attribute"))
+        }
+    }
+}
+
+pub(crate) fn expand(args: Args, mut item: ItemFn) -> Result<TokenStream, Error> {
+    let Some(histogram) = args.histogram else {
+        return Err(Error::new_spanned(
+            &item.sig.ident,
+            "missing histogram, add `histogram = path::to::HISTOGRAM`",
+        ));
+    };
+
+    let block = &item.block;
+    let start_timer = quote! {
+        ::prometools::histogram::TimeHistogram::start_timer(&#histogram)
+    };
+
+    item.block = if item.sig.asyncness.is_none() {
+        // A synchronous function can't be cancelled, so the timer always
+        // records when dropped, including on early returns, `?` and panics.
+        syn::parse_quote!({
+            let __prometools_timer = #start_timer.on_drop(
+                ::prometools::histogram::DropPolicy::Record,
+            );
+            #block
+        })
+    } else {
+        // The body is wrapped in an async block so that its early returns
+        // and `?` complete the timer, which otherwise follows the drop
+        // policy of the histogram if the future is cancelled.
+        let output = match &item.sig.output {
+            ReturnType::Default => quote!(()),
+            ReturnType::Type(_, ty) if ty.to_token_stream().to_string().contains("impl") => {
+                quote!(_)
+            }
+            ReturnType::Type(_, ty) => quote!(#ty),
+        };
+
+        syn::parse_quote!({
+            let __prometools_timer = #start_timer;
+            let __prometools_output =
+                ::prometools::__private::expect_output::<#output, _>(async move #block).await;
+            __prometools_timer.stop_and_record();
+            __prometools_output
+        })
+    };
+
+    Ok(quote!(#item))
+}
@@ -0,0 +1,112 @@
+//! Common bucket layouts for histograms.
+//!
+//! Latency layouts are in seconds, to be used with
+//! [`TimeHistogram`](crate::histogram::TimeHistogram), e.g.
+//! `TimeHistogram::new(buckets::HTTP_LATENCY.into_iter())`, or in durations,
+//! to be used with
+//! [`TimeHistogram::with_duration_buckets`](crate::histogram::TimeHistogram::with_duration_buckets).
+
+use std::time::Duration;
+
+/// Latency buckets for HTTP servers, the default buckets of the Prometheus
+/// client libraries, from 5ms to 10s.
+pub const HTTP_LATENCY: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Latency buckets for gRPC servers, finer than [`HTTP_LATENCY`] for fast
+/// unary calls, from 1ms to 10s.
+pub const GRPC_LATENCY: [f64; 13] = [
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Latency buckets for DNS lookups, from 0.5ms for cached answers to 2s for
+/// timeouts.
+pub const DNS_LATENCY: [f64; 12] = [
+    0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.0,
+];
+
+/// Size buckets in bytes, powers of 4 from 64B to 64MiB.
+pub const BYTE_SIZE: [f64; 11] = [
+    64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0, 16777216.0,
+    67108864.0,
+];
+
+/// Returns the powers of two from `2^min_exp` to `2^max_exp` inclusive.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::buckets;
+/// #
+/// assert_eq!(
+///     buckets::powers_of_two(-2, 2).collect::<Vec<_>>(),
+///     [0.25, 0.5, 1.0, 2.0, 4.0],
+/// );
+/// ```
+pub fn powers_of_two(min_exp: i32, max_exp: i32) -> impl Iterator<Item = f64> {
+    (min_exp..=max_exp).map(|exp| 2f64.powi(exp))
+}
+
+/// Returns buckets centered on a latency objective, so that the proportion
+/// of requests meeting it can be computed exactly.
+///
+/// Buckets are spaced linearly by `resolution` times `target` from half to
+/// one and a half times `target`, which is always a bucket bound, with
+/// coarser buckets at a tenth and a quarter of `target` below them, and at
+/// two, four and ten times `target` above them. `resolution` is clamped
+/// between 1% and 50%.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::buckets;
+/// #
+/// assert_eq!(
+///     buckets::around_slo(1.0, 0.25).collect::<Vec<_>>(),
+///     [0.1, 0.25, 0.5, 0.75, 1.0, 1.25, 1.5, 2.0, 4.0, 10.0],
+/// );
+/// ```
+pub fn around_slo(target: f64, resolution: f64) -> impl Iterator<Item = f64> {
+    let resolution = resolution.clamp(0.01, 0.5);
+    let steps = (0.5 / resolution).floor() as i32;
+
+    let below = [0.1, 0.25].into_iter();
+    let around = (-steps..=steps).map(move |i| 1.0 + f64::from(i) * resolution);
+    let above = [2.0, 4.0, 10.0].into_iter();
+
+    below
+        .chain(around)
+        .chain(above)
+        .map(move |factor| target * factor)
+}
+
+/// Returns `len` durations starting at `start`, each `factor` times the
+/// previous one, saturating at [`Duration::MAX`].
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::buckets;
+/// # use std::time::Duration;
+/// #
+/// assert_eq!(
+///     buckets::duration_exponential_buckets(Duration::from_millis(5), 2.0, 3).collect::<Vec<_>>(),
+///     [
+///         Duration::from_millis(5),
+///         Duration::from_millis(10),
+///         Duration::from_millis(20),
+///     ],
+/// );
+/// ```
+pub fn duration_exponential_buckets(
+    start: Duration,
+    factor: f64,
+    len: u16,
+) -> impl Iterator<Item = Duration> {
+    (0..len).map(move |i| {
+        let secs = start.as_secs_f64() * factor.powi(i.into());
+
+        Duration::try_from_secs_f64(secs).unwrap_or(Duration::MAX)
+    })
+}
@@ -0,0 +1,114 @@
+//! Metrics with a fixed value, e.g. computed once from a configuration, which
+//! don't need atomics.
+
+use prometheus_client::{
+    encoding::text::{Encode, EncodeMetric, Encoder},
+    metrics::{MetricType, TypedMetric},
+};
+use std::io;
+
+/// A counter with a fixed value.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::constant::{ConstCounter, ConstGauge};
+/// #
+/// let mut registry = <Registry>::default();
+///
+/// registry.register("restarts", "Number of restarts", Box::new(ConstCounter::new(3u64)));
+/// registry.register("workers", "Number of workers", Box::new(ConstGauge::new(8u64)));
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP restarts Number of restarts.\n",
+///         "# TYPE restarts counter\n",
+///         "restarts_total 3\n",
+///         "# HELP workers Number of workers.\n",
+///         "# TYPE workers gauge\n",
+///         "workers 8\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConstCounter<N = u64> {
+    value: N,
+}
+
+impl<N> ConstCounter<N> {
+    pub const fn new(value: N) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> &N {
+        &self.value
+    }
+}
+
+impl<N> TypedMetric for ConstCounter<N> {
+    const TYPE: MetricType = MetricType::Counter;
+}
+
+impl<N> EncodeMetric for ConstCounter<N>
+where
+    N: Encode + Clone,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .encode_suffix("total")?
+            .no_bucket()?
+            .encode_value(self.value.clone())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A gauge with a fixed value, see [`ConstCounter`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConstGauge<N = u64> {
+    value: N,
+}
+
+impl<N> ConstGauge<N> {
+    pub const fn new(value: N) -> Self {
+        Self { value }
+    }
+
+    pub fn get(&self) -> &N {
+        &self.value
+    }
+}
+
+impl<N> TypedMetric for ConstGauge<N> {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl<N> EncodeMetric for ConstGauge<N>
+where
+    N: Encode + Clone,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(self.value.clone())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
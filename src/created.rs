@@ -0,0 +1,32 @@
+//! Creation timestamps, exposed as OpenMetrics `_created` samples.
+
+use prometheus_client::encoding::text::Encoder;
+use std::io;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Returns the number of nanoseconds between the Unix epoch and now.
+pub(crate) fn now_nanos() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos().try_into().unwrap_or(u64::MAX))
+}
+
+/// Returns the time `nanos` nanoseconds after the Unix epoch.
+pub(crate) fn from_nanos(nanos: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_nanos(nanos)
+}
+
+/// Encodes the `_created` sample of a metric created at `created`.
+pub(crate) fn encode(encoder: &mut Encoder, created: SystemTime) -> io::Result<()> {
+    let secs = created
+        .duration_since(UNIX_EPOCH)
+        .map_or(0.0, |since| since.as_secs_f64());
+
+    encoder
+        .encode_suffix("created")?
+        .no_bucket()?
+        .encode_value(secs)?
+        .no_exemplar()?;
+
+    Ok(())
+}
@@ -0,0 +1,158 @@
+//! Encodings of registries in other formats than OpenMetrics.
+
+pub mod filter;
+#[cfg(feature = "gzip")]
+#[cfg_attr(docsrs, doc(cfg(feature = "gzip")))]
+pub mod gzip;
+pub mod incremental;
+pub mod json;
+pub mod line_protocol;
+pub(crate) mod message;
+pub mod openmetrics_protobuf;
+pub(crate) mod parse;
+pub mod protobuf;
+#[cfg(feature = "relabel")]
+#[cfg_attr(docsrs, doc(cfg(feature = "relabel")))]
+pub mod relabel;
+pub mod text_legacy;
+
+use crate::histogram::NativeHistograms;
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use std::io;
+
+/// The content type of the OpenMetrics text format.
+pub const OPENMETRICS_CONTENT_TYPE: &str =
+    "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+/// An exposition format, which scrapers ask for with their `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    /// The OpenMetrics text format.
+    OpenMetricsText,
+    /// The OpenMetrics protobuf format, see [`openmetrics_protobuf`].
+    OpenMetricsProtobuf,
+    /// The Prometheus protobuf format, see [`protobuf`].
+    Protobuf,
+    /// The classic Prometheus text format, see [`text_legacy`].
+    TextLegacy,
+}
+
+impl Format {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::OpenMetricsText => OPENMETRICS_CONTENT_TYPE,
+            Self::OpenMetricsProtobuf => openmetrics_protobuf::CONTENT_TYPE,
+            Self::Protobuf => protobuf::CONTENT_TYPE,
+            Self::TextLegacy => text_legacy::CONTENT_TYPE,
+        }
+    }
+
+    /// Encodes the registry in this format, and returns it with its content
+    /// type.
+    pub fn encode<M: EncodeMetric>(
+        self,
+        registry: &Registry<M>,
+    ) -> io::Result<(Vec<u8>, &'static str)> {
+        self.encode_with_native(registry, &NativeHistograms::default())
+    }
+
+    /// Encodes the registry in this format like [`Format::encode`], with the
+    /// histograms listed in `natives` encoded as native histograms if this
+    /// is the Prometheus protobuf format, see
+    /// [`protobuf::encode_with_native`].
+    pub fn encode_with_native<M: EncodeMetric>(
+        self,
+        registry: &Registry<M>,
+        natives: &NativeHistograms,
+    ) -> io::Result<(Vec<u8>, &'static str)> {
+        let mut body = vec![];
+
+        match self {
+            Self::OpenMetricsText => text::encode(&mut body, registry)?,
+            Self::OpenMetricsProtobuf => openmetrics_protobuf::encode(&mut body, registry)?,
+            Self::Protobuf => protobuf::encode_with_native(&mut body, registry, natives)?,
+            Self::TextLegacy => text_legacy::encode(&mut body, registry)?,
+        }
+
+        Ok((body, self.content_type()))
+    }
+
+    /// Returns the format described by a media range of an `Accept` header,
+    /// e.g. `text/plain;version=0.0.4`.
+    fn from_media_range(media_range: &str) -> Option<Self> {
+        let mut parts = media_range.split(';').map(str::trim);
+        let media_type = parts.next()?.to_ascii_lowercase();
+        let mut params = parts.filter_map(|param| param.split_once('='));
+
+        match &*media_type {
+            "application/openmetrics-text" => Some(Self::OpenMetricsText),
+            "application/openmetrics-protobuf" => Some(Self::OpenMetricsProtobuf),
+            "application/vnd.google.protobuf" => params
+                .any(|(name, value)| {
+                    name.eq_ignore_ascii_case("proto")
+                        && value == "io.prometheus.client.MetricFamily"
+                })
+                .then_some(Self::Protobuf),
+            "text/plain" | "text/*" | "*/*" => Some(Self::TextLegacy),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the format to encode a registry in, given the `Accept` header of
+/// a scrape request.
+///
+/// The supported format with the highest quality is returned, the first one
+/// listed winning ties. The classic Prometheus text format is returned if
+/// the header is missing, or if no supported format is acceptable, as all
+/// Prometheus scrapers support it.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::encoding::{negotiate, Format};
+/// #
+/// let mut registry = <Registry>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(<Counter>::default()));
+///
+/// // The `Accept` header sent by Prometheus.
+/// let accept = concat!(
+///     "application/openmetrics-text;version=1.0.0;q=0.5,",
+///     "application/openmetrics-text;version=0.0.1;q=0.4,",
+///     "text/plain;version=0.0.4;q=0.3,",
+///     "*/*;q=0.2",
+/// );
+///
+/// assert_eq!(negotiate(Some(accept)), Format::OpenMetricsText);
+/// assert_eq!(negotiate(None), Format::TextLegacy);
+///
+/// let (body, content_type) = negotiate(Some(accept)).encode(&registry).unwrap();
+///
+/// assert_eq!(content_type, "application/openmetrics-text; version=1.0.0; charset=utf-8");
+/// assert!(body.ends_with(b"# EOF\n"));
+/// ```
+pub fn negotiate(accept: Option<&str>) -> Format {
+    let mut best = (Format::TextLegacy, 0.0);
+
+    for media_range in accept.unwrap_or_default().split(',') {
+        let Some(format) = Format::from_media_range(media_range) else {
+            continue;
+        };
+        let quality = media_range
+            .split(';')
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.parse::<f64>().ok())
+            .unwrap_or(1.0);
+
+        if quality > best.1 {
+            best = (format, quality);
+        }
+    }
+
+    best.0
+}
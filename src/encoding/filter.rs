@@ -0,0 +1,235 @@
+//! Filtering of the families and series exposed by a registry, e.g. to
+//! hide per-tenant families from a public endpoint.
+//!
+//! The registry is encoded in the OpenMetrics text format, then its lines
+//! are filtered, so the output is OpenMetrics text too.
+
+use super::parse;
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use std::io;
+
+/// Rules deciding which families and series are encoded.
+///
+/// A family is encoded if its name matches one of the allowed patterns, if
+/// any, and none of the denied patterns. A series is encoded if its labels
+/// match all the required label patterns and none of the denied ones, a
+/// missing label matching like an empty one. Families whose series are all
+/// skipped are skipped too.
+///
+/// Patterns match whole names and values, with `*` matching any sequence of
+/// characters.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, family::Family},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::encoding::filter::Filter;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+/// let tenant_requests = Family::<Vec<(&str, &str)>, Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("tenant_requests", "Number of requests per tenant", Box::new(tenant_requests.clone()));
+///
+/// requests.get_or_create(&vec![("path", "/api")]).inc();
+/// requests.get_or_create(&vec![("path", "/internal/health")]).inc();
+/// tenant_requests.get_or_create(&vec![("tenant", "acme")]).inc();
+///
+/// let filter = Filter::default()
+///     .deny("tenant_*")
+///     .deny_label("path", "/internal/*");
+/// let mut serialized = vec![];
+///
+/// filter.encode(&mut serialized, &registry).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total{path=\"/api\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Filter {
+    allow: Vec<String>,
+    deny: Vec<String>,
+    require_labels: Vec<(String, String)>,
+    deny_labels: Vec<(String, String)>,
+}
+
+impl Filter {
+    /// Only encodes families whose name matches one of the allowed patterns.
+    pub fn allow(mut self, pattern: impl Into<String>) -> Self {
+        self.allow.push(pattern.into());
+        self
+    }
+
+    /// Skips families whose name matches `pattern`.
+    pub fn deny(mut self, pattern: impl Into<String>) -> Self {
+        self.deny.push(pattern.into());
+        self
+    }
+
+    /// Only encodes series whose label `name` matches `pattern`.
+    pub fn require_label(mut self, name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.require_labels.push((name.into(), pattern.into()));
+        self
+    }
+
+    /// Skips series whose label `name` matches `pattern`.
+    pub fn deny_label(mut self, name: impl Into<String>, pattern: impl Into<String>) -> Self {
+        self.deny_labels.push((name.into(), pattern.into()));
+        self
+    }
+
+    /// Encodes the families and series of the registry allowed by the
+    /// filter, in the OpenMetrics text format.
+    pub fn encode<W, M>(&self, writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+    where
+        W: io::Write,
+        M: EncodeMetric,
+    {
+        let mut buf = vec![];
+        text::encode(&mut buf, registry)?;
+
+        let text = String::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        writer.write_all(self.filter(&text).as_bytes())
+    }
+
+    /// Filters metrics in the OpenMetrics text format.
+    ///
+    /// Lines which can't be parsed are kept as they are.
+    pub fn filter(&self, text: &str) -> String {
+        let mut output = String::new();
+        let mut family = FamilyLines::default();
+
+        for line in text.lines() {
+            let (name, is_sample) = match line.strip_prefix("# ") {
+                Some(rest) => match rest.split(' ').nth(1) {
+                    Some(name) if rest != "EOF" => (name, false),
+                    _ => {
+                        family.flush(&mut output);
+                        output.push_str(line);
+                        output.push('\n');
+                        continue;
+                    }
+                },
+                None => (&line[..line.find(['{', ' ']).unwrap_or(line.len())], true),
+            };
+
+            let same_family = match family.name {
+                Some(family) => name.starts_with(family),
+                None => false,
+            };
+
+            if !same_family || (!is_sample && family.name != Some(name)) {
+                family.flush(&mut output);
+                family.name = Some(name);
+                family.allowed = self.allows_family(name);
+            }
+
+            if !family.allowed {
+                continue;
+            }
+
+            if is_sample {
+                family.samples += 1;
+
+                if !self.allows_series(&line[name.len()..]) {
+                    continue;
+                }
+
+                family.kept += 1;
+            }
+
+            family.lines.push_str(line);
+            family.lines.push('\n');
+        }
+
+        family.flush(&mut output);
+        output
+    }
+
+    fn allows_family(&self, name: &str) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|pattern| glob(pattern, name)))
+            && !self.deny.iter().any(|pattern| glob(pattern, name))
+    }
+
+    /// Returns whether the series whose label set starts `rest` is allowed.
+    fn allows_series(&self, rest: &str) -> bool {
+        let Some((labels, _rest)) = parse::parse_labels(rest) else {
+            return true;
+        };
+        let value = |name: &str| {
+            labels
+                .iter()
+                .find(|(label, _value)| *label == name)
+                .map_or("", |(_label, value)| &**value)
+        };
+
+        self.require_labels
+            .iter()
+            .all(|(name, pattern)| glob(pattern, value(name)))
+            && !self
+                .deny_labels
+                .iter()
+                .any(|(name, pattern)| glob(pattern, value(name)))
+    }
+}
+
+/// The lines of the family being filtered.
+#[derive(Default)]
+struct FamilyLines<'a> {
+    name: Option<&'a str>,
+    allowed: bool,
+    lines: String,
+    samples: usize,
+    kept: usize,
+}
+
+impl FamilyLines<'_> {
+    fn flush(&mut self, output: &mut String) {
+        if self.samples == 0 || self.kept > 0 {
+            output.push_str(&self.lines);
+        }
+
+        *self = Self::default();
+    }
+}
+
+/// Returns whether `s` matches `pattern`, in which `*` matches any sequence
+/// of characters.
+fn glob(pattern: &str, s: &str) -> bool {
+    let Some((prefix, rest)) = pattern.split_once('*') else {
+        return pattern == s;
+    };
+    let Some(mut s) = s.strip_prefix(prefix) else {
+        return false;
+    };
+    let mut parts = rest.split('*').peekable();
+
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return s.ends_with(part);
+        }
+
+        match s.find(part) {
+            Some(i) => s = &s[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    true
+}
@@ -0,0 +1,86 @@
+//! Gzip-compressed encoding in the OpenMetrics text format, for handlers
+//! honoring `Accept-Encoding: gzip`.
+
+use flate2::{write::GzEncoder, Compression};
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use std::io;
+
+/// The content type of the OpenMetrics text format, which is compressed.
+pub const CONTENT_TYPE: &str = super::OPENMETRICS_CONTENT_TYPE;
+
+/// The content encoding of gzip-compressed bodies.
+pub const CONTENT_ENCODING: &str = "gzip";
+
+/// Encodes the registry in the OpenMetrics text format, compressed with
+/// gzip, to `writer` as the registry is encoded.
+pub fn encode<W, M>(writer: W, registry: &Registry<M>) -> io::Result<W>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let mut gzip = GzipWriter::new(writer);
+    text::encode(&mut gzip, registry)?;
+    gzip.finish()
+}
+
+/// Encodes the registry in the OpenMetrics text format, compressed with
+/// gzip.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::encoding::gzip::{self, CONTENT_ENCODING, CONTENT_TYPE};
+/// #
+/// let mut registry = <Registry>::default();
+///
+/// for i in 0..100 {
+///     registry.register(format!("requests_{i}"), "Number of requests", Box::new(<Counter>::default()));
+/// }
+///
+/// let body = gzip::encode_to_vec(&registry).unwrap();
+///
+/// // The response is sent with these headers.
+/// let headers = [("Content-Type", CONTENT_TYPE), ("Content-Encoding", CONTENT_ENCODING)];
+///
+/// assert_eq!(body[..2], [0x1f, 0x8b]);
+/// assert!(body.len() < 2000);
+/// ```
+pub fn encode_to_vec<M: EncodeMetric>(registry: &Registry<M>) -> io::Result<Vec<u8>> {
+    encode(vec![], registry)
+}
+
+/// A writer compressing what is written to it with gzip.
+///
+/// [`GzipWriter::finish`] must be called to write the end of the compressed
+/// stream.
+#[derive(Debug)]
+pub struct GzipWriter<W: io::Write>(GzEncoder<W>);
+
+impl<W: io::Write> GzipWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self(GzEncoder::new(inner, Compression::default()))
+    }
+
+    /// Writes the end of the compressed stream, and returns the inner
+    /// writer.
+    pub fn finish(self) -> io::Result<W> {
+        let mut inner = self.0.finish()?;
+        inner.flush()?;
+
+        Ok(inner)
+    }
+}
+
+impl<W: io::Write> io::Write for GzipWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -0,0 +1,175 @@
+//! Incremental encoding of a registry, for push pipelines which accept
+//! partial updates of large registries whose series rarely change.
+//!
+//! The registry is encoded in the OpenMetrics text format, then samples
+//! whose value, timestamp and exemplar didn't change since the last encoding
+//! are skipped, along with the families left without samples. The output is
+//! thus OpenMetrics text too.
+
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use std::collections::HashMap;
+use std::io;
+use std::time::{Duration, Instant};
+
+/// An encoder which only encodes the samples which changed since its last
+/// encoding, and periodically encodes all of them.
+///
+/// Series which disappeared from the registry aren't reported, and are
+/// encoded again if they reappear.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::encoding::incremental::IncrementalEncoder;
+/// # use std::time::Duration;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Counter::<u64>::default();
+/// let errors = Counter::<u64>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("errors", "Number of errors", Box::new(errors.clone()));
+///
+/// let mut encoder = IncrementalEncoder::new(Duration::from_secs(300));
+/// let mut serialized = vec![];
+///
+/// assert!(encoder.encode(&mut serialized, &registry).unwrap());
+///
+/// requests.inc();
+/// serialized.clear();
+///
+/// assert!(!encoder.encode(&mut serialized, &registry).unwrap());
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct IncrementalEncoder {
+    resync_interval: Duration,
+    last_resync: Option<Instant>,
+    /// The rest of the last line of each sample, keyed by its name and
+    /// labels.
+    last: HashMap<String, String>,
+}
+
+impl IncrementalEncoder {
+    /// Creates an encoder which encodes all samples on its first encoding,
+    /// then once every `resync_interval`.
+    pub fn new(resync_interval: Duration) -> Self {
+        Self {
+            resync_interval,
+            last_resync: None,
+            last: HashMap::new(),
+        }
+    }
+
+    /// Encodes all samples on the next encoding.
+    pub fn resync(&mut self) {
+        self.last_resync = None;
+    }
+
+    /// Encodes the samples of the registry which changed since the last
+    /// encoding, or all of them if a resync is due, in the OpenMetrics text
+    /// format.
+    ///
+    /// Returns whether all samples were encoded.
+    pub fn encode<W, M>(&mut self, writer: &mut W, registry: &Registry<M>) -> io::Result<bool>
+    where
+        W: io::Write,
+        M: EncodeMetric,
+    {
+        let mut buf = vec![];
+        text::encode(&mut buf, registry)?;
+
+        let text = String::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let now = Instant::now();
+        let full = self
+            .last_resync
+            .is_none_or(|last_resync| now.duration_since(last_resync) >= self.resync_interval);
+
+        if full {
+            self.last_resync = Some(now);
+        }
+
+        writer.write_all(self.changes(&text, full).as_bytes())?;
+
+        Ok(full)
+    }
+
+    /// Returns the lines of `text` which changed since the last encoding,
+    /// or all of them if `full`, and remembers the current samples.
+    fn changes(&mut self, text: &str, full: bool) -> String {
+        let mut output = String::new();
+        let mut last = HashMap::with_capacity(self.last.len());
+        // The metadata of the current family, until one of its samples is
+        // written.
+        let mut metadata = String::new();
+        let mut in_samples = false;
+
+        for line in text.lines() {
+            if line == "# EOF" {
+                output.push_str(line);
+                output.push('\n');
+                continue;
+            }
+
+            if line.starts_with('#') {
+                if in_samples {
+                    metadata.clear();
+                    in_samples = false;
+                }
+
+                metadata.push_str(line);
+                metadata.push('\n');
+                continue;
+            }
+
+            in_samples = true;
+
+            let (series, rest) = split_sample(line);
+            let changed = self.last.get(series).map(String::as_str) != Some(rest);
+
+            if full || changed {
+                output.push_str(&metadata);
+                output.push_str(line);
+                output.push('\n');
+                metadata.clear();
+            }
+
+            last.insert(series.to_string(), rest.to_string());
+        }
+
+        self.last = last;
+        output
+    }
+}
+
+/// Splits a sample line between its name and labels, and its value,
+/// timestamp and exemplar.
+fn split_sample(line: &str) -> (&str, &str) {
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ' ' if !quoted => return line.split_at(i),
+            _ => {}
+        }
+    }
+
+    (line, "")
+}
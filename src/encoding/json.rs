@@ -0,0 +1,192 @@
+//! Encoding as a JSON document, e.g. for debug endpoints.
+//!
+//! The registry is encoded as an array of families in the order they were
+//! registered, each with its name, type, help, unit and samples:
+//!
+//! ```json
+//! [
+//!   {
+//!     "name": "latency",
+//!     "type": "histogram",
+//!     "help": "Request latency.",
+//!     "unit": "seconds",
+//!     "samples": [
+//!       {
+//!         "name": "latency_bucket",
+//!         "labels": {"le": "0.5"},
+//!         "value": 2,
+//!         "timestamp": 1700000000.25,
+//!         "exemplar": {"labels": {"trace_id": "3fa9"}, "value": 0.3}
+//!       }
+//!     ]
+//!   }
+//! ]
+//! ```
+//!
+//! `help`, `unit`, `timestamp` and `exemplar` are omitted when missing, and
+//! timestamps are in seconds. Values which JSON can't represent are encoded
+//! as the strings `"NaN"`, `"+Inf"` and `"-Inf"`.
+
+use super::parse::{self, Family, Sample};
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::fmt::Write as _;
+use std::io;
+
+/// The content type of JSON documents.
+pub const CONTENT_TYPE: &str = "application/json";
+
+/// Encodes the registry as a JSON document.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, family::Family},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::encoding::json::encode;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// requests.get_or_create(&vec![("path", "/")]).inc();
+///
+/// let mut serialized = vec![];
+///
+/// encode(&mut serialized, &registry).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         r#"[{"name":"requests","type":"counter","help":"Number of requests.","#,
+///         r#""samples":[{"name":"requests_total","labels":{"path":"/"},"value":1}]}]"#,
+///     ),
+/// );
+/// ```
+pub fn encode<W, M>(writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let text = parse::encode_text(registry)?;
+    let mut json = String::from("[");
+
+    for (i, family) in parse::parse(&text).iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+
+        encode_family(&mut json, family);
+    }
+
+    json.push(']');
+    writer.write_all(json.as_bytes())
+}
+
+fn encode_family(json: &mut String, family: &Family<'_>) {
+    json.push_str("{\"name\":");
+    encode_str(json, family.name);
+    json.push_str(",\"type\":");
+    encode_str(json, family.ty);
+
+    if let Some(help) = &family.help {
+        json.push_str(",\"help\":");
+        encode_str(json, help);
+    }
+
+    if let Some(unit) = family.unit {
+        json.push_str(",\"unit\":");
+        encode_str(json, unit);
+    }
+
+    json.push_str(",\"samples\":[");
+
+    for (i, sample) in family.samples.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+
+        encode_sample(json, sample);
+    }
+
+    json.push_str("]}");
+}
+
+fn encode_sample(json: &mut String, sample: &Sample<'_>) {
+    json.push_str("{\"name\":");
+    encode_str(json, sample.name);
+    json.push_str(",\"labels\":");
+    encode_labels(json, &sample.labels);
+    json.push_str(",\"value\":");
+    encode_f64(json, sample.value);
+
+    if let Some(timestamp) = sample.timestamp {
+        json.push_str(",\"timestamp\":");
+        encode_f64(json, timestamp);
+    }
+
+    if let Some(exemplar) = &sample.exemplar {
+        json.push_str(",\"exemplar\":{\"labels\":");
+        encode_labels(json, &exemplar.labels);
+        json.push_str(",\"value\":");
+        encode_f64(json, exemplar.value);
+
+        if let Some(timestamp) = exemplar.timestamp {
+            json.push_str(",\"timestamp\":");
+            encode_f64(json, timestamp);
+        }
+
+        json.push('}');
+    }
+
+    json.push('}');
+}
+
+fn encode_labels(json: &mut String, labels: &[(&str, String)]) {
+    json.push('{');
+
+    for (i, (name, value)) in labels.iter().enumerate() {
+        if i > 0 {
+            json.push(',');
+        }
+
+        encode_str(json, name);
+        json.push(':');
+        encode_str(json, value);
+    }
+
+    json.push('}');
+}
+
+fn encode_f64(json: &mut String, v: f64) {
+    if v.is_nan() {
+        json.push_str("\"NaN\"");
+    } else if v == f64::INFINITY {
+        json.push_str("\"+Inf\"");
+    } else if v == f64::NEG_INFINITY {
+        json.push_str("\"-Inf\"");
+    } else {
+        // Writing to a `String` can't fail.
+        let _ = write!(json, "{v}");
+    }
+}
+
+fn encode_str(json: &mut String, s: &str) {
+    json.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", u32::from(c));
+            }
+            c => json.push(c),
+        }
+    }
+
+    json.push('"');
+}
@@ -0,0 +1,149 @@
+//! Encoding in the InfluxDB line protocol, which InfluxDB and
+//! VictoriaMetrics ingest.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, so that the label sets of [`serde`](crate::serde) families are
+//! serialized by the same serializer as in the text format. The samples of
+//! each family are then grouped by label set, each group being written as
+//! one line:
+//!
+//! - the measurement is the name of the family;
+//! - labels are written as tags sorted by name, except those with an empty
+//!   value, and histogram buckets keep their `le` tag;
+//! - each sample is written as a float field named after its suffix, e.g.
+//!   `total`, `sum` or `bucket`, or `value` for samples without one;
+//! - infinite and NaN values, which the line protocol can't represent, are
+//!   skipped, as are exemplars;
+//! - timestamps are converted from seconds to nanoseconds.
+
+use super::parse::{self, Family};
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::fmt::Write as _;
+use std::io;
+
+/// The content type of the InfluxDB line protocol.
+pub const CONTENT_TYPE: &str = "text/plain; charset=utf-8";
+
+/// Encodes the registry in the InfluxDB line protocol.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, family::Family},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::encoding::line_protocol::encode;
+/// # use prometools::histogram::TimeHistogram;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+/// let latency = TimeHistogram::new([0.5].into_iter());
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("latency", "Request latency", Box::new(latency.clone()));
+///
+/// requests.get_or_create(&vec![("path", "/"), ("method", "GET")]).inc();
+/// latency.observe_secs(0.25);
+///
+/// let mut serialized = vec![];
+///
+/// encode(&mut serialized, &registry).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         "requests,method=GET,path=/ total=1\n",
+///         "latency sum=0.25,count=1\n",
+///         "latency,le=0.5 bucket=1\n",
+///         "latency,le=+Inf bucket=1\n",
+///     ),
+/// );
+/// ```
+pub fn encode<W, M>(writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let text = parse::encode_text(registry)?;
+    let mut lines = String::new();
+
+    for family in parse::parse(&text) {
+        encode_family(&mut lines, &family);
+    }
+
+    writer.write_all(lines.as_bytes())
+}
+
+fn encode_family(lines: &mut String, family: &Family<'_>) {
+    for series in parse::series(&family.samples, "") {
+        let mut tags: Vec<_> = series
+            .labels
+            .iter()
+            .filter(|(_name, value)| !value.is_empty())
+            .collect();
+        let mut fields = String::new();
+        let mut timestamp = None;
+
+        tags.sort_unstable_by_key(|(name, _value)| *name);
+
+        for sample in series.samples {
+            if !sample.value.is_finite() {
+                continue;
+            }
+
+            let field = match family.suffix(sample) {
+                Some("") | None => "value",
+                Some(suffix) => suffix.trim_start_matches('_'),
+            };
+
+            if !fields.is_empty() {
+                fields.push(',');
+            }
+
+            escape(&mut fields, field, ",= ");
+            // Writing to a `String` can't fail.
+            let _ = write!(fields, "={}", sample.value);
+            timestamp = timestamp.or(sample.timestamp);
+        }
+
+        if fields.is_empty() {
+            continue;
+        }
+
+        escape(lines, family.name, ", ");
+
+        for (name, value) in tags {
+            lines.push(',');
+            escape(lines, name, ",= ");
+            lines.push('=');
+            escape(lines, value, ",= ");
+        }
+
+        lines.push(' ');
+        lines.push_str(&fields);
+
+        if let Some(secs) = timestamp {
+            let _ = write!(lines, " {}", (secs * 1E9).round() as i64);
+        }
+
+        lines.push('\n');
+    }
+}
+
+/// Escapes `special` characters with a backslash, and replaces newlines,
+/// which can't be escaped, with spaces.
+fn escape(output: &mut String, s: &str, special: &str) {
+    for c in s.chars() {
+        if c == '\n' {
+            output.push(' ');
+            continue;
+        }
+
+        if special.contains(c) {
+            output.push('\\');
+        }
+
+        output.push(c);
+    }
+}
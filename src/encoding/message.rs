@@ -0,0 +1,82 @@
+//! A minimal protobuf writer, for the encodings which need one.
+
+use std::time::Duration;
+
+pub(crate) fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+/// A protobuf message being written.
+#[derive(Default)]
+pub(crate) struct Message(pub(crate) Vec<u8>);
+
+impl Message {
+    pub(crate) fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.0.push(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.0.push(v as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    pub(crate) fn uint64(&mut self, field: u32, v: u64) {
+        self.key(field, 0);
+        self.varint(v);
+    }
+
+    pub(crate) fn int64(&mut self, field: u32, v: i64) {
+        self.uint64(field, v as u64);
+    }
+
+    pub(crate) fn sint64(&mut self, field: u32, v: i64) {
+        self.uint64(field, zigzag(v));
+    }
+
+    pub(crate) fn double(&mut self, field: u32, v: f64) {
+        self.key(field, 1);
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    #[cfg_attr(not(feature = "otlp"), allow(dead_code))]
+    pub(crate) fn fixed64(&mut self, field: u32, v: u64) {
+        self.key(field, 1);
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn bytes(&mut self, field: u32, bytes: &[u8]) {
+        self.key(field, 2);
+        self.varint(bytes.len() as u64);
+        self.0.extend_from_slice(bytes);
+    }
+
+    pub(crate) fn string(&mut self, field: u32, s: &str) {
+        self.bytes(field, s.as_bytes());
+    }
+
+    pub(crate) fn message(&mut self, field: u32, message: &Message) {
+        self.bytes(field, &message.0);
+    }
+
+    /// Writes a `google.protobuf.Timestamp` of `secs` seconds since the
+    /// Unix epoch.
+    pub(crate) fn timestamp(&mut self, field: u32, secs: f64) {
+        let since = Duration::try_from_secs_f64(secs).unwrap_or_default();
+        let mut timestamp = Message::default();
+
+        timestamp.int64(1, since.as_secs() as i64);
+        timestamp.int64(2, i64::from(since.subsec_nanos()));
+        self.message(field, &timestamp);
+    }
+
+    /// Returns the message prefixed with its length.
+    pub(crate) fn delimited(&self) -> Vec<u8> {
+        let mut delimited = Message::default();
+        delimited.varint(self.0.len() as u64);
+        delimited.0.extend_from_slice(&self.0);
+        delimited.0
+    }
+}
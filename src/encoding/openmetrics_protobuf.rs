@@ -0,0 +1,292 @@
+//! Encoding in the OpenMetrics protobuf format, i.e. as a single
+//! `openmetrics.MetricSet` message.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then converted:
+//!
+//! - counters, histograms and their exemplars and `_created` samples are
+//!   kept as they are;
+//! - info metrics are exposed with their labels as info labels;
+//! - samples whose suffix isn't part of the type of their family, e.g. the
+//!   `_sum` and `_count` of a
+//!   [`SimpleSummary`](crate::nonstandard::SimpleSummary) or both counters of
+//!   a [`Ratio`](crate::nonstandard::Ratio), are described by a family named
+//!   after them, of the same type or `UNKNOWN` for histograms.
+
+use super::message::Message;
+use super::parse::{self, Exemplar, Family, Sample};
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::io;
+
+/// The content type of the OpenMetrics protobuf format.
+pub const CONTENT_TYPE: &str = "application/openmetrics-protobuf; version=1.0.0";
+
+const UNKNOWN: u64 = 0;
+const GAUGE: u64 = 1;
+const COUNTER: u64 = 2;
+const STATE_SET: u64 = 3;
+const INFO: u64 = 4;
+const HISTOGRAM: u64 = 5;
+
+/// Encodes the registry in the OpenMetrics protobuf format.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::encoding::openmetrics_protobuf::encode;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = <Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// requests.inc();
+///
+/// let mut serialized = vec![];
+///
+/// encode(&mut serialized, &registry).unwrap();
+///
+/// // The set starts with the key and length of its first family, then the
+/// // key and length of its name.
+/// assert_eq!(&serialized[4..12], b"requests");
+/// ```
+pub fn encode<W, M>(writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let text = parse::encode_text(registry)?;
+    let mut metric_set = Message::default();
+
+    for family in parse::parse(&text) {
+        for message in convert(&family) {
+            metric_set.message(1, &message);
+        }
+    }
+
+    writer.write_all(&metric_set.0)
+}
+
+/// Converts a family to `MetricFamily` messages.
+fn convert(family: &Family<'_>) -> Vec<Message> {
+    match family.ty {
+        "histogram" => histogram(family),
+        "stateset" => vec![state_set(family)],
+        ty => points(family, family.samples.iter(), ty),
+    }
+}
+
+fn histogram(family: &Family<'_>) -> Vec<Message> {
+    let (histogram, other): (Vec<_>, Vec<_>) = family.samples.iter().partition(|sample| {
+        matches!(
+            family.suffix(sample),
+            Some("_sum" | "_count" | "_bucket" | "_created"),
+        )
+    });
+
+    let mut metrics = vec![];
+
+    for series in parse::series(histogram, "le") {
+        let mut histogram = Message::default();
+        let mut timestamp = None;
+
+        for sample in series.samples {
+            timestamp = timestamp.or(sample.timestamp);
+
+            match family.suffix(sample) {
+                Some("_sum") => histogram.double(1, sample.value),
+                Some("_count") => histogram.uint64(3, sample.value as u64),
+                Some("_created") => histogram.timestamp(4, sample.value),
+                _ => {
+                    let mut bucket = Message::default();
+                    let upper_bound = sample.label("le").and_then(|le| le.parse().ok());
+
+                    bucket.uint64(1, sample.value as u64);
+                    bucket.double(2, upper_bound.unwrap_or(f64::INFINITY));
+
+                    if let Some(exemplar) = &sample.exemplar {
+                        bucket.message(3, &encode_exemplar(exemplar));
+                    }
+
+                    histogram.message(5, &bucket);
+                }
+            }
+        }
+
+        metrics.push(metric(series.labels.into_iter(), 4, &histogram, timestamp));
+    }
+
+    let mut messages = vec![metric_family(family, family.name, HISTOGRAM, &metrics)];
+    messages.extend(points(family, other.into_iter(), "unknown"));
+    messages
+}
+
+/// Converts a state set, whose states are the values of the label named
+/// after the family.
+fn state_set(family: &Family<'_>) -> Message {
+    let mut metrics = vec![];
+
+    for series in parse::series(&family.samples, family.name) {
+        let mut states = Message::default();
+        let mut timestamp = None;
+
+        for sample in series.samples {
+            let mut state = Message::default();
+
+            timestamp = timestamp.or(sample.timestamp);
+            state.uint64(1, u64::from(sample.value != 0.0));
+            state.string(2, sample.label(family.name).unwrap_or_default());
+            states.message(1, &state);
+        }
+
+        metrics.push(metric(series.labels.into_iter(), 5, &states, timestamp));
+    }
+
+    metric_family(family, family.name, STATE_SET, &metrics)
+}
+
+/// Converts samples of a family of type `ty` holding a single value, to
+/// families named after them.
+fn points<'s, 'a: 's>(
+    family: &Family<'a>,
+    samples: impl Iterator<Item = &'s Sample<'a>>,
+    ty: &str,
+) -> Vec<Message> {
+    let mut families: Vec<(&str, u64, Vec<Message>)> = vec![];
+
+    for sample in samples {
+        let mut labels = sample.labels.as_slice();
+        let mut value = Message::default();
+        let (name, ty, field) = match ty {
+            "counter" => {
+                if family.suffix(sample) == Some("_created") {
+                    continue;
+                }
+
+                let name = sample.name.strip_suffix("_total").unwrap_or(sample.name);
+                let created = family.samples.iter().find(|created| {
+                    created.name.strip_suffix("_created") == Some(name)
+                        && created.labels == sample.labels
+                });
+
+                value.double(1, sample.value);
+
+                if let Some(created) = created {
+                    value.timestamp(3, created.value);
+                }
+
+                if let Some(exemplar) = &sample.exemplar {
+                    value.message(4, &encode_exemplar(exemplar));
+                }
+
+                (name, COUNTER, 3)
+            }
+            "info" => {
+                for label in labels {
+                    value.message(1, &label_pair(label));
+                }
+
+                labels = &[];
+                let name = sample.name.strip_suffix("_info").unwrap_or(sample.name);
+
+                (name, INFO, 6)
+            }
+            "gauge" => {
+                value.double(1, sample.value);
+                (sample.name, GAUGE, 2)
+            }
+            _ => {
+                value.double(1, sample.value);
+                (sample.name, UNKNOWN, 1)
+            }
+        };
+
+        let metric = metric(labels.iter(), field, &value, sample.timestamp);
+
+        match families.iter_mut().find(|(n, t, _)| *n == name && *t == ty) {
+            Some((_, _, metrics)) => metrics.push(metric),
+            None => families.push((name, ty, vec![metric])),
+        }
+    }
+
+    families
+        .into_iter()
+        .map(|(name, ty, metrics)| metric_family(family, name, ty, &metrics))
+        .collect()
+}
+
+fn metric_family(family: &Family<'_>, name: &str, ty: u64, metrics: &[Message]) -> Message {
+    let mut message = Message::default();
+
+    message.string(1, name);
+    message.uint64(2, ty);
+
+    if let (Some(unit), true) = (family.unit, name == family.name) {
+        message.string(3, unit);
+    }
+
+    if let Some(help) = &family.help {
+        message.string(4, help);
+    }
+
+    for metric in metrics {
+        message.message(5, metric);
+    }
+
+    message
+}
+
+/// Returns a `Metric` message with the given labels and a single point,
+/// whose value is `value` in the field `field`.
+fn metric<'l>(
+    labels: impl Iterator<Item = &'l (&'l str, String)>,
+    field: u32,
+    value: &Message,
+    timestamp: Option<f64>,
+) -> Message {
+    let mut metric = Message::default();
+
+    for label in labels {
+        metric.message(1, &label_pair(label));
+    }
+
+    metric.message(2, &point(field, value, timestamp));
+    metric
+}
+
+fn point(field: u32, value: &Message, timestamp: Option<f64>) -> Message {
+    let mut point = Message::default();
+
+    point.message(field, value);
+
+    if let Some(secs) = timestamp {
+        point.timestamp(8, secs);
+    }
+
+    point
+}
+
+fn encode_exemplar(exemplar: &Exemplar<'_>) -> Message {
+    let mut message = Message::default();
+
+    message.double(1, exemplar.value);
+
+    if let Some(secs) = exemplar.timestamp {
+        message.timestamp(2, secs);
+    }
+
+    for label in &exemplar.labels {
+        message.message(3, &label_pair(label));
+    }
+
+    message
+}
+
+fn label_pair((name, value): &(&str, String)) -> Message {
+    let mut label = Message::default();
+
+    label.string(1, name);
+    label.string(2, value);
+    label
+}
@@ -0,0 +1,242 @@
+//! A parser for the OpenMetrics text format, which the encodings in other
+//! formats convert from.
+
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use std::io;
+
+/// Encodes the registry in the OpenMetrics text format.
+pub(crate) fn encode_text<M: EncodeMetric>(registry: &Registry<M>) -> io::Result<String> {
+    let mut buf = vec![];
+    text::encode(&mut buf, registry)?;
+
+    String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// A metric family, described by its metadata lines.
+#[derive(Debug, Default)]
+pub(crate) struct Family<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) help: Option<String>,
+    /// The declared type, `unknown` if there is none.
+    pub(crate) ty: &'a str,
+    pub(crate) unit: Option<&'a str>,
+    pub(crate) samples: Vec<Sample<'a>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Sample<'a> {
+    pub(crate) name: &'a str,
+    pub(crate) labels: Vec<(&'a str, String)>,
+    pub(crate) value: f64,
+    /// The timestamp, in seconds.
+    pub(crate) timestamp: Option<f64>,
+    pub(crate) exemplar: Option<Exemplar<'a>>,
+}
+
+#[derive(Debug)]
+pub(crate) struct Exemplar<'a> {
+    pub(crate) labels: Vec<(&'a str, String)>,
+    pub(crate) value: f64,
+    /// The timestamp, in seconds.
+    pub(crate) timestamp: Option<f64>,
+}
+
+impl<'a> Family<'a> {
+    /// Returns the suffix of the name of `sample`, if it is one of the
+    /// samples of the family.
+    pub(crate) fn suffix(&self, sample: &Sample<'a>) -> Option<&'a str> {
+        sample.name.strip_prefix(self.name)
+    }
+}
+
+impl<'a> Sample<'a> {
+    /// Returns the value of the label `name`.
+    pub(crate) fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _value)| *label == name)
+            .map(|(_label, value)| &**value)
+    }
+}
+
+/// Samples sharing the same label set, ignoring some labels.
+pub(crate) struct Series<'s, 'a> {
+    pub(crate) labels: Vec<&'s (&'a str, String)>,
+    pub(crate) samples: Vec<&'s Sample<'a>>,
+}
+
+/// Groups samples by label set, ignoring the label `ignored`, e.g. `le` to
+/// group the samples of each histogram.
+pub(crate) fn series<'s, 'a>(
+    samples: impl IntoIterator<Item = &'s Sample<'a>>,
+    ignored: &str,
+) -> Vec<Series<'s, 'a>> {
+    let mut series: Vec<Series<'s, 'a>> = vec![];
+
+    for sample in samples {
+        let labels: Vec<_> = sample
+            .labels
+            .iter()
+            .filter(|(l, _)| *l != ignored)
+            .collect();
+
+        match series.iter_mut().find(|series| series.labels == labels) {
+            Some(series) => series.samples.push(sample),
+            None => series.push(Series {
+                labels,
+                samples: vec![sample],
+            }),
+        }
+    }
+
+    series
+}
+
+/// Parses metrics in the OpenMetrics text format.
+///
+/// Lines which can't be parsed are skipped.
+pub(crate) fn parse(text: &str) -> Vec<Family<'_>> {
+    let mut families: Vec<Family<'_>> = vec![];
+
+    for line in text.lines() {
+        if let Some(rest) = line.strip_prefix("# ") {
+            let mut parts = rest.splitn(3, ' ');
+            let (Some(keyword), Some(name)) = (parts.next(), parts.next()) else {
+                continue;
+            };
+            let value = parts.next().unwrap_or("");
+
+            if !matches!(families.last(), Some(family) if family.name == name) {
+                families.push(Family {
+                    name,
+                    ty: "unknown",
+                    ..Family::default()
+                });
+            }
+
+            let Some(family) = families.last_mut() else {
+                continue;
+            };
+
+            match keyword {
+                "HELP" => family.help = Some(unescape(value)),
+                "TYPE" => family.ty = value,
+                "UNIT" => family.unit = Some(value),
+                _ => {}
+            }
+        } else if let Some(sample) = parse_sample(line) {
+            let belongs = matches!(
+                families.last(),
+                Some(family) if sample.name.starts_with(family.name),
+            );
+
+            if !belongs {
+                families.push(Family {
+                    name: sample.name,
+                    ty: "unknown",
+                    ..Family::default()
+                });
+            }
+
+            if let Some(family) = families.last_mut() {
+                family.samples.push(sample);
+            }
+        }
+    }
+
+    families
+}
+
+fn parse_sample(line: &str) -> Option<Sample<'_>> {
+    let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+    let (name, rest) = line.split_at(name_end);
+    let (labels, rest) = parse_labels(rest)?;
+    let (rest, exemplar) = match rest.split_once(" # ") {
+        Some((rest, exemplar)) => (rest, Some(parse_exemplar(exemplar)?)),
+        None => (rest, None),
+    };
+    let mut parts = rest.split_whitespace();
+    let value = parts.next()?.parse().ok()?;
+    let timestamp = parts.next().map(str::parse).transpose().ok()?;
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(Sample {
+        name,
+        labels,
+        value,
+        timestamp,
+        exemplar,
+    })
+}
+
+fn parse_exemplar(exemplar: &str) -> Option<Exemplar<'_>> {
+    let (labels, rest) = parse_labels(exemplar)?;
+    let mut parts = rest.split_whitespace();
+    let value = parts.next()?.parse().ok()?;
+    let timestamp = parts.next().map(str::parse).transpose().ok()?;
+
+    Some(Exemplar {
+        labels,
+        value,
+        timestamp,
+    })
+}
+
+/// Parses the label set at the start of `s`, if any, and returns the rest.
+pub(crate) fn parse_labels(s: &str) -> Option<(Vec<(&str, String)>, &str)> {
+    let Some(mut rest) = s.strip_prefix('{') else {
+        return Some((vec![], s));
+    };
+    let mut labels = vec![];
+
+    loop {
+        rest = rest.trim_start_matches(',');
+
+        if let Some(rest) = rest.strip_prefix('}') {
+            return Some((labels, rest));
+        }
+
+        let (name, after) = rest.split_once("=\"")?;
+        let mut value = String::new();
+        let mut chars = after.char_indices();
+
+        rest = loop {
+            match chars.next()? {
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => value.push('\n'),
+                    c => value.push(c),
+                },
+                (i, '"') => break &after[i + 1..],
+                (_, c) => value.push(c),
+            }
+        };
+
+        labels.push((name, value));
+    }
+}
+
+fn unescape(s: &str) -> String {
+    let mut unescaped = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('n')) => unescaped.push('\n'),
+            ('\\', Some(c @ ('\\' | '"'))) => unescaped.push(c),
+            (c, _) => {
+                unescaped.push(c);
+                continue;
+            }
+        }
+
+        chars.next();
+    }
+
+    unescaped
+}
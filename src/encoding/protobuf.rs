@@ -0,0 +1,265 @@
+//! Encoding in the Prometheus protobuf exposition format, i.e. as
+//! length-delimited `io.prometheus.client.MetricFamily` messages.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then converted:
+//!
+//! - samples of counters, gauges, info metrics, state sets and unknown
+//!   metrics are described by a family named after them, e.g. with their
+//!   `_total` or `_info` suffix, and `_created` samples of counters become
+//!   their created timestamp;
+//! - info metrics and state sets are exposed as gauges, and the `unknown`
+//!   type as `UNTYPED`;
+//! - histograms keep their name, with their `_sum`, `_count`, `_bucket` and
+//!   `_created` samples grouped by label set, and their other samples are
+//!   exposed as `UNTYPED` families;
+//! - units and `# EOF` are dropped, exemplars are kept.
+//!
+//! [`NativeHistogram`]s of the registry listed in a [`NativeHistograms`]
+//! passed to [`encode_with_native`] are encoded as native histograms rather
+//! than with classic buckets, which makes this the format to negotiate with
+//! scrapers supporting them.
+//!
+//! [`NativeHistogram`]: crate::histogram::NativeHistogram
+
+use super::message::Message;
+use super::parse::{self, Exemplar, Family, Sample};
+use crate::histogram::NativeHistograms;
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::io;
+
+/// The content type of the Prometheus protobuf exposition format.
+pub const CONTENT_TYPE: &str =
+    "application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited";
+
+const COUNTER: u64 = 0;
+const GAUGE: u64 = 1;
+const UNTYPED: u64 = 3;
+const HISTOGRAM: u64 = 4;
+
+/// Encodes the registry in the Prometheus protobuf exposition format.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::encoding::protobuf::encode;
+/// # use prometools::histogram::NativeHistogram;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = <Counter>::default();
+/// let latency = NativeHistogram::new(3);
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("latency", "Request latency", Box::new(latency.clone()));
+///
+/// requests.inc();
+/// latency.observe(0.25);
+///
+/// let mut serialized = vec![];
+///
+/// encode(&mut serialized, &registry).unwrap();
+///
+/// // The first message is the `requests_total` family, starting with its
+/// // length, then the key and length of its name.
+/// assert_eq!(&serialized[3..17], b"requests_total");
+/// ```
+pub fn encode<W, M>(writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    encode_with_native(writer, registry, &NativeHistograms::default())
+}
+
+/// Encodes the registry in the Prometheus protobuf exposition format, with
+/// the histograms listed in `natives` encoded as native histograms, see
+/// [`NativeHistograms`].
+pub fn encode_with_native<W, M>(
+    writer: &mut W,
+    registry: &Registry<M>,
+    natives: &NativeHistograms,
+) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let text = parse::encode_text(registry)?;
+
+    for family in parse::parse(&text) {
+        for message in convert(&family, natives) {
+            writer.write_all(&message.delimited())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Converts a family to `MetricFamily` messages, with the series listed in
+/// `natives` encoded as native histograms.
+fn convert(family: &Family<'_>, natives: &NativeHistograms) -> Vec<Message> {
+    if family.ty != "histogram" {
+        return by_name(family, family.samples.iter(), family.ty);
+    }
+
+    let (histogram, other): (Vec<_>, Vec<_>) = family.samples.iter().partition(|sample| {
+        matches!(
+            family.suffix(sample),
+            Some("_sum" | "_count" | "_bucket" | "_created"),
+        )
+    });
+
+    let mut metrics = vec![];
+
+    for series in parse::series(histogram, "le") {
+        let native = natives.find(family.name, &series.labels);
+        let mut histogram =
+            native.map_or_else(Message::default, |native| native.histogram_message());
+        let mut timestamp = None;
+
+        for sample in series.samples {
+            timestamp = timestamp.or(sample.timestamp);
+
+            match family.suffix(sample) {
+                Some("_created") => histogram.timestamp(15, sample.value),
+                // The native histogram replaces the classic samples.
+                _ if native.is_some() => {}
+                Some("_sum") => histogram.double(2, sample.value),
+                Some("_count") => histogram.uint64(1, sample.value as u64),
+                _ => {
+                    let mut bucket = Message::default();
+                    let upper_bound = sample.label("le").and_then(|le| le.parse().ok());
+
+                    bucket.uint64(1, sample.value as u64);
+                    bucket.double(2, upper_bound.unwrap_or(f64::INFINITY));
+
+                    if let Some(exemplar) = &sample.exemplar {
+                        bucket.message(3, &encode_exemplar(exemplar));
+                    }
+
+                    histogram.message(3, &bucket);
+                }
+            }
+        }
+
+        let mut metric = metric(series.labels.into_iter(), timestamp);
+        metric.message(7, &histogram);
+        metrics.push(metric);
+    }
+
+    let mut messages = vec![metric_family(family, family.name, HISTOGRAM, &metrics)];
+    messages.extend(by_name(family, other.into_iter(), "unknown"));
+    messages
+}
+
+/// Converts samples of a family of type `ty` to families named after them.
+fn by_name<'s, 'a: 's>(
+    family: &Family<'a>,
+    samples: impl Iterator<Item = &'s Sample<'a>>,
+    ty: &str,
+) -> Vec<Message> {
+    let (ty, value_field) = match ty {
+        "counter" => (COUNTER, 3),
+        "gauge" | "info" | "stateset" => (GAUGE, 2),
+        _ => (UNTYPED, 5),
+    };
+    let mut families: Vec<(&str, Vec<Message>)> = vec![];
+
+    for sample in samples {
+        let mut value = Message::default();
+
+        value.double(1, sample.value);
+
+        if ty == COUNTER {
+            if family.suffix(sample) == Some("_created") {
+                continue;
+            }
+
+            if let Some(exemplar) = &sample.exemplar {
+                value.message(2, &encode_exemplar(exemplar));
+            }
+
+            let created = family.samples.iter().find(|created| {
+                family.suffix(created) == Some("_created") && created.labels == sample.labels
+            });
+
+            if let Some(created) = created {
+                value.timestamp(3, created.value);
+            }
+        }
+
+        let mut metric = metric(sample.labels.iter(), sample.timestamp);
+        metric.message(value_field, &value);
+
+        match families.iter_mut().find(|(name, _)| *name == sample.name) {
+            Some((_, metrics)) => metrics.push(metric),
+            None => families.push((sample.name, vec![metric])),
+        }
+    }
+
+    families
+        .into_iter()
+        .map(|(name, metrics)| metric_family(family, name, ty, &metrics))
+        .collect()
+}
+
+fn metric_family(family: &Family<'_>, name: &str, ty: u64, metrics: &[Message]) -> Message {
+    let mut message = Message::default();
+
+    message.string(1, name);
+
+    if let Some(help) = &family.help {
+        message.string(2, help);
+    }
+
+    message.uint64(3, ty);
+
+    for metric in metrics {
+        message.message(4, metric);
+    }
+
+    message
+}
+
+/// Returns a `Metric` message with the given labels and timestamp, to which
+/// the value is then added.
+fn metric<'l>(
+    labels: impl Iterator<Item = &'l (&'l str, String)>,
+    timestamp: Option<f64>,
+) -> Message {
+    let mut metric = Message::default();
+
+    for label in labels {
+        metric.message(1, &label_pair(label));
+    }
+
+    if let Some(secs) = timestamp {
+        metric.int64(6, (secs * 1E3).round() as i64);
+    }
+
+    metric
+}
+
+fn encode_exemplar(exemplar: &Exemplar<'_>) -> Message {
+    let mut message = Message::default();
+
+    for label in &exemplar.labels {
+        message.message(1, &label_pair(label));
+    }
+
+    message.double(2, exemplar.value);
+
+    if let Some(secs) = exemplar.timestamp {
+        message.timestamp(3, secs);
+    }
+
+    message
+}
+
+fn label_pair((name, value): &(&str, String)) -> Message {
+    let mut label = Message::default();
+
+    label.string(1, name);
+    label.string(2, value);
+    label
+}
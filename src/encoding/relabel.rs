@@ -0,0 +1,319 @@
+//! Relabeling of the series exposed by a registry, e.g. to fix label naming
+//! mistakes at the exposition boundary without touching instrumentation.
+//!
+//! The registry is encoded in the OpenMetrics text format, then the rules
+//! are applied in order to the labels of each sample, so the output is
+//! OpenMetrics text too. Exemplar labels are left untouched.
+//!
+//! Values are replaced with the [`regex`] crate, whose syntax is close to
+//! the RE2 syntax used by Prometheus. Like in Prometheus, patterns are
+//! anchored to match whole values, and are matched in time linear in the
+//! length of the values.
+
+use super::parse;
+use prometheus_client::{
+    encoding::text::{self, EncodeMetric},
+    registry::Registry,
+};
+use regex::{Captures, Regex};
+use std::fmt::{self, Write};
+use std::io;
+
+/// Rules rewriting the labels of the series of a registry.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, family::Family},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::encoding::relabel::Relabel;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// requests
+///     .get_or_create(&vec![("statuscode", "404"), ("pod", "api-7d9f")])
+///     .inc();
+///
+/// let relabel = Relabel::default()
+///     .rename_label("statuscode", "status")
+///     .replace_value("status", r"(\d)\d\d", "${1}xx")
+///     .unwrap()
+///     .drop_label("pod")
+///     .add_label("service", "api");
+/// let mut serialized = vec![];
+///
+/// relabel.encode(&mut serialized, &registry).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total{status=\"4xx\",service=\"api\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Relabel {
+    rules: Vec<Rule>,
+}
+
+#[derive(Clone, Debug)]
+enum Rule {
+    Rename {
+        from: String,
+        to: String,
+    },
+    Drop(String),
+    Replace {
+        label: String,
+        regex: Regex,
+        replacement: String,
+    },
+    Add {
+        label: String,
+        value: String,
+    },
+}
+
+impl Relabel {
+    /// Renames the label `from` to `to`, replacing any label `to`.
+    pub fn rename_label(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.rules.push(Rule::Rename {
+            from: from.into(),
+            to: to.into(),
+        });
+        self
+    }
+
+    /// Removes the label `label`.
+    pub fn drop_label(mut self, label: impl Into<String>) -> Self {
+        self.rules.push(Rule::Drop(label.into()));
+        self
+    }
+
+    /// Replaces the value of the label `label` if `regex` matches all of it,
+    /// a missing label matching like an empty one.
+    ///
+    /// `$1` or `${1}` in `replacement` are replaced with the text of the
+    /// first capture group, and `$$` with `$`. The label is removed if the
+    /// replaced value is empty.
+    ///
+    /// Returns an error if `regex` is invalid.
+    pub fn replace_value(
+        mut self,
+        label: impl Into<String>,
+        regex: &str,
+        replacement: impl Into<String>,
+    ) -> Result<Self, Error> {
+        let compiled = Regex::new(&format!("^(?:{regex})$")).map_err(|reason| Error {
+            regex: regex.to_string(),
+            reason,
+        })?;
+
+        self.rules.push(Rule::Replace {
+            label: label.into(),
+            regex: compiled,
+            replacement: replacement.into(),
+        });
+        Ok(self)
+    }
+
+    /// Sets the label `label` to `value`, replacing its current value.
+    pub fn add_label(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.rules.push(Rule::Add {
+            label: label.into(),
+            value: value.into(),
+        });
+        self
+    }
+
+    /// Encodes the registry with its series relabeled, in the OpenMetrics
+    /// text format.
+    pub fn encode<W, M>(&self, writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+    where
+        W: io::Write,
+        M: EncodeMetric,
+    {
+        let mut buf = vec![];
+        text::encode(&mut buf, registry)?;
+
+        let text = String::from_utf8(buf)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        writer.write_all(self.relabel(&text).as_bytes())
+    }
+
+    /// Relabels metrics in the OpenMetrics text format.
+    ///
+    /// Lines which can't be parsed are kept as they are.
+    pub fn relabel(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+
+        for line in text.lines() {
+            let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+            let (name, rest) = line.split_at(name_end);
+
+            match parse::parse_labels(rest) {
+                Some((labels, rest)) if !line.starts_with('#') => {
+                    let labels = self.apply(labels);
+
+                    output.push_str(name);
+
+                    if !labels.is_empty() {
+                        output.push('{');
+
+                        for (i, (label, value)) in labels.iter().enumerate() {
+                            if i > 0 {
+                                output.push(',');
+                            }
+
+                            let _ = write!(output, "{label}=\"");
+                            escape(&mut output, value);
+                            output.push('"');
+                        }
+
+                        output.push('}');
+                    }
+
+                    output.push_str(rest);
+                }
+                _ => output.push_str(line),
+            }
+
+            output.push('\n');
+        }
+
+        output
+    }
+
+    fn apply(&self, labels: Vec<(&str, String)>) -> Vec<(String, String)> {
+        let mut labels: Vec<_> = labels
+            .into_iter()
+            .map(|(label, value)| (label.to_string(), value))
+            .collect();
+        let position = |labels: &[(String, String)], name: &str| {
+            labels.iter().position(|(label, _value)| label == name)
+        };
+
+        for rule in &self.rules {
+            match rule {
+                Rule::Rename { from, to } => {
+                    if from != to && position(&labels, from).is_some() {
+                        labels.retain(|(label, _value)| label != to);
+
+                        if let Some(i) = position(&labels, from) {
+                            labels[i].0.clone_from(to);
+                        }
+                    }
+                }
+                Rule::Drop(name) => labels.retain(|(label, _value)| label != name),
+                Rule::Replace {
+                    label,
+                    regex,
+                    replacement,
+                } => {
+                    let i = position(&labels, label);
+                    let value = i.map_or("", |i| &labels[i].1);
+                    let Some(captures) = regex.captures(value) else {
+                        continue;
+                    };
+                    let value = expand(replacement, &captures);
+
+                    match (i, value.is_empty()) {
+                        (Some(i), true) => drop(labels.remove(i)),
+                        (Some(i), false) => labels[i].1 = value,
+                        (None, true) => {}
+                        (None, false) => labels.push((label.clone(), value)),
+                    }
+                }
+                Rule::Add { label, value } => match position(&labels, label) {
+                    Some(i) => labels[i].1.clone_from(value),
+                    None => labels.push((label.clone(), value.clone())),
+                },
+            }
+        }
+
+        labels
+    }
+}
+
+/// An error returned by [`Relabel::replace_value`] for an invalid regex.
+#[derive(Clone, Debug)]
+pub struct Error {
+    regex: String,
+    reason: regex::Error,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid relabeling regex {:?}: {}",
+            self.regex, self.reason
+        )
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.reason)
+    }
+}
+
+/// Replaces `$n`, `${n}` and `$$` in `replacement`, unknown groups being
+/// replaced with nothing.
+fn expand(replacement: &str, captures: &Captures<'_>) -> String {
+    let mut expanded = String::new();
+    let mut rest = replacement;
+
+    while let Some(i) = rest.find('$') {
+        expanded.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+
+        if let Some(after) = rest.strip_prefix('$') {
+            expanded.push('$');
+            rest = after;
+            continue;
+        }
+
+        let (group, after) = match rest.strip_prefix('{').and_then(|s| s.split_once('}')) {
+            Some((group, after)) => (group, after),
+            None => {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(rest.len());
+
+                rest.split_at(end)
+            }
+        };
+
+        match group.parse::<usize>() {
+            Ok(group) => expanded.push_str(captures.get(group).map_or("", |m| m.as_str())),
+            Err(_) if group.is_empty() => expanded.push('$'),
+            Err(_) => {}
+        }
+
+        rest = after;
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+fn escape(output: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '\\' => output.push_str("\\\\"),
+            '"' => output.push_str("\\\""),
+            '\n' => output.push_str("\\n"),
+            c => output.push(c),
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Encoding in the classic Prometheus text format, version 0.0.4, for
+//! scrapers which don't support OpenMetrics.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then converted:
+//!
+//! - counters are described with the name of their samples, e.g. with their
+//!   `_total` suffix, and info metrics are exposed as gauges with their
+//!   `_info` suffix;
+//! - the `unknown` type is declared as `untyped`;
+//! - `_created` samples, units, exemplars and `# EOF` are dropped;
+//! - timestamps are converted from seconds to milliseconds.
+
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::io;
+
+/// The content type of the classic Prometheus text format.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Encodes the registry in the classic Prometheus text format.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, info::Info},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::encoding::text_legacy::encode;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = <Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("build", "Build information", Box::new(Info::new(vec![("version", "1.0")])));
+///
+/// requests.inc();
+///
+/// let mut serialized = vec![];
+///
+/// encode(&mut serialized, &registry).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     concat!(
+///         "# HELP requests_total Number of requests.\n",
+///         "# TYPE requests_total counter\n",
+///         "requests_total 1\n",
+///         "# HELP build_info Build information.\n",
+///         "# TYPE build_info gauge\n",
+///         "build_info{version=\"1.0\"} 1\n",
+///     ),
+/// );
+/// ```
+pub fn encode<W, M>(writer: &mut W, registry: &Registry<M>) -> io::Result<()>
+where
+    W: io::Write,
+    M: EncodeMetric,
+{
+    let text = super::parse::encode_text(registry)?;
+
+    writer.write_all(convert(&text).as_bytes())
+}
+
+/// Converts metrics in the OpenMetrics text format to the classic Prometheus
+/// text format.
+///
+/// Lines which can't be parsed are kept as they are.
+pub fn convert(text: &str) -> String {
+    let mut converter = Converter::default();
+
+    for line in text.lines() {
+        converter.convert_line(line);
+    }
+
+    converter.flush();
+    converter.output
+}
+
+#[derive(Default)]
+struct Converter<'a> {
+    output: String,
+    family: Option<Family<'a>>,
+}
+
+#[derive(Default)]
+struct Family<'a> {
+    name: &'a str,
+    help: Option<&'a str>,
+    ty: Option<&'a str>,
+    samples: Vec<String>,
+    /// The name of the first sample, which counters and info metrics are
+    /// described with.
+    sample_name: Option<&'a str>,
+}
+
+impl<'a> Converter<'a> {
+    fn convert_line(&mut self, line: &'a str) {
+        if line == "# EOF" {
+            return;
+        }
+
+        let Some(rest) = line.strip_prefix("# ") else {
+            return self.convert_sample(line);
+        };
+
+        let mut parts = rest.splitn(3, ' ');
+        let (Some(keyword), Some(name)) = (parts.next(), parts.next()) else {
+            return self.push_sample(line.to_owned());
+        };
+        let value = parts.next().unwrap_or("");
+
+        if !matches!(&self.family, Some(family) if family.name == name) {
+            self.flush();
+            self.family = Some(Family {
+                name,
+                ..Family::default()
+            });
+        }
+
+        let Some(family) = &mut self.family else {
+            return;
+        };
+
+        match keyword {
+            "HELP" => family.help = Some(value),
+            "TYPE" => family.ty = Some(value),
+            "UNIT" => {}
+            _ => family.samples.push(line.to_owned()),
+        }
+    }
+
+    fn convert_sample(&mut self, line: &'a str) {
+        let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+        let name = &line[..name_end];
+        let Some(labels_end) = labels_end(line, name_end) else {
+            return self.push_sample(line.to_owned());
+        };
+        let (series, rest) = line.split_at(labels_end);
+
+        // Exemplars are the only part of a sample which can contain ` # `
+        // after its labels.
+        let rest = rest
+            .split_once(" # ")
+            .map_or(rest, |(rest, _exemplar)| rest);
+        let mut parts = rest.split_whitespace();
+        let Some(value) = parts.next() else {
+            return self.push_sample(line.to_owned());
+        };
+
+        if let Some(family) = &mut self.family {
+            let skip = name
+                .strip_prefix(family.name)
+                .is_some_and(|suffix| suffix == "_created")
+                && matches!(family.ty, Some("counter" | "histogram" | "summary"));
+
+            if skip {
+                return;
+            }
+
+            family.sample_name.get_or_insert(name);
+        }
+
+        let mut sample = format!("{series} {value}");
+
+        if let Some(timestamp) = parts.next() {
+            match timestamp.parse::<f64>() {
+                Ok(secs) => sample.push_str(&format!(" {}", (secs * 1E3).round() as i64)),
+                Err(_) => return self.push_sample(line.to_owned()),
+            }
+        }
+
+        self.push_sample(sample);
+    }
+
+    fn push_sample(&mut self, sample: String) {
+        match &mut self.family {
+            Some(family) => family.samples.push(sample),
+            None => {
+                self.output.push_str(&sample);
+                self.output.push('\n');
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let Some(family) = self.family.take() else {
+            return;
+        };
+
+        let (name, ty) = match family.ty {
+            Some("counter") => (family.sample_name.unwrap_or(family.name), "counter"),
+            Some("info") => (family.sample_name.unwrap_or(family.name), "gauge"),
+            Some("stateset") => (family.name, "gauge"),
+            Some("unknown") | None => (family.name, "untyped"),
+            Some(ty) => (family.name, ty),
+        };
+
+        if let Some(help) = family.help {
+            self.output.push_str(&format!("# HELP {name} {help}\n"));
+        }
+
+        self.output.push_str(&format!("# TYPE {name} {ty}\n"));
+
+        for sample in family.samples {
+            self.output.push_str(&sample);
+            self.output.push('\n');
+        }
+    }
+}
+
+/// Returns the index of the end of the label set of a sample, or of its name
+/// if it has no label set.
+fn labels_end(line: &str, name_end: usize) -> Option<usize> {
+    if !line[name_end..].starts_with('{') {
+        return Some(name_end);
+    }
+
+    let mut chars = line[name_end..].char_indices();
+    let mut quoted = false;
+
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quoted => {
+                chars.next()?;
+            }
+            '"' => quoted = !quoted,
+            '}' if !quoted => return Some(name_end + i + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
@@ -0,0 +1,422 @@
+//! Helpers for [`Gauge`]s, and metrics exposed as gauges.
+
+use prometheus_client::{
+    encoding::text::{Encode, EncodeMetric, Encoder},
+    metrics::{gauge::Gauge, MetricType, TypedMetric},
+};
+use std::io;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Extension methods of [`Gauge`].
+pub trait GaugeExt {
+    type Value;
+
+    /// Sets the gauge to the current Unix time in seconds, e.g. for the last
+    /// time a job succeeded, and returns the previous value.
+    ///
+    /// Gauges of `u64` are set to whole seconds.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::gauge::Gauge;
+    /// # use prometools::gauge::GaugeExt;
+    /// # use std::sync::atomic::AtomicU64;
+    /// # use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    /// #
+    /// let last_success = Gauge::<f64, AtomicU64>::default();
+    ///
+    /// last_success.set_to_current_time();
+    ///
+    /// let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+    ///
+    /// assert!(now.as_secs_f64() - last_success.get() < 1.0);
+    /// ```
+    fn set_to_current_time(&self) -> Self::Value;
+}
+
+impl GaugeExt for Gauge<f64, AtomicU64> {
+    type Value = f64;
+
+    fn set_to_current_time(&self) -> f64 {
+        self.set(unix_time().as_secs_f64())
+    }
+}
+
+impl GaugeExt for Gauge<u64, AtomicU64> {
+    type Value = u64;
+
+    fn set_to_current_time(&self) -> u64 {
+        self.set(unix_time().as_secs())
+    }
+}
+
+fn unix_time() -> Duration {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+}
+
+/// A counter which can go down as well as up, like the `UpDownCounter` of
+/// OpenTelemetry, exposed as a gauge.
+///
+/// It holds an `i64` by default, or an `f64` with `UpDownCounter<f64,
+/// AtomicU64>`.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::gauge::UpDownCounter;
+/// # use std::sync::atomic::AtomicU64;
+/// #
+/// let mut registry = <Registry>::default();
+/// let connections = <UpDownCounter>::default();
+/// let balance = UpDownCounter::<f64, AtomicU64>::default();
+///
+/// registry.register("connections", "Open connections", Box::new(connections.clone()));
+/// registry.register("balance", "Account balance", Box::new(balance.clone()));
+///
+/// connections.inc();
+/// connections.sub(3);
+/// balance.add(2.5);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP connections Open connections.\n",
+///         "# TYPE connections gauge\n",
+///         "connections -2\n",
+///         "# HELP balance Account balance.\n",
+///         "# TYPE balance gauge\n",
+///         "balance 2.5\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct UpDownCounter<N = i64, A = AtomicI64> {
+    value: Arc<A>,
+    phantom: PhantomData<N>,
+}
+
+/// The atomic storage of the value of an [`UpDownCounter`].
+pub trait UpDownAtomic<N> {
+    fn add(&self, v: N) -> N;
+
+    fn sub(&self, v: N) -> N;
+
+    fn get(&self) -> N;
+}
+
+impl UpDownAtomic<i64> for AtomicI64 {
+    fn add(&self, v: i64) -> i64 {
+        self.fetch_add(v, Ordering::Relaxed)
+    }
+
+    fn sub(&self, v: i64) -> i64 {
+        self.fetch_sub(v, Ordering::Relaxed)
+    }
+
+    fn get(&self) -> i64 {
+        self.load(Ordering::Relaxed)
+    }
+}
+
+impl UpDownAtomic<f64> for AtomicU64 {
+    fn add(&self, v: f64) -> f64 {
+        let previous = self
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + v).to_bits())
+            })
+            .unwrap_or_else(|bits| bits);
+
+        f64::from_bits(previous)
+    }
+
+    fn sub(&self, v: f64) -> f64 {
+        self.add(-v)
+    }
+
+    fn get(&self) -> f64 {
+        f64::from_bits(self.load(Ordering::Relaxed))
+    }
+}
+
+impl<N, A> UpDownCounter<N, A>
+where
+    A: UpDownAtomic<N>,
+{
+    /// Adds `v` to the counter, and returns the previous value.
+    pub fn add(&self, v: N) -> N {
+        self.value.add(v)
+    }
+
+    /// Subtracts `v` from the counter, and returns the previous value.
+    pub fn sub(&self, v: N) -> N {
+        self.value.sub(v)
+    }
+
+    /// Adds one to the counter, and returns the previous value.
+    pub fn inc(&self) -> N
+    where
+        N: From<u8>,
+    {
+        self.add(N::from(1))
+    }
+
+    /// Subtracts one from the counter, and returns the previous value.
+    pub fn dec(&self) -> N
+    where
+        N: From<u8>,
+    {
+        self.sub(N::from(1))
+    }
+
+    pub fn get(&self) -> N {
+        self.value.get()
+    }
+}
+
+impl<N, A> Clone for UpDownCounter<N, A> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<N, A: Default> Default for UpDownCounter<N, A> {
+    fn default() -> Self {
+        Self {
+            value: Arc::default(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<N, A> TypedMetric for UpDownCounter<N, A> {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl EncodeMetric for UpDownCounter<i64, AtomicI64> {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(Signed(self.get()))?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl EncodeMetric for UpDownCounter<f64, AtomicU64> {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(self.get())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A gauge holding the value of an enum, e.g. the state of a state machine,
+/// so that the mapping between states and numbers lives in code.
+///
+/// [`EnumGauge::state_set`] also exposes one sample per state, set to 1 for
+/// the current state and 0 for the others, like an OpenMetrics state set.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::gauge::EnumGauge;
+/// #
+/// #[derive(Clone, Copy)]
+/// enum State {
+///     Idle = 0,
+///     Running = 1,
+///     Failed = -1,
+/// }
+///
+/// impl From<State> for i64 {
+///     fn from(state: State) -> i64 {
+///         state as i64
+///     }
+/// }
+///
+/// let mut registry = <Registry>::default();
+/// let state = EnumGauge::new(State::Idle);
+/// let states = state.state_set(
+///     "job_state",
+///     [
+///         (State::Idle, "idle"),
+///         (State::Running, "running"),
+///         (State::Failed, "failed"),
+///     ],
+/// );
+///
+/// registry.register("job", "State of the job", Box::new(state.clone()));
+/// registry.register("job_state", "States of the job", Box::new(states));
+///
+/// state.set(State::Failed);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP job State of the job.\n",
+///         "# TYPE job gauge\n",
+///         "job -1\n",
+///         "# HELP job_state States of the job.\n",
+///         "# TYPE job_state gauge\n",
+///         "job_state{job_state=\"idle\"} 0\n",
+///         "job_state{job_state=\"running\"} 0\n",
+///         "job_state{job_state=\"failed\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct EnumGauge<E> {
+    value: Arc<AtomicI64>,
+    phantom: PhantomData<fn(E)>,
+}
+
+impl<E> EnumGauge<E>
+where
+    E: Into<i64>,
+{
+    pub fn new(value: E) -> Self {
+        Self {
+            value: Arc::new(AtomicI64::new(value.into())),
+            phantom: PhantomData,
+        }
+    }
+
+    pub fn set(&self, value: E) {
+        self.value.store(value.into(), Ordering::Relaxed);
+    }
+
+    /// Returns the number of the current value.
+    pub fn get(&self) -> i64 {
+        self.value.load(Ordering::Relaxed)
+    }
+
+    /// Returns a metric exposing whether each of the given states is the
+    /// current value, as a sample with a `label` label named after the
+    /// state.
+    ///
+    /// prometheus-client can't declare the `stateset` type, so it is declared
+    /// as a gauge, and OpenMetrics requires `label` to be the name of the
+    /// metric.
+    pub fn state_set(
+        &self,
+        label: &'static str,
+        states: impl IntoIterator<Item = (E, &'static str)>,
+    ) -> EnumStateSet {
+        EnumStateSet {
+            value: self.value.clone(),
+            label,
+            states: states
+                .into_iter()
+                .map(|(state, name)| (state.into(), name))
+                .collect(),
+        }
+    }
+}
+
+impl<E> Clone for EnumGauge<E> {
+    fn clone(&self) -> Self {
+        Self {
+            value: self.value.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E> TypedMetric for EnumGauge<E> {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl<E> EncodeMetric for EnumGauge<E> {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(Signed(self.value.load(Ordering::Relaxed)))?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// The states of an [`EnumGauge`], returned by [`EnumGauge::state_set`].
+#[derive(Clone, Debug)]
+pub struct EnumStateSet {
+    value: Arc<AtomicI64>,
+    label: &'static str,
+    states: Vec<(i64, &'static str)>,
+}
+
+impl TypedMetric for EnumStateSet {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl EncodeMetric for EnumStateSet {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let value = self.value.load(Ordering::Relaxed);
+
+        for (state, name) in &self.states {
+            let label_set: &[_] = &[(self.label, *name)];
+
+            encoder
+                .with_label_set(&label_set)
+                .no_suffix()?
+                .no_bucket()?
+                .encode_value(u32::from(*state == value))?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// prometheus-client can't encode signed integers.
+struct Signed(i64);
+
+impl Encode for Signed {
+    fn encode(&self, writer: &mut dyn io::Write) -> Result<(), io::Error> {
+        write!(writer, "{}", self.0)
+    }
+}
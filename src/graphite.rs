@@ -0,0 +1,234 @@
+//! Pushing metrics to Graphite, with its plaintext protocol.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then each sample is written as a line with its path, value
+//! and timestamp in seconds. Its path is made of its name and labels,
+//! flattened following a [`LabelScheme`]. Samples without a timestamp are
+//! timestamped with the time of the push, and infinite and NaN values are
+//! skipped.
+
+use crate::encoding::parse::{self, Sample};
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Timeout used by [`Graphite::new`] to connect and send.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How labels are flattened in the path of a sample.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LabelScheme {
+    /// Appends the name and value of each label, e.g.
+    /// `requests_total.method.GET`, like the Java client does.
+    #[default]
+    NamesAndValues,
+    /// Appends the value of each label, e.g. `requests_total.GET`.
+    Values,
+    /// Appends each label as a Graphite tag, e.g.
+    /// `requests_total;method=GET`.
+    Tags,
+}
+
+/// A client of a Graphite server.
+///
+/// Characters other than ASCII letters, digits, `_` and `-` are replaced by
+/// `_` in label names and values, so that they don't split the path.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     metrics::{counter::Counter, family::Family},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::graphite::{Graphite, LabelScheme};
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// requests.get_or_create(&vec![("path", "/api")]).inc();
+///
+/// let graphite = Graphite::new("localhost:2003").with_prefix("web01");
+/// let now = UNIX_EPOCH + Duration::from_secs(1700000000);
+/// let mut serialized = vec![];
+///
+/// graphite.encode(&mut serialized, &registry, now).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     "web01.requests_total.path._api 1 1700000000\n",
+/// );
+///
+/// let graphite = graphite.with_label_scheme(LabelScheme::Tags);
+/// let mut serialized = vec![];
+///
+/// graphite.encode(&mut serialized, &registry, now).unwrap();
+///
+/// assert_eq!(
+///     String::from_utf8(serialized).unwrap(),
+///     "web01.requests_total;path=_api 1 1700000000\n",
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Graphite {
+    addr: String,
+    prefix: Option<String>,
+    label_scheme: LabelScheme,
+    timeout: Duration,
+}
+
+impl Graphite {
+    /// Creates a client pushing to the given address, e.g.
+    /// `graphite:2003`, with a timeout of 30 seconds.
+    pub fn new(addr: impl Into<String>) -> Self {
+        Self {
+            addr: addr.into(),
+            prefix: None,
+            label_scheme: LabelScheme::default(),
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Prefixes the path of all samples, e.g. with the name of the host.
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_label_scheme(mut self, label_scheme: LabelScheme) -> Self {
+        self.label_scheme = label_scheme;
+        self
+    }
+
+    /// Sets the timeout to connect and send.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Pushes the samples of the registry.
+    pub fn push<M: EncodeMetric>(&self, registry: &Registry<M>) -> io::Result<()> {
+        let mut lines = vec![];
+        self.encode(&mut lines, registry, SystemTime::now())?;
+
+        let addr = self.addr.to_socket_addrs()?.next().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "no address to connect to")
+        })?;
+        let mut stream = TcpStream::connect_timeout(&addr, self.timeout)?;
+
+        stream.set_write_timeout(Some(self.timeout))?;
+        stream.write_all(&lines)
+    }
+
+    /// Pushes the samples of the registry every `interval` from a new
+    /// thread, until the returned task is dropped.
+    ///
+    /// Failed pushes are reported to `on_error` and not retried.
+    pub fn spawn<M>(
+        self,
+        registry: Arc<Registry<M>>,
+        interval: Duration,
+        mut on_error: impl FnMut(io::Error) + Send + 'static,
+    ) -> GraphiteTask
+    where
+        M: EncodeMetric + Send + Sync + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(err) = self.push(&registry) {
+                    on_error(err);
+                }
+            }
+        });
+
+        GraphiteTask { _stop: stop }
+    }
+
+    /// Encodes the samples of the registry in the Graphite plaintext
+    /// protocol, timestamping samples without a timestamp with `now`.
+    pub fn encode<W, M>(
+        &self,
+        writer: &mut W,
+        registry: &Registry<M>,
+        now: SystemTime,
+    ) -> io::Result<()>
+    where
+        W: io::Write,
+        M: EncodeMetric,
+    {
+        let text = parse::encode_text(registry)?;
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64();
+        let mut lines = String::new();
+
+        for family in parse::parse(&text) {
+            for sample in family.samples.iter().filter(|s| s.value.is_finite()) {
+                self.encode_path(&mut lines, sample);
+
+                let timestamp = sample.timestamp.unwrap_or(now).floor() as i64;
+
+                // Writing to a `String` can't fail.
+                let _ = writeln!(lines, " {} {timestamp}", sample.value);
+            }
+        }
+
+        writer.write_all(lines.as_bytes())
+    }
+
+    fn encode_path(&self, lines: &mut String, sample: &Sample<'_>) {
+        if let Some(prefix) = &self.prefix {
+            lines.push_str(prefix);
+            lines.push('.');
+        }
+
+        lines.push_str(sample.name);
+
+        for (name, value) in &sample.labels {
+            match self.label_scheme {
+                LabelScheme::NamesAndValues => {
+                    lines.push('.');
+                    sanitize(lines, name);
+                    lines.push('.');
+                    sanitize(lines, value);
+                }
+                LabelScheme::Values => {
+                    lines.push('.');
+                    sanitize(lines, value);
+                }
+                LabelScheme::Tags => {
+                    lines.push(';');
+                    sanitize(lines, name);
+                    lines.push('=');
+                    sanitize(lines, value);
+                }
+            }
+        }
+    }
+}
+
+/// A task pushing metrics periodically, see [`Graphite::spawn`].
+///
+/// The task stops when dropped, without waiting for a push in progress.
+#[derive(Debug)]
+pub struct GraphiteTask {
+    _stop: mpsc::Sender<()>,
+}
+
+fn sanitize(output: &mut String, s: &str) {
+    output.extend(s.chars().map(|c| match c {
+        'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+        _ => '_',
+    }));
+}
@@ -2,50 +2,184 @@
 //!
 //! This is based on the implementation for [`prometheus_client::metrics::histogram::Histogram`],
 //! with several changes made to eliminate the need for locks.
+//!
+//! [`NativeHistogram`] is a lock-free histogram with exponential buckets,
+//! exposed as a Prometheus native histogram. [`ShardedTimeHistogram`] is a
+//! variant of [`TimeHistogram`] for histograms observed by many threads at
+//! once, and [`RollingTimeHistogram`] also keeps the distribution of recent
+//! observations. [`FixedTimeHistogram`] stores a fixed number of buckets
+//! inline, and can be used in a `static`. With the `hdr` feature,
+//! [`HdrTimeHistogram`] records observations with a bounded relative error,
+//! and computes quantiles in-process. [`AdaptiveTimeHistogram`] picks its
+//! own bucket bounds after a warm-up.
 
-use std::time::{Duration, Instant};
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant, SystemTime};
 
 use prometheus_client::encoding::text::{Encode, EncodeMetric, Encoder};
-use prometheus_client::metrics::exemplar::Exemplar;
+use prometheus_client::metrics::counter::Counter;
 use prometheus_client::metrics::{MetricType, TypedMetric};
-use std::collections::HashMap;
+
+use self::exemplar::{BucketExemplar, ExemplarSlot};
+use crate::created;
+use std::io;
 use std::iter::once;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+
+mod adaptive;
+mod clock;
+mod exemplar;
+mod fixed;
+mod future;
+#[cfg(feature = "hdr")]
+mod hdr;
+mod native;
+mod rolling;
+mod sharded;
+mod tracker;
+
+pub use self::adaptive::AdaptiveTimeHistogram;
+pub use self::clock::{Clock, CoarseClock, ManualClock, MonotonicClock, TimeSource};
+pub use self::exemplar::ExemplarSampling;
+pub use self::fixed::FixedTimeHistogram;
+pub use self::future::{InstrumentedFuture, PollTimedFuture};
+#[cfg(feature = "hdr")]
+pub use self::hdr::HdrTimeHistogram;
+pub use self::native::{NativeHistogram, NativeHistograms};
+pub use self::rolling::{QuantileGauge, RollingTimeHistogram};
+pub use self::sharded::ShardedTimeHistogram;
+pub use self::tracker::{RequestGuard, RequestTracker};
 
 /// A faster, lock-free histogram for tracking time.
 #[derive(Debug)]
 pub struct TimeHistogram {
     inner: Arc<Inner>,
+    time_source: TimeSource,
+    expose_created: bool,
+    unit: TimeUnit,
+    drop_policy: Option<Arc<DropPolicy>>,
+}
+
+/// The unit in which a [`TimeHistogram`] is encoded, see
+/// [`TimeHistogram::with_unit`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimeUnit {
+    #[default]
+    Seconds,
+    Milliseconds,
+    Microseconds,
+}
+
+impl TimeUnit {
+    /// Converts a number of seconds to this unit.
+    pub(crate) fn scale_secs(self, secs: f64) -> f64 {
+        match self {
+            Self::Seconds => secs,
+            Self::Milliseconds => secs * 1E3,
+            Self::Microseconds => secs * 1E6,
+        }
+    }
+}
+
+/// What a [`HistogramTimer`] does when it goes out of scope without being
+/// stopped, see [`HistogramTimer::on_drop`].
+#[derive(Clone, Debug, Default)]
+pub enum DropPolicy {
+    /// Records the elapsed time in the histogram of the timer.
+    #[default]
+    Record,
+    /// Discards the elapsed time, e.g. so that work cancelled at a timeout
+    /// doesn't pollute latency data.
+    Discard,
+    /// Records the elapsed time in another histogram, e.g. one dedicated to
+    /// cancelled work.
+    RecordTo(TimeHistogram),
 }
 
 /// Timer to measure and record the duration of an event.
 ///
 /// This timer can be stopped and observed at most once, either automatically
 /// (when it goes out of scope) or manually. Alternatively, it can be manually
-/// stopped and discarded in order to not record its value.
-pub struct HistogramTimer {
+/// stopped and discarded in order to not record its value. What happens when
+/// it goes out of scope can be changed with [`HistogramTimer::on_drop`].
+///
+/// With the `tracing` feature, a timer can be attached to a span with
+/// `tracing::SpanExt::attach_timer`, to be recorded when the span closes.
+pub struct HistogramTimer<C: Clock = TimeSource> {
     histogram: TimeHistogram,
+    clock: C,
     observed: bool,
     start: Option<Instant>,
     accumulated: Duration,
+    drop_policy: DropPolicy,
+    /// Other histograms in which the timer records, see
+    /// [`HistogramTimer::also_record`].
+    also: Vec<TimeHistogram>,
 }
 
 #[derive(Debug)]
 struct Inner {
-    sum: AtomicU64,
+    sum: NanosSum,
     count: AtomicU64,
-    buckets: Vec<(f64, AtomicU64)>,
+    min: AtomicU64,
+    max: AtomicU64,
+    /// Nanoseconds since the Unix epoch when the histogram was created or
+    /// last reset.
+    created: AtomicU64,
+    /// The upper bounds of the buckets, ending with `f64::MAX`, which can
+    /// be shared by many histograms, see [`TimeHistogram::with_shared_buckets`].
+    upper_bounds: Arc<[f64]>,
+    buckets: Box<[AtomicU64]>,
+    exemplars: Box<[ExemplarSlot]>,
+    thresholds: Vec<Threshold>,
+    /// The largest value observed as is, in nanoseconds, see
+    /// [`TimeHistogram::with_max_value`].
+    max_value: Option<u64>,
+    clamped: AtomicU64,
+    exemplar_sampling: ExemplarSampling,
+}
+
+/// Counters of the observations within and above a threshold, see
+/// [`TimeHistogram::with_threshold`].
+#[derive(Debug)]
+struct Threshold {
+    nanos: u64,
+    within: (&'static str, AtomicU64),
+    breached: (&'static str, AtomicU64),
 }
 
-impl HistogramTimer {
+impl<C: Clock> HistogramTimer<C> {
+    /// Sets what the timer does when it goes out of scope without being
+    /// stopped, instead of the drop policy of its histogram.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{DropPolicy, TimeHistogram};
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    /// let cancelled = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// drop(histogram.start_timer().on_drop(DropPolicy::Discard));
+    /// drop(histogram.start_timer().on_drop(DropPolicy::RecordTo(cancelled.clone())));
+    ///
+    /// assert_eq!(histogram.snapshot().count(), 0);
+    /// assert_eq!(cancelled.snapshot().count(), 1);
+    /// ```
+    pub fn on_drop(mut self, drop_policy: DropPolicy) -> Self {
+        self.drop_policy = drop_policy;
+        self
+    }
+
     /// Pauses time tracking until `unpause` is called. Any time passed between this call and
     /// calling `unpause` or `stop` is NOT counted.
     ///
     /// If the timer is already paused, then this call has no effect.
     pub fn pause(&mut self) {
         self.accumulated += self.start.map_or(Duration::ZERO, |value| {
-            Instant::now().saturating_duration_since(value)
+            self.clock.now().saturating_duration_since(value)
         });
         self.start = None
     }
@@ -56,10 +190,112 @@ impl HistogramTimer {
     /// If the timer is already un-paused or was not paused ever, then this call has no effect.
     pub fn resume(&mut self) {
         if self.start.is_none() {
-            self.start = Some(Instant::now());
+            self.start = Some(self.clock.now());
         }
     }
 
+    /// Also records the duration of the timer in `histogram` when it is
+    /// recorded, e.g. to feed both a per-endpoint and a global histogram
+    /// with a single measurement.
+    ///
+    /// Exemplars are only recorded in the histogram the timer was started
+    /// from, and the duration of laps only in the histogram given to
+    /// [`HistogramTimer::lap`].
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let endpoint = TimeHistogram::new([1.0].into_iter());
+    /// let global = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// endpoint.start_timer().also_record(&global).stop_and_record();
+    ///
+    /// assert_eq!(endpoint.snapshot().count(), 1);
+    /// assert_eq!(global.snapshot().count(), 1);
+    /// ```
+    pub fn also_record(mut self, histogram: &TimeHistogram) -> Self {
+        self.also.push(histogram.clone());
+        self
+    }
+
+    /// Pauses the timer until the returned guard is dropped, so that it is
+    /// resumed on every path out of a scope, including early returns.
+    ///
+    /// Like [`HistogramTimer::resume`], dropping the guard has no effect on
+    /// a timer which was already paused.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{ManualClock, TimeHistogram};
+    /// # use std::time::Duration;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    /// let clock = ManualClock::new();
+    ///
+    /// let mut timer = histogram.start_timer_with_clock(clock.clone());
+    /// clock.advance(Duration::from_millis(100));
+    ///
+    /// {
+    ///     let _paused = timer.pause_guard();
+    ///     // Time spent in downstream calls isn't counted.
+    ///     clock.advance(Duration::from_millis(500));
+    /// }
+    ///
+    /// clock.advance(Duration::from_millis(100));
+    /// assert_eq!(timer.stop_and_record(), Duration::from_millis(200));
+    /// ```
+    pub fn pause_guard(&mut self) -> PauseGuard<'_, C> {
+        let resume = self.start.is_some();
+
+        self.pause();
+        PauseGuard {
+            timer: self,
+            resume,
+        }
+    }
+
+    /// Records the duration of the current segment in `histogram`, and
+    /// starts a new segment, which is what the timer records when stopped.
+    ///
+    /// Like the timer itself, segments don't count the time during which the
+    /// timer is paused.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{ManualClock, TimeHistogram};
+    /// # use std::time::Duration;
+    /// #
+    /// let queued = TimeHistogram::new([1.0].into_iter());
+    /// let processing = TimeHistogram::new([1.0].into_iter());
+    /// let clock = ManualClock::new();
+    ///
+    /// let mut timer = processing.start_timer_with_clock(clock.clone());
+    /// clock.advance(Duration::from_millis(300));
+    /// assert_eq!(timer.lap(&queued), Duration::from_millis(300));
+    /// clock.advance(Duration::from_millis(200));
+    /// assert_eq!(timer.stop_and_record(), Duration::from_millis(200));
+    ///
+    /// assert_eq!(queued.snapshot().count(), 1);
+    /// assert_eq!(processing.snapshot().count(), 1);
+    /// ```
+    pub fn lap(&mut self, histogram: &TimeHistogram) -> Duration {
+        let now = self.clock.now();
+        let elapsed = self.elapsed_at(now);
+
+        histogram.observe_duration(elapsed);
+
+        self.accumulated = Duration::ZERO;
+        if self.start.is_some() {
+            self.start = Some(now);
+        }
+
+        elapsed
+    }
+
     /// Observe, record and return timer duration (in seconds).
     ///
     /// It observes and returns a floating-point number for seconds elapsed since
@@ -69,6 +305,37 @@ impl HistogramTimer {
         timer.observe(true)
     }
 
+    /// Observe, record and return timer duration, along with an exemplar,
+    /// see [`TimeHistogram::observe_with_exemplar`].
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// let timer = histogram.start_timer();
+    /// timer.stop_and_record_with_exemplar(vec![("trace_id", "3fa9")]);
+    ///
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn stop_and_record_with_exemplar<S>(self, label_set: S) -> Duration
+    where
+        S: Encode + Send + Sync + 'static,
+    {
+        let mut timer = self;
+        let elapsed = timer.observe(false);
+
+        timer
+            .histogram
+            .observe_with_exemplar(duration_nanos(elapsed), label_set);
+        for histogram in &timer.also {
+            histogram.observe_duration(elapsed);
+        }
+        elapsed
+    }
+
     /// Observe and return timer duration (in seconds).
     ///
     /// It returns a floating-point number of seconds elapsed since the timer started,
@@ -78,114 +345,1154 @@ impl HistogramTimer {
         timer.observe(false)
     }
 
+    /// Returns the duration tracked by the timer so far, without stopping
+    /// it.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{ManualClock, TimeHistogram};
+    /// # use std::time::Duration;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    /// let clock = ManualClock::new();
+    ///
+    /// let mut timer = histogram.start_timer_with_clock(clock.clone());
+    /// clock.advance(Duration::from_millis(300));
+    /// timer.pause();
+    /// clock.advance(Duration::from_millis(200));
+    ///
+    /// assert_eq!(timer.elapsed(), Duration::from_millis(300));
+    /// assert_eq!(histogram.snapshot().count(), 0);
+    /// ```
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed_at(self.clock.now())
+    }
+
+    fn elapsed_at(&self, now: Instant) -> Duration {
+        let elapsed_since_start = self
+            .start
+            .map_or(Duration::ZERO, |value| now.saturating_duration_since(value));
+
+        elapsed_since_start + self.accumulated
+    }
+
     fn observe(&mut self, record: bool) -> Duration {
-        let elapsed_since_start = self.start.map_or(Duration::ZERO, |value| {
-            Instant::now().saturating_duration_since(value)
-        });
-        let elapsed = elapsed_since_start + self.accumulated;
+        let elapsed = self.elapsed();
 
         self.observed = true;
         if record {
-            self.histogram.observe(elapsed.as_nanos() as u64);
+            for histogram in once(&self.histogram).chain(&self.also) {
+                histogram.observe_duration(elapsed);
+            }
         }
 
         elapsed
     }
 }
 
-impl Drop for HistogramTimer {
+/// A guard pausing a [`HistogramTimer`] until dropped, returned by
+/// [`HistogramTimer::pause_guard`].
+#[must_use = "the timer is resumed as soon as the guard is dropped"]
+pub struct PauseGuard<'a, C: Clock = TimeSource> {
+    timer: &'a mut HistogramTimer<C>,
+    resume: bool,
+}
+
+impl<C: Clock> Drop for PauseGuard<'_, C> {
     fn drop(&mut self) {
-        if !self.observed {
-            self.observe(true);
+        if self.resume {
+            self.timer.resume();
         }
     }
 }
 
+impl<C: Clock> Drop for HistogramTimer<C> {
+    fn drop(&mut self) {
+        if self.observed {
+            return;
+        }
+
+        match &self.drop_policy {
+            DropPolicy::Record => {
+                self.observe(true);
+            }
+            DropPolicy::Discard => {}
+            DropPolicy::RecordTo(histogram) => histogram.observe_duration(self.elapsed()),
+        }
+    }
+}
+
+/// Creates a histogram with the default buckets of the Prometheus client
+/// libraries, see [`buckets::HTTP_LATENCY`](crate::buckets::HTTP_LATENCY).
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::metrics::family::Family;
+/// # use prometools::{buckets, histogram::TimeHistogram};
+/// #
+/// let family = Family::<Vec<(String, String)>, TimeHistogram>::default();
+///
+/// family
+///     .get_or_create(&vec![("method".to_owned(), "GET".to_owned())])
+///     .observe_secs(0.2);
+///
+/// assert!(TimeHistogram::default()
+///     .upper_bounds()
+///     .eq(buckets::HTTP_LATENCY.into_iter().chain([f64::MAX])));
+/// ```
+impl Default for TimeHistogram {
+    fn default() -> Self {
+        static UPPER_BOUNDS: OnceLock<Arc<[f64]>> = OnceLock::new();
+
+        Self::from_upper_bounds(
+            UPPER_BOUNDS
+                .get_or_init(|| {
+                    crate::buckets::HTTP_LATENCY
+                        .into_iter()
+                        .chain(once(f64::MAX))
+                        .collect()
+                })
+                .clone(),
+        )
+    }
+}
+
 impl Clone for TimeHistogram {
     fn clone(&self) -> Self {
         TimeHistogram {
             inner: self.inner.clone(),
+            time_source: self.time_source,
+            expose_created: self.expose_created,
+            unit: self.unit,
+            drop_policy: self.drop_policy.clone(),
         }
     }
 }
 
 impl TimeHistogram {
+    /// Creates a histogram with the given bucket upper bounds, in seconds.
+    ///
+    /// The upper bounds must be sorted in increasing order.
     pub fn new(buckets: impl Iterator<Item = f64>) -> Self {
+        let upper_bounds: Arc<[f64]> = buckets.into_iter().chain(once(f64::MAX)).collect();
+
+        debug_assert!(
+            upper_bounds.windows(2).all(|w| w[0] <= w[1]),
+            "bucket upper bounds must be sorted",
+        );
+
+        Self::from_upper_bounds(upper_bounds)
+    }
+
+    /// Creates an empty histogram with the same bucket upper bounds as this
+    /// one, sharing their allocation, e.g. for each series of a
+    /// [`Family`](prometheus_client::metrics::family::Family).
+    ///
+    /// Only the buckets are shared, the other settings of this histogram
+    /// aren't copied. Histograms created with [`TimeHistogram::default`]
+    /// already share their buckets.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::family::Family;
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let buckets = TimeHistogram::new([0.1, 1.0].into_iter());
+    /// let family = Family::<Vec<(&str, &str)>, TimeHistogram, _>::new_with_constructor(move || {
+    ///     buckets.with_shared_buckets()
+    /// });
+    ///
+    /// family.get_or_create(&vec![("method", "GET")]).observe_secs(0.5);
+    ///
+    /// assert_eq!(family.get_or_create(&vec![("method", "GET")]).count(), 1);
+    /// assert_eq!(family.get_or_create(&vec![("method", "PUT")]).count(), 0);
+    /// ```
+    pub fn with_shared_buckets(&self) -> Self {
+        Self::from_upper_bounds(self.inner.upper_bounds.clone())
+    }
+
+    fn from_upper_bounds(upper_bounds: Arc<[f64]>) -> Self {
+        let buckets = upper_bounds.iter().map(|_| AtomicU64::new(0)).collect();
+        let exemplars = upper_bounds.iter().map(|_| ExemplarSlot::new()).collect();
+
         Self {
             inner: Arc::new(Inner {
-                sum: Default::default(),
+                sum: NanosSum::new(),
                 count: Default::default(),
-                buckets: buckets
-                    .into_iter()
-                    .chain(once(f64::MAX))
-                    .map(|upper_bound| (upper_bound, AtomicU64::new(0)))
-                    .collect(),
+                min: AtomicU64::new(u64::MAX),
+                max: Default::default(),
+                created: AtomicU64::new(created::now_nanos()),
+                upper_bounds,
+                buckets,
+                exemplars,
+                thresholds: Vec::new(),
+                max_value: None,
+                clamped: Default::default(),
+                exemplar_sampling: ExemplarSampling::default(),
             }),
+            time_source: TimeSource::default(),
+            expose_created: false,
+            unit: TimeUnit::default(),
+            drop_policy: None,
         }
     }
 
+    /// Creates a histogram with the given bucket upper bounds, as durations.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::{buckets, histogram::TimeHistogram};
+    /// # use std::time::Duration;
+    /// #
+    /// let histogram = TimeHistogram::with_duration_buckets([
+    ///     Duration::from_millis(5),
+    ///     Duration::from_millis(50),
+    ///     Duration::from_secs(1),
+    /// ]);
+    ///
+    /// assert!(histogram.upper_bounds().eq([0.005, 0.05, 1.0, f64::MAX]));
+    ///
+    /// let histogram = TimeHistogram::with_duration_buckets(buckets::duration_exponential_buckets(
+    ///     Duration::from_millis(1),
+    ///     10.0,
+    ///     3,
+    /// ));
+    ///
+    /// assert!(histogram.upper_bounds().eq([0.001, 0.01, 0.1, f64::MAX]));
+    /// ```
+    pub fn with_duration_buckets(buckets: impl IntoIterator<Item = Duration>) -> Self {
+        Self::new(buckets.into_iter().map(|bucket| bucket.as_secs_f64()))
+    }
+
+    /// Sets the clock used by the timers of this histogram, including
+    /// [`TimeHistogram::time_future`].
+    ///
+    /// Clones made afterwards share this histogram but keep their own time
+    /// source.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{TimeHistogram, TimeSource};
+    /// #
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter())
+    ///     .with_time_source(TimeSource::Coarse);
+    ///
+    /// histogram.start_timer().stop_and_record();
+    ///
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn with_time_source(mut self, time_source: TimeSource) -> Self {
+        self.time_source = time_source;
+        self
+    }
+
+    /// Exposes the time at which the histogram was created, or last reset,
+    /// as the OpenMetrics `_created` sample.
+    ///
+    /// Like [`TimeHistogram::with_time_source`], this only applies to this
+    /// handle and its clones made afterwards, so it must be set before
+    /// registering a clone of the histogram.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::time::UNIX_EPOCH;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram = TimeHistogram::new([1.0].into_iter()).with_created();
+    ///
+    /// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// let created = serialized
+    ///     .lines()
+    ///     .find_map(|line| line.strip_prefix("latency_created "))
+    ///     .unwrap();
+    /// let expected = histogram.created().duration_since(UNIX_EPOCH).unwrap();
+    ///
+    /// assert!((created.parse::<f64>().unwrap() - expected.as_secs_f64()).abs() < 1e-3);
+    /// ```
+    pub fn with_created(mut self) -> Self {
+        self.expose_created = true;
+        self
+    }
+
+    /// Sets the unit of the sum, bucket bounds and exemplars of the
+    /// histogram when encoded, for legacy consumers which don't expect
+    /// seconds.
+    ///
+    /// Bucket bounds are still given in seconds when creating the histogram,
+    /// and snapshots are still in seconds. Like
+    /// [`TimeHistogram::with_time_source`], this only applies to this handle
+    /// and its clones made afterwards.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::{TimeHistogram, TimeUnit};
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter()).with_unit(TimeUnit::Milliseconds);
+    ///
+    /// registry.register("latency_ms", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// histogram.observe_secs(0.5);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP latency_ms Request latency.\n",
+    ///         "# TYPE latency_ms histogram\n",
+    ///         "latency_ms_sum 500.0\n",
+    ///         "latency_ms_count 1\n",
+    ///         "latency_ms_bucket{le=\"100.0\"} 0\n",
+    ///         "latency_ms_bucket{le=\"1000.0\"} 1\n",
+    ///         "latency_ms_bucket{le=\"+Inf\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn with_unit(mut self, unit: TimeUnit) -> Self {
+        self.unit = unit;
+        self
+    }
+
+    /// Also counts the observations within `threshold`, i.e. lower than or
+    /// equal to it, and those above it, exposed as companion series of the
+    /// histogram with the given suffixes, e.g. `within_slo_total` and
+    /// `breached_slo_total`.
+    ///
+    /// Unlike bucket bounds, the threshold doesn't need to be known by
+    /// queries computing the proportion of observations meeting an
+    /// objective. Companion series aren't part of the histogram type though,
+    /// so strict OpenMetrics parsers may reject them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histogram was already cloned, as its clones wouldn't
+    /// count the observations around the new threshold.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::time::Duration;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram = TimeHistogram::new([1.0].into_iter()).with_threshold(
+    ///     Duration::from_millis(300),
+    ///     "within_slo_total",
+    ///     "breached_slo_total",
+    /// );
+    ///
+    /// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// histogram.observe_secs(0.25);
+    /// histogram.observe_secs(0.5);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP latency Request latency.\n",
+    ///         "# TYPE latency histogram\n",
+    ///         "latency_sum 0.75\n",
+    ///         "latency_count 2\n",
+    ///         "latency_bucket{le=\"1.0\"} 2\n",
+    ///         "latency_bucket{le=\"+Inf\"} 2\n",
+    ///         "latency_within_slo_total 1\n",
+    ///         "latency_breached_slo_total 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn with_threshold(
+        mut self,
+        threshold: Duration,
+        within: &'static str,
+        breached: &'static str,
+    ) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("thresholds must be added before cloning the histogram")
+            .thresholds
+            .push(Threshold {
+                nanos: duration_nanos(threshold),
+                within: (within, AtomicU64::new(0)),
+                breached: (breached, AtomicU64::new(0)),
+            });
+        self
+    }
+
+    /// Clamps observations larger than `max_value`, e.g. from a request
+    /// which hung for hours, so that they don't skew the sum.
+    ///
+    /// Clamped observations are counted as `max_value`, which should be
+    /// larger than the largest bucket bound so that they are still counted
+    /// in the `+Inf` bucket. Their number is exposed as a companion series
+    /// of the histogram with the `clamped_total` suffix, see
+    /// [`TimeHistogram::with_threshold`] about companion series.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histogram was already cloned.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::time::Duration;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram =
+    ///     TimeHistogram::new([1.0].into_iter()).with_max_value(Duration::from_secs(10));
+    ///
+    /// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.observe_secs(3600.0);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP latency Request latency.\n",
+    ///         "# TYPE latency histogram\n",
+    ///         "latency_sum 10.5\n",
+    ///         "latency_count 2\n",
+    ///         "latency_bucket{le=\"1.0\"} 1\n",
+    ///         "latency_bucket{le=\"+Inf\"} 2\n",
+    ///         "latency_clamped_total 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn with_max_value(mut self, max_value: Duration) -> Self {
+        Arc::get_mut(&mut self.inner)
+            .expect("the maximum value must be set before cloning the histogram")
+            .max_value = Some(duration_nanos(max_value));
+        self
+    }
+
+    /// Sets which exemplar each bucket keeps, see
+    /// [`TimeHistogram::observe_with_exemplar`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the histogram was already cloned.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::{ExemplarSampling, TimeHistogram};
+    /// # use std::time::Duration;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram = TimeHistogram::new([1.0].into_iter())
+    ///     .with_exemplar_sampling(ExemplarSampling::Max(Duration::from_secs(60)));
+    ///
+    /// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// histogram.observe_with_exemplar(750_000_000, vec![("trace_id", "slow")]);
+    /// histogram.observe_with_exemplar(250_000_000, vec![("trace_id", "fast")]);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert!(serialized.contains("latency_bucket{le=\"1.0\"} 2 # {trace_id=\"slow\"} 0.75\n"));
+    /// ```
+    pub fn with_exemplar_sampling(mut self, exemplar_sampling: ExemplarSampling) -> Self {
+        let exemplar_sampling = match exemplar_sampling {
+            ExemplarSampling::Probabilistic(probability) => {
+                ExemplarSampling::Probabilistic(probability.clamp(0.0, 1.0))
+            }
+            exemplar_sampling => exemplar_sampling,
+        };
+
+        Arc::get_mut(&mut self.inner)
+            .expect("exemplar sampling must be set before cloning the histogram")
+            .exemplar_sampling = exemplar_sampling;
+        self
+    }
+
+    /// Sets what the timers of this histogram do when they go out of scope
+    /// without being stopped, see [`HistogramTimer::on_drop`].
+    ///
+    /// Like [`TimeHistogram::with_time_source`], this only applies to this
+    /// handle and its clones made afterwards.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{DropPolicy, TimeHistogram};
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter()).with_drop_policy(DropPolicy::Discard);
+    ///
+    /// drop(histogram.start_timer());
+    /// histogram.start_timer().stop_and_record();
+    ///
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn with_drop_policy(mut self, drop_policy: DropPolicy) -> Self {
+        self.drop_policy = Some(Arc::new(drop_policy));
+        self
+    }
+
+    /// Returns the unit in which the histogram is encoded.
+    pub fn unit(&self) -> TimeUnit {
+        self.unit
+    }
+
+    /// Returns the time at which the histogram was created, or last reset.
+    pub fn created(&self) -> SystemTime {
+        created::from_nanos(self.inner.created.load(Ordering::Relaxed))
+    }
+
     pub fn start_timer(&self) -> HistogramTimer {
+        self.start_timer_with_clock(self.time_source)
+    }
+
+    /// Starts a timer reading time from the given clock.
+    pub fn start_timer_with_clock<C: Clock>(&self, clock: C) -> HistogramTimer<C> {
         HistogramTimer {
             histogram: self.clone(),
+            start: Some(clock.now()),
+            clock,
             observed: false,
-            start: Some(Instant::now()),
             accumulated: Duration::new(0, 0),
+            drop_policy: self.drop_policy.as_deref().cloned().unwrap_or_default(),
+            also: Vec::new(),
         }
     }
 
     pub fn observe(&self, nanos: u64) {
-        self.observe_and_bucket(nanos);
+        self.observe_and_bucket(nanos, 1);
+    }
+
+    /// Observes the same value `count` times, with a single update of each
+    /// counter of the histogram.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// histogram.observe_n(250_000_000, 4);
+    ///
+    /// let snapshot = histogram.snapshot();
+    ///
+    /// assert_eq!(snapshot.count(), 4);
+    /// assert_eq!(snapshot.sum(), 1.0);
+    /// assert_eq!(snapshot.buckets(), [(1.0, 4), (f64::MAX, 0)]);
+    /// ```
+    pub fn observe_n(&self, nanos: u64, count: u64) {
+        if count > 0 {
+            self.observe_and_bucket(nanos, count);
+        }
     }
 
-    fn observe_and_bucket(&self, v: u64) -> Option<usize> {
-        self.inner.sum.fetch_add(v, Ordering::Relaxed);
-        self.inner.count.fetch_add(1, Ordering::Relaxed);
+    /// Calls `f` and records how long it took, returning its result.
+    ///
+    /// The duration is also recorded if `f` panics.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// let sum = histogram.time(|| (1..=10).sum::<u32>());
+    ///
+    /// assert_eq!(sum, 55);
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn time<T>(&self, f: impl FnOnce() -> T) -> T {
+        let timer = self.start_timer();
+        let output = f();
 
-        let first_bucket = self
-            .inner
-            .buckets
-            .iter()
-            .enumerate()
-            .find(|(_i, (upper_bound, _value))| upper_bound >= &(v as f64 * 1E-9));
+        timer.stop_and_record();
+        output
+    }
+
+    /// Wraps a future to record the time it takes to complete, from its
+    /// first poll.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::future::Future;
+    /// # use std::pin::pin;
+    /// # use std::task::{Context, Poll, Waker};
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// let mut future = pin!(histogram.time_future(async { 42 }).exclude_idle());
+    /// let mut cx = Context::from_waker(Waker::noop());
+    ///
+    /// assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(42));
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn time_future<F>(&self, future: F) -> InstrumentedFuture<F>
+    where
+        F: Future,
+    {
+        InstrumentedFuture::new(future, self.clone())
+    }
+
+    /// Wraps a future to record the duration of each of its polls, and to
+    /// count them in `polls`, e.g. to find tasks blocking their executor.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::counter::Counter;
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::future::{poll_fn, Future};
+    /// # use std::pin::pin;
+    /// # use std::task::{Context, Poll, Waker};
+    /// #
+    /// let histogram = TimeHistogram::new([0.001, 0.01].into_iter());
+    /// let polls = Counter::default();
+    ///
+    /// let mut yielded = false;
+    /// let task = poll_fn(|cx| {
+    ///     if yielded {
+    ///         Poll::Ready(())
+    ///     } else {
+    ///         yielded = true;
+    ///         cx.waker().wake_by_ref();
+    ///         Poll::Pending
+    ///     }
+    /// });
+    ///
+    /// let mut future = pin!(histogram.time_polls(task, polls.clone()));
+    /// let mut cx = Context::from_waker(Waker::noop());
+    ///
+    /// assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+    /// assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(()));
+    /// assert_eq!(histogram.count(), 2);
+    /// assert_eq!(polls.get(), 2);
+    /// ```
+    pub fn time_polls<F>(&self, future: F, polls: Counter) -> PollTimedFuture<F>
+    where
+        F: Future,
+    {
+        PollTimedFuture::new(future, self.clone(), polls)
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::time::Duration;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// histogram.observe_duration(Duration::from_millis(1500));
+    /// histogram.observe_secs(0.25);
+    ///
+    /// let snapshot = histogram.snapshot();
+    ///
+    /// assert_eq!(snapshot.sum(), 1.75);
+    /// assert_eq!(snapshot.buckets(), [(1.0, 1), (f64::MAX, 1)]);
+    /// ```
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    /// Observes the time elapsed since `start`, read from the time source of
+    /// the histogram, and returns it.
+    ///
+    /// `start` should come from the same clock, a `start` in the future is
+    /// observed as zero.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// # use std::time::Instant;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    /// let start = Instant::now();
+    ///
+    /// let elapsed = histogram.observe_since(start);
+    ///
+    /// assert!(elapsed <= start.elapsed());
+    /// assert_eq!(histogram.snapshot().count(), 1);
+    /// ```
+    pub fn observe_since(&self, start: Instant) -> Duration {
+        let elapsed = self.time_source.now().saturating_duration_since(start);
+
+        self.observe_duration(elapsed);
+        elapsed
+    }
+
+    /// Observes a value, and records it as the exemplar of its bucket along
+    /// with the given label set, e.g. a trace ID.
+    ///
+    /// Each bucket only keeps one exemplar, by default the last one observed
+    /// in it, see [`TimeHistogram::with_exemplar_sampling`]. Like other
+    /// observations, recording an exemplar is lock-free.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+    /// let mut registry = <Registry>::default();
+    ///
+    /// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+    ///
+    /// histogram.observe_with_exemplar(
+    ///     250_000_000,
+    ///     vec![("trace_id".to_owned(), "3fa9".to_owned())],
+    /// );
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP latency Request latency.\n",
+    ///         "# TYPE latency histogram\n",
+    ///         "latency_sum 0.25\n",
+    ///         "latency_count 1\n",
+    ///         "latency_bucket{le=\"0.1\"} 0\n",
+    ///         "latency_bucket{le=\"1.0\"} 1 # {trace_id=\"3fa9\"} 0.25\n",
+    ///         "latency_bucket{le=\"+Inf\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn observe_with_exemplar<S>(&self, nanos: u64, label_set: S)
+    where
+        S: Encode + Send + Sync + 'static,
+    {
+        if let Some(bucket) = self.observe_and_bucket(nanos, 1) {
+            self.inner.exemplars[bucket].record(
+                self.inner.exemplar_sampling,
+                nanos,
+                Box::new(label_set),
+            );
+        }
+    }
 
-        match first_bucket {
-            Some((i, (_upper_bound, value))) => {
-                value.fetch_add(1, Ordering::Relaxed);
-                Some(i)
+    fn observe_and_bucket(&self, v: u64, n: u64) -> Option<usize> {
+        let v = match self.inner.max_value {
+            Some(max_value) if v > max_value => {
+                self.inner.clamped.fetch_add(n, Ordering::Relaxed);
+                max_value
             }
-            None => None,
+            _ => v,
+        };
+
+        self.inner.sum.add(v as f64 * n as f64);
+        self.inner.count.fetch_add(n, Ordering::Relaxed);
+        self.inner.min.fetch_min(v, Ordering::Relaxed);
+        self.inner.max.fetch_max(v, Ordering::Relaxed);
+
+        for threshold in &self.inner.thresholds {
+            let (_suffix, value) = if v <= threshold.nanos {
+                &threshold.within
+            } else {
+                &threshold.breached
+            };
+
+            value.fetch_add(n, Ordering::Relaxed);
         }
+
+        // Buckets are sorted by their upper bound, so the first one which
+        // can hold the value can be binary searched.
+        let secs = seconds(v);
+        let i = self
+            .inner
+            .upper_bounds
+            .partition_point(|upper_bound| *upper_bound < secs);
+
+        self.inner.buckets.get(i)?.fetch_add(n, Ordering::Relaxed);
+        Some(i)
+    }
+
+    /// Zeroes the sum, the count, all the buckets, threshold and clamped
+    /// counters of the histogram, and clears its minimum, maximum and exemplars.
+    ///
+    /// Prometheus treats any decrease of these values as a counter reset, as
+    /// if the process had restarted, so functions like `rate()` keep working
+    /// across resets. The creation time of the histogram is updated, so that
+    /// the reset is also visible in its `_created` sample. However,
+    /// observations made since the last scrape before the reset are lost,
+    /// and so are any observations made concurrently with the reset.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.reset();
+    ///
+    /// let snapshot = histogram.snapshot();
+    ///
+    /// assert_eq!(snapshot.count(), 0);
+    /// assert_eq!(snapshot.sum(), 0.0);
+    /// assert_eq!(snapshot.buckets(), [(1.0, 0), (f64::MAX, 0)]);
+    /// ```
+    pub fn reset(&self) {
+        self.inner.sum.reset();
+        self.inner.count.store(0, Ordering::Relaxed);
+        self.inner.min.store(u64::MAX, Ordering::Relaxed);
+        self.inner.max.store(0, Ordering::Relaxed);
+        self.inner.clamped.store(0, Ordering::Relaxed);
+        self.inner
+            .created
+            .store(created::now_nanos(), Ordering::Relaxed);
+
+        for value in &self.inner.buckets {
+            value.store(0, Ordering::Relaxed);
+        }
+
+        for exemplar in &self.inner.exemplars {
+            exemplar.clear();
+        }
+
+        for threshold in &self.inner.thresholds {
+            threshold.within.1.store(0, Ordering::Relaxed);
+            threshold.breached.1.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the upper bounds of the buckets, in seconds, ending with
+    /// `f64::MAX` for the `+Inf` bucket.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::{buckets, histogram::TimeHistogram};
+    /// #
+    /// let histogram = TimeHistogram::new(buckets::HTTP_LATENCY.into_iter());
+    ///
+    /// assert!(histogram.upper_bounds().eq(buckets::HTTP_LATENCY.into_iter().chain([f64::MAX])));
+    /// ```
+    pub fn upper_bounds(&self) -> impl ExactSizeIterator<Item = f64> + '_ {
+        self.inner.upper_bounds.iter().copied()
+    }
+
+    /// Returns the upper bound and the current count of each bucket, without
+    /// taking a full snapshot.
+    ///
+    /// Counts are not cumulative, and are read one at a time as the iterator
+    /// advances.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+    ///
+    /// histogram.observe_secs(0.5);
+    ///
+    /// assert_eq!(
+    ///     histogram.buckets().collect::<Vec<_>>(),
+    ///     [(0.1, 0), (1.0, 1), (f64::MAX, 0)],
+    /// );
+    /// ```
+    pub fn buckets(&self) -> impl ExactSizeIterator<Item = (f64, u64)> + '_ {
+        self.upper_bounds()
+            .zip(&*self.inner.buckets)
+            .map(|(upper_bound, value)| (upper_bound, value.load(Ordering::Relaxed)))
+    }
+
+    /// Returns the number of observations, without taking a full snapshot.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.observe_secs(1.5);
+    ///
+    /// assert_eq!(histogram.count(), 2);
+    /// assert_eq!(histogram.sum_seconds(), 2.0);
+    /// ```
+    pub fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of the observations, in seconds, without taking a
+    /// full snapshot.
+    pub fn sum_seconds(&self) -> f64 {
+        self.inner.sum.seconds()
     }
 
     pub fn snapshot(&self) -> HistogramSnapshot {
-        let sum = seconds(self.inner.sum.load(Ordering::Relaxed));
-        let count = self.inner.count.load(Ordering::Relaxed);
-        let buckets = self
-            .inner
+        let mut snapshot = HistogramSnapshot::default();
+        self.snapshot_into(&mut snapshot);
+        snapshot
+    }
+
+    /// Takes a snapshot of the histogram into `snapshot`, reusing the
+    /// allocation of its buckets, e.g. across scrapes.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{HistogramSnapshot, TimeHistogram};
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    /// let mut snapshot = HistogramSnapshot::default();
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.snapshot_into(&mut snapshot);
+    ///
+    /// assert_eq!(snapshot, histogram.snapshot());
+    /// assert_eq!(snapshot.buckets(), [(1.0, 1), (f64::MAX, 0)]);
+    /// ```
+    pub fn snapshot_into(&self, snapshot: &mut HistogramSnapshot) {
+        let (min, max) = min_max(
+            self.inner.min.load(Ordering::Relaxed),
+            self.inner.max.load(Ordering::Relaxed),
+        );
+
+        snapshot.sum = self.inner.sum.seconds();
+        snapshot.count = self.inner.count.load(Ordering::Relaxed);
+        snapshot.min = min;
+        snapshot.max = max;
+        snapshot.buckets.clear();
+        snapshot.buckets.extend(self.buckets());
+    }
+
+    /// Adds the observations of `snapshot` to the histogram, e.g. to fold in
+    /// latencies aggregated by another process.
+    ///
+    /// The snapshot must have the same bucket upper bounds as the histogram,
+    /// a finite and positive sum, and a count matching its buckets, otherwise
+    /// nothing is added. Its minimum and maximum are merged if known, but
+    /// its observations are neither clamped nor counted by the thresholds of
+    /// the histogram.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::{AbsorbError, HistogramSnapshot, TimeHistogram};
+    /// #
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+    /// let external = HistogramSnapshot::from_parts(2.5, 3, vec![(0.1, 1), (1.0, 2), (f64::MAX, 0)]);
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.absorb_snapshot(&external).unwrap();
+    ///
+    /// assert_eq!(histogram.count(), 4);
+    /// assert_eq!(histogram.sum_seconds(), 3.0);
+    /// assert_eq!(histogram.snapshot().buckets(), [(0.1, 1), (1.0, 3), (f64::MAX, 0)]);
+    ///
+    /// let other_buckets = HistogramSnapshot::from_parts(0.5, 1, vec![(0.5, 1), (f64::MAX, 0)]);
+    ///
+    /// assert_eq!(histogram.absorb_snapshot(&other_buckets), Err(AbsorbError::BucketMismatch));
+    /// assert_eq!(histogram.count(), 4);
+    /// ```
+    pub fn absorb_snapshot(&self, snapshot: &HistogramSnapshot) -> Result<(), AbsorbError> {
+        if !snapshot
             .buckets
             .iter()
-            .map(|(k, v)| (*k, v.load(Ordering::Relaxed)))
-            .collect();
+            .map(|(upper_bound, _n)| *upper_bound)
+            .eq(self.inner.upper_bounds.iter().copied())
+        {
+            return Err(AbsorbError::BucketMismatch);
+        }
 
-        HistogramSnapshot {
-            sum,
-            count,
-            buckets,
+        if !snapshot.sum.is_finite() || snapshot.sum < 0.0 {
+            return Err(AbsorbError::InvalidSum(snapshot.sum));
         }
+
+        let buckets = snapshot
+            .buckets
+            .iter()
+            .try_fold(0u64, |total, (_upper_bound, n)| total.checked_add(*n));
+
+        if buckets != Some(snapshot.count) {
+            return Err(AbsorbError::CountMismatch {
+                count: snapshot.count,
+                buckets,
+            });
+        }
+
+        self.inner.sum.add(snapshot.sum * 1E9);
+        self.inner
+            .count
+            .fetch_add(snapshot.count, Ordering::Relaxed);
+
+        if let Some(min) = snapshot.min {
+            self.inner.min.fetch_min(secs_nanos(min), Ordering::Relaxed);
+        }
+
+        if let Some(max) = snapshot.max {
+            self.inner.max.fetch_max(secs_nanos(max), Ordering::Relaxed);
+        }
+
+        for (bucket, (_upper_bound, n)) in self.inner.buckets.iter().zip(&snapshot.buckets) {
+            bucket.fetch_add(*n, Ordering::Relaxed);
+        }
+
+        Ok(())
     }
 }
 
+/// Why a snapshot couldn't be added to a histogram, see
+/// [`TimeHistogram::absorb_snapshot`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AbsorbError {
+    /// The buckets of the snapshot don't have the same upper bounds as the
+    /// buckets of the histogram.
+    BucketMismatch,
+    /// The sum of the snapshot is negative, infinite or NaN.
+    InvalidSum(f64),
+    /// The count of the snapshot doesn't match the total of its buckets,
+    /// which is `None` if it overflows.
+    CountMismatch { count: u64, buckets: Option<u64> },
+}
+
+impl fmt::Display for AbsorbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::BucketMismatch => f.write_str("snapshot buckets don't match the histogram"),
+            Self::InvalidSum(sum) => write!(f, "invalid snapshot sum {sum}"),
+            Self::CountMismatch {
+                count,
+                buckets: Some(buckets),
+            } => write!(
+                f,
+                "snapshot count {count} doesn't match its buckets total {buckets}",
+            ),
+            Self::CountMismatch {
+                count,
+                buckets: None,
+            } => write!(
+                f,
+                "snapshot count {count} doesn't match its overflowing buckets"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for AbsorbError {}
+
 impl TypedMetric for TimeHistogram {
     const TYPE: MetricType = MetricType::Histogram;
 }
 
+/// The values of a histogram at some point in time.
+///
+/// With the `serde` feature, snapshots can be serialized as a struct with
+/// `sum`, `count`, `min` and `max` fields, and a `buckets` sequence of
+/// structs with an `le` upper bound, formatted as in the text exposition
+/// format, and a non-cumulative `count`, e.g. in JSON:
+///
+/// ```json
+/// {
+///   "sum": 0.5,
+///   "count": 1,
+///   "min": 0.5,
+///   "max": 0.5,
+///   "buckets": [
+///     { "le": "0.1", "count": 0 },
+///     { "le": "1.0", "count": 1 },
+///     { "le": "+Inf", "count": 0 }
+///   ]
+/// }
+/// ```
+///
+/// The default snapshot is empty, without any bucket.
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct HistogramSnapshot {
     sum: f64,
     count: u64,
+    min: Option<f64>,
+    max: Option<f64>,
     buckets: Vec<(f64, u64)>,
 }
 
 impl HistogramSnapshot {
+    /// Creates a snapshot from its sum, in seconds, its count, and its
+    /// buckets, as upper bounds in seconds ending with `f64::MAX` and
+    /// non-cumulative counts, e.g. to import externally aggregated values.
+    ///
+    /// Its minimum and maximum are unknown.
+    pub fn from_parts(sum: f64, count: u64, buckets: Vec<(f64, u64)>) -> Self {
+        Self {
+            sum,
+            count,
+            min: None,
+            max: None,
+            buckets,
+        }
+    }
+
     pub fn sum(&self) -> f64 {
         self.sum
     }
@@ -194,22 +1501,188 @@ impl HistogramSnapshot {
         self.count
     }
 
+    /// Returns the smallest observed value, in seconds, if it is known.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([1.0].into_iter());
+    ///
+    /// assert_eq!(histogram.snapshot().min(), None);
+    ///
+    /// histogram.observe_secs(0.5);
+    /// histogram.observe_secs(4.0);
+    ///
+    /// assert_eq!(histogram.snapshot().min(), Some(0.5));
+    /// assert_eq!(histogram.snapshot().max(), Some(4.0));
+    /// ```
+    pub fn min(&self) -> Option<f64> {
+        self.min
+    }
+
+    /// Returns the largest observed value, in seconds, if it is known.
+    pub fn max(&self) -> Option<f64> {
+        self.max
+    }
+
     pub fn buckets(&self) -> &[(f64, u64)] {
         &self.buckets
     }
 
-    fn encode_with_maybe_exemplars<S>(
+    /// Estimates the value below which the fraction `q` of the observations
+    /// fall, like PromQL's `histogram_quantile`, or returns `None` if
+    /// nothing was observed.
+    ///
+    /// Observations are assumed to be spread linearly within their bucket,
+    /// from zero for the first bucket, and the `+Inf` bucket is reported as
+    /// the largest finite upper bound. `q` is clamped between 0 and 1.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([0.25, 0.5, 1.0].into_iter());
+    ///
+    /// for _ in 0..50 {
+    ///     histogram.observe_secs(0.1);
+    ///     histogram.observe_secs(0.6);
+    /// }
+    ///
+    /// assert_eq!(histogram.snapshot().quantile(0.5), Some(0.25));
+    /// assert_eq!(histogram.snapshot().quantile(0.75), Some(0.75));
+    /// ```
+    pub fn quantile(&self, q: f64) -> Option<f64> {
+        let total: u64 = self.buckets.iter().map(|(_upper_bound, count)| count).sum();
+
+        if total == 0 {
+            return None;
+        }
+
+        let rank = q.clamp(0.0, 1.0) * total as f64;
+        let mut lower_bound = 0.0;
+        let mut cumulative = 0;
+
+        for (upper_bound, count) in &self.buckets {
+            if *upper_bound == f64::MAX {
+                return Some(lower_bound);
+            }
+
+            let previous = cumulative;
+            cumulative += count;
+
+            if cumulative as f64 >= rank && *count > 0 {
+                let fraction = (rank - previous as f64) / *count as f64;
+
+                return Some(lower_bound + (upper_bound - lower_bound) * fraction);
+            }
+
+            lower_bound = *upper_bound;
+        }
+
+        Some(lower_bound)
+    }
+
+    /// Returns the observations made between `earlier` and this snapshot,
+    /// or `None` if their buckets have different upper bounds.
+    ///
+    /// Values saturate at zero, e.g. if `earlier` was actually taken after
+    /// this snapshot. The minimum and maximum of the difference aren't known.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+    ///
+    /// histogram.observe_secs(0.05);
+    /// let earlier = histogram.snapshot();
+    /// histogram.observe_secs(0.5);
+    /// histogram.observe_secs(0.75);
+    ///
+    /// let interval = histogram.snapshot().diff(&earlier).unwrap();
+    ///
+    /// assert_eq!(interval.count(), 2);
+    /// assert_eq!(interval.sum(), 1.25);
+    /// assert_eq!(interval.buckets(), [(0.1, 0), (1.0, 2), (f64::MAX, 0)]);
+    /// ```
+    pub fn diff(&self, earlier: &HistogramSnapshot) -> Option<HistogramSnapshot> {
+        self.zip_buckets(
+            earlier,
+            |sum, other| (sum - other).max(0.0),
+            u64::saturating_sub,
+        )
+    }
+
+    /// Returns the observations of both snapshots together, or `None` if
+    /// their buckets have different upper bounds.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::histogram::TimeHistogram;
+    /// #
+    /// let reads = TimeHistogram::new([0.1, 1.0].into_iter());
+    /// let writes = TimeHistogram::new([0.1, 1.0].into_iter());
+    ///
+    /// reads.observe_secs(0.05);
+    /// writes.observe_secs(0.5);
+    ///
+    /// let merged = reads.snapshot().merge(&writes.snapshot()).unwrap();
+    ///
+    /// assert_eq!(merged.count(), 2);
+    /// assert_eq!(merged.buckets(), [(0.1, 1), (1.0, 1), (f64::MAX, 0)]);
+    /// ```
+    pub fn merge(&self, other: &HistogramSnapshot) -> Option<HistogramSnapshot> {
+        let mut merged = self.zip_buckets(other, |sum, other| sum + other, u64::saturating_add)?;
+
+        merged.min = merge_option(self.min, other.min, f64::min);
+        merged.max = merge_option(self.max, other.max, f64::max);
+
+        Some(merged)
+    }
+
+    fn zip_buckets(
         &self,
-        exemplars: Option<&HashMap<usize, Exemplar<S, f64>>>,
-        mut encoder: Encoder,
-    ) -> Result<(), std::io::Error>
-    where
-        S: Encode,
-    {
+        other: &HistogramSnapshot,
+        sum: impl Fn(f64, f64) -> f64,
+        count: impl Fn(u64, u64) -> u64,
+    ) -> Option<HistogramSnapshot> {
+        if self.buckets.len() != other.buckets.len() {
+            return None;
+        }
+
+        let buckets = self
+            .buckets
+            .iter()
+            .zip(&other.buckets)
+            .map(|((upper_bound, n), (other_upper_bound, other_n))| {
+                (upper_bound == other_upper_bound).then(|| (*upper_bound, count(*n, *other_n)))
+            })
+            .collect::<Option<_>>()?;
+
+        Some(HistogramSnapshot {
+            sum: sum(self.sum, other.sum),
+            count: count(self.count, other.count),
+            min: None,
+            max: None,
+            buckets,
+        })
+    }
+
+    fn encode_with_exemplars(
+        &self,
+        exemplars: &[ExemplarSlot],
+        unit: TimeUnit,
+        encoder: &mut Encoder,
+    ) -> Result<(), std::io::Error> {
         encoder
             .encode_suffix("sum")?
             .no_bucket()?
-            .encode_value(self.sum)?
+            .encode_value(unit.scale_secs(self.sum))?
             .no_exemplar()?;
         encoder
             .encode_suffix("count")?
@@ -221,15 +1694,105 @@ impl HistogramSnapshot {
         for (i, (upper_bound, count)) in self.buckets.iter().enumerate() {
             cummulative += count;
             let mut bucket_encoder = encoder.encode_suffix("bucket")?;
-            let mut value_encoder = bucket_encoder.encode_bucket(*upper_bound)?;
-            let mut exemplar_encoder = value_encoder.encode_value(cummulative)?;
+            let upper_bound = if *upper_bound == f64::MAX {
+                f64::MAX
+            } else {
+                unit.scale_secs(*upper_bound)
+            };
+            let mut value_encoder = bucket_encoder.encode_bucket(upper_bound)?;
+            let exemplar = exemplars.get(i).and_then(ExemplarSlot::load);
+
+            // prometheus-client doesn't let us build its own `Exemplar`
+            // type, so the exemplar is written right after the value.
+            value_encoder
+                .encode_value(ValueWithExemplar {
+                    value: cummulative,
+                    exemplar: exemplar.as_deref(),
+                    unit,
+                })?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+}
 
-            match exemplars.and_then(|es| es.get(&i)) {
-                Some(exemplar) => exemplar_encoder.encode_exemplar(exemplar)?,
-                None => exemplar_encoder.no_exemplar()?,
+#[cfg(feature = "serde")]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+impl serde::Serialize for HistogramSnapshot {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::{SerializeSeq, SerializeStruct};
+
+        struct Buckets<'a>(&'a [(f64, u64)]);
+
+        struct Bucket(f64, u64);
+
+        impl serde::Serialize for Buckets<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+
+                for (upper_bound, count) in self.0 {
+                    seq.serialize_element(&Bucket(*upper_bound, *count))?;
+                }
+
+                seq.end()
             }
         }
 
+        impl serde::Serialize for Bucket {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: serde::Serializer,
+            {
+                let mut buf = ryu::Buffer::new();
+                let le = if self.0 == f64::MAX {
+                    "+Inf"
+                } else {
+                    buf.format(self.0)
+                };
+
+                let mut bucket = serializer.serialize_struct("Bucket", 2)?;
+                bucket.serialize_field("le", le)?;
+                bucket.serialize_field("count", &self.1)?;
+                bucket.end()
+            }
+        }
+
+        let mut snapshot = serializer.serialize_struct("HistogramSnapshot", 5)?;
+        snapshot.serialize_field("sum", &self.sum)?;
+        snapshot.serialize_field("count", &self.count)?;
+        snapshot.serialize_field("min", &self.min)?;
+        snapshot.serialize_field("max", &self.max)?;
+        snapshot.serialize_field("buckets", &Buckets(&self.buckets))?;
+        snapshot.end()
+    }
+}
+
+struct ValueWithExemplar<'a> {
+    value: u64,
+    exemplar: Option<&'a BucketExemplar>,
+    unit: TimeUnit,
+}
+
+impl Encode for ValueWithExemplar<'_> {
+    fn encode(&self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        self.value.encode(writer)?;
+
+        if let Some(exemplar) = self.exemplar {
+            writer.write_all(b" # {")?;
+            exemplar.label_set.encode(writer)?;
+            writer.write_all(b"} ")?;
+            self.unit
+                .scale_secs(seconds(exemplar.nanos))
+                .encode(writer)?;
+        }
+
         Ok(())
     }
 }
@@ -239,11 +1802,110 @@ fn seconds(val: u64) -> f64 {
     (val as f64) * 1E-9
 }
 
+/// A lock-free sum of nanoseconds, which doesn't overflow like a single
+/// `AtomicU64` would after about 584 years, e.g. when observing long
+/// durations many times at once.
+///
+/// The sum is stored as the bits of a `f64`, like Prometheus does, and
+/// updated with a compare-and-swap loop, so that it is read in a single
+/// load and never goes backwards.
+#[derive(Debug)]
+struct NanosSum(AtomicU64);
+
+impl NanosSum {
+    const fn new() -> Self {
+        // The bits of `0f64`.
+        Self(AtomicU64::new(0))
+    }
+
+    fn add(&self, nanos: f64) {
+        let _ = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + nanos).to_bits())
+            });
+    }
+
+    fn load(&self) -> f64 {
+        f64::from_bits(self.0.load(Ordering::Relaxed))
+    }
+
+    fn seconds(&self) -> f64 {
+        self.load() * 1E-9
+    }
+
+    fn reset(&self) {
+        self.0.store(0f64.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Returns the minimum and maximum in seconds, unless nothing was observed
+/// yet.
+fn min_max(min: u64, max: u64) -> (Option<f64>, Option<f64>) {
+    if min <= max {
+        (Some(seconds(min)), Some(seconds(max)))
+    } else {
+        (None, None)
+    }
+}
+
+fn merge_option(a: Option<f64>, b: Option<f64>, f: fn(f64, f64) -> f64) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(f(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+#[inline(always)]
+fn duration_nanos(duration: Duration) -> u64 {
+    duration.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+#[inline(always)]
+fn secs_nanos(secs: f64) -> u64 {
+    // Float to int casts saturate, and NaN is cast to 0.
+    (secs * 1E9) as u64
+}
+
+impl TimeHistogram {
+    /// Encodes the sum, count and buckets of `snapshot` with the exemplars
+    /// and unit of this histogram.
+    pub(crate) fn encode_snapshot(
+        &self,
+        snapshot: &HistogramSnapshot,
+        encoder: &mut Encoder,
+    ) -> Result<(), std::io::Error> {
+        snapshot.encode_with_exemplars(&self.inner.exemplars, self.unit, encoder)
+    }
+}
+
 impl EncodeMetric for TimeHistogram {
-    fn encode(&self, encoder: Encoder) -> Result<(), std::io::Error> {
-        // TODO: Would be better to use never type instead of `()`.
-        self.snapshot()
-            .encode_with_maybe_exemplars::<()>(None, encoder)
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        self.encode_snapshot(&self.snapshot(), &mut encoder)?;
+
+        if self.expose_created {
+            created::encode(&mut encoder, self.created())?;
+        }
+
+        if self.inner.max_value.is_some() {
+            encoder
+                .encode_suffix("clamped_total")?
+                .no_bucket()?
+                .encode_value(self.inner.clamped.load(Ordering::Relaxed))?
+                .no_exemplar()?;
+        }
+
+        for threshold in &self.inner.thresholds {
+            for (suffix, value) in [&threshold.within, &threshold.breached] {
+                encoder
+                    .encode_suffix(suffix)?
+                    .no_bucket()?
+                    .encode_value(value.load(Ordering::Relaxed))?
+                    .no_exemplar()?;
+            }
+        }
+
+        Ok(())
     }
 
     fn metric_type(&self) -> MetricType {
@@ -0,0 +1,224 @@
+use super::{
+    duration_nanos, min_max, secs_nanos, HistogramSnapshot, NanosSum, TimeHistogram, TimeUnit,
+};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Number of decades of nanoseconds covered by the warm-up buckets, from
+/// 1ns to 5000s.
+const DECADES: u32 = 13;
+/// Maximum number of buckets used by [`AdaptiveTimeHistogram::new`].
+const DEFAULT_MAX_BUCKETS: usize = 12;
+
+/// A lock-free histogram for tracking time, which picks its own bucket upper
+/// bounds from the values observed during a warm-up phase.
+///
+/// During warm-up, observations are counted in fine buckets following the
+/// 1-2-5 series, from 1ns to 5000s. Once `warmup` values have been
+/// observed, the histogram settles on the bounds of the fine buckets
+/// around the observed range, merging adjacent buckets by doubling their
+/// width until there are at most `max_buckets` of them, and keeps this
+/// layout from then on.
+///
+/// Until then, the histogram is only encoded with its sum, count and `+Inf`
+/// bucket, so that its layout only changes once. Observations racing with
+/// the end of the warm-up may be lost.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::AdaptiveTimeHistogram;
+/// #
+/// let histogram = AdaptiveTimeHistogram::with_max_buckets(100, 8);
+///
+/// for i in 0..100 {
+///     histogram.observe(10_000_000 + i * 1_000_000);
+/// }
+///
+/// assert_eq!(
+///     histogram.upper_bounds().unwrap(),
+///     [0.005, 0.01, 0.02, 0.05, 0.1, 0.2, 0.5, f64::MAX],
+/// );
+/// assert_eq!(histogram.snapshot().count(), 100);
+/// ```
+#[derive(Clone, Debug)]
+pub struct AdaptiveTimeHistogram {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    warmup: u64,
+    max_buckets: usize,
+    /// Upper bounds of the warm-up buckets, in nanoseconds.
+    fine_bounds: Box<[u64]>,
+    /// Counts of the warm-up buckets, followed by the `+Inf` bucket.
+    fine: Box<[AtomicU64]>,
+    sum: NanosSum,
+    count: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+    settled: OnceLock<TimeHistogram>,
+}
+
+impl AdaptiveTimeHistogram {
+    /// Creates a histogram which settles on at most 12 buckets after
+    /// `warmup` observations.
+    pub fn new(warmup: u64) -> Self {
+        Self::with_max_buckets(warmup, DEFAULT_MAX_BUCKETS)
+    }
+
+    /// Creates a histogram which settles on at most `max_buckets` buckets,
+    /// not counting `+Inf`, after `warmup` observations.
+    pub fn with_max_buckets(warmup: u64, max_buckets: usize) -> Self {
+        let fine_bounds: Box<[u64]> = (0..DECADES)
+            .flat_map(|decade| [1, 2, 5].map(|m| m * 10u64.pow(decade)))
+            .collect();
+
+        Self {
+            inner: Arc::new(Inner {
+                warmup: warmup.max(1),
+                max_buckets: max_buckets.max(1),
+                fine: (0..=fine_bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+                fine_bounds,
+                sum: NanosSum::new(),
+                count: Default::default(),
+                min: AtomicU64::new(u64::MAX),
+                max: Default::default(),
+                settled: OnceLock::new(),
+            }),
+        }
+    }
+
+    pub fn observe(&self, nanos: u64) {
+        if let Some(histogram) = self.inner.settled.get() {
+            return histogram.observe(nanos);
+        }
+
+        let i = self
+            .inner
+            .fine_bounds
+            .partition_point(|bound| *bound < nanos);
+
+        self.inner.fine[i].fetch_add(1, Ordering::Relaxed);
+        self.inner.sum.add(nanos as f64);
+        self.inner.min.fetch_min(nanos, Ordering::Relaxed);
+        self.inner.max.fetch_max(nanos, Ordering::Relaxed);
+
+        if self.inner.count.fetch_add(1, Ordering::Relaxed) + 1 >= self.inner.warmup {
+            self.inner.settled.get_or_init(|| self.settle());
+        }
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    /// Returns the bucket upper bounds, in seconds and ending with
+    /// `f64::MAX`, or `None` while warming up.
+    pub fn upper_bounds(&self) -> Option<Vec<f64>> {
+        Some(self.inner.settled.get()?.upper_bounds().collect())
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        if let Some(histogram) = self.inner.settled.get() {
+            return histogram.snapshot();
+        }
+
+        let count = self.inner.count.load(Ordering::Relaxed);
+        let (min, max) = min_max(
+            self.inner.min.load(Ordering::Relaxed),
+            self.inner.max.load(Ordering::Relaxed),
+        );
+
+        HistogramSnapshot {
+            sum: self.inner.sum.seconds(),
+            count,
+            min,
+            max,
+            buckets: vec![(f64::MAX, count)],
+        }
+    }
+
+    /// Picks the bucket upper bounds from the warm-up buckets, and moves
+    /// their counts to a histogram with these bounds.
+    fn settle(&self) -> TimeHistogram {
+        let fine: Vec<_> = self
+            .inner
+            .fine
+            .iter()
+            .map(|n| n.swap(0, Ordering::Relaxed))
+            .collect();
+        let last = self.inner.fine_bounds.len() - 1;
+
+        // Keep an empty bucket on each side of the observed range.
+        let lo = fine.iter().position(|n| *n > 0).unwrap_or(0);
+        let hi = fine.iter().rposition(|n| *n > 0).unwrap_or(0);
+        let lo = lo.saturating_sub(1).min(last);
+        let hi = (hi + 1).min(last);
+
+        let mut step = 1;
+        while (hi - lo) / step + 1 > self.inner.max_buckets {
+            step *= 2;
+        }
+
+        let mut indices: Vec<_> = (lo..=hi).rev().step_by(step).collect();
+        indices.reverse();
+
+        let histogram = TimeHistogram::new(
+            indices
+                .iter()
+                .map(|i| self.inner.fine_bounds[*i] as f64 / 1E9),
+        );
+
+        for (i, n) in fine.iter().enumerate() {
+            let bucket = indices.partition_point(|index| *index < i);
+            histogram.inner.buckets[bucket].fetch_add(*n, Ordering::Relaxed);
+        }
+
+        histogram.inner.sum.add(self.inner.sum.load());
+        histogram
+            .inner
+            .count
+            .store(self.inner.count.load(Ordering::Relaxed), Ordering::Relaxed);
+        histogram
+            .inner
+            .min
+            .store(self.inner.min.load(Ordering::Relaxed), Ordering::Relaxed);
+        histogram
+            .inner
+            .max
+            .store(self.inner.max.load(Ordering::Relaxed), Ordering::Relaxed);
+
+        histogram
+    }
+}
+
+impl TypedMetric for AdaptiveTimeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for AdaptiveTimeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        match self.inner.settled.get() {
+            Some(histogram) => histogram.encode(encoder),
+            None => self
+                .snapshot()
+                .encode_with_exemplars(&[], TimeUnit::Seconds, &mut encoder),
+        }
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
@@ -0,0 +1,148 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A monotonic source of time, used by [`HistogramTimer`](super::HistogramTimer).
+pub trait Clock {
+    /// Returns the current instant, which must never go backwards.
+    fn now(&self) -> Instant;
+}
+
+/// The default clock, reading [`Instant::now`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MonotonicClock;
+
+impl Clock for MonotonicClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A clock with a resolution of about a millisecond, cheaper to read than
+/// [`MonotonicClock`].
+///
+/// Reading it only loads an instant cached by a background thread, which is
+/// spawned the first time any coarse clock is read and updates it every
+/// millisecond for the rest of the life of the process.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CoarseClock;
+
+struct Ticker {
+    start: Instant,
+    elapsed_nanos: AtomicU64,
+}
+
+const TICK: Duration = Duration::from_millis(1);
+
+impl Clock for CoarseClock {
+    #[inline]
+    fn now(&self) -> Instant {
+        let ticker = ticker();
+
+        ticker.start + Duration::from_nanos(ticker.elapsed_nanos.load(Ordering::Relaxed))
+    }
+}
+
+fn ticker() -> &'static Ticker {
+    static TICKER: OnceLock<Ticker> = OnceLock::new();
+
+    TICKER.get_or_init(|| {
+        thread::Builder::new()
+            .name("prometools-coarse-clock".to_owned())
+            .spawn(|| loop {
+                thread::sleep(TICK);
+
+                let ticker = ticker();
+                let nanos = ticker.start.elapsed().as_nanos().try_into();
+
+                ticker
+                    .elapsed_nanos
+                    .store(nanos.unwrap_or(u64::MAX), Ordering::Relaxed);
+            })
+            .expect("failed to spawn the coarse clock thread");
+
+        Ticker {
+            start: Instant::now(),
+            elapsed_nanos: AtomicU64::new(0),
+        }
+    })
+}
+
+/// The clock used by the timers of a [`TimeHistogram`](super::TimeHistogram),
+/// see [`TimeHistogram::with_time_source`](super::TimeHistogram::with_time_source).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum TimeSource {
+    /// Reads time from [`MonotonicClock`].
+    #[default]
+    Monotonic,
+    /// Reads time from [`CoarseClock`], trading resolution for a lower
+    /// overhead.
+    Coarse,
+}
+
+impl Clock for TimeSource {
+    #[inline]
+    fn now(&self) -> Instant {
+        match self {
+            Self::Monotonic => MonotonicClock.now(),
+            Self::Coarse => CoarseClock.now(),
+        }
+    }
+}
+
+/// A clock which only advances when told to, for deterministic tests.
+///
+/// Clones of a manual clock share the same time.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::{ManualClock, TimeHistogram};
+/// # use std::time::Duration;
+/// #
+/// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+/// let clock = ManualClock::new();
+///
+/// let mut timer = histogram.start_timer_with_clock(clock.clone());
+/// clock.advance(Duration::from_millis(50));
+/// timer.pause();
+/// clock.advance(Duration::from_secs(10));
+///
+/// assert_eq!(timer.stop_and_record(), Duration::from_millis(50));
+/// assert_eq!(histogram.snapshot().buckets()[0], (0.1, 1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ManualClock {
+    start: Instant,
+    elapsed_nanos: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            elapsed_nanos: Default::default(),
+        }
+    }
+
+    /// Advances the clock, and all its clones, by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        let nanos = duration.as_nanos().try_into().unwrap_or(u64::MAX);
+
+        self.elapsed_nanos.fetch_add(nanos, Ordering::Relaxed);
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + Duration::from_nanos(self.elapsed_nanos.load(Ordering::Relaxed))
+    }
+}
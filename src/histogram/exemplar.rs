@@ -0,0 +1,169 @@
+use arc_swap::ArcSwapOption;
+use prometheus_client::encoding::text::Encode;
+use std::fmt;
+use std::ptr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Which exemplar each bucket of a [`TimeHistogram`](super::TimeHistogram)
+/// keeps, see
+/// [`TimeHistogram::with_exemplar_sampling`](super::TimeHistogram::with_exemplar_sampling).
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum ExemplarSampling {
+    /// Keeps the last exemplar observed in the bucket, which biases
+    /// exemplars towards recent traces.
+    #[default]
+    Last,
+    /// Replaces the exemplar of the bucket with the given probability,
+    /// clamped between 0 and 1. Empty buckets always keep their first
+    /// exemplar.
+    Probabilistic(f64),
+    /// Keeps the exemplar with the smallest value observed in the bucket
+    /// within the given window. The first exemplar observed after the
+    /// window elapsed starts a new window.
+    Min(Duration),
+    /// Keeps the exemplar with the largest value observed in the bucket
+    /// within the given window, e.g. to link to the slowest traces.
+    Max(Duration),
+}
+
+impl ExemplarSampling {
+    /// Returns whether an exemplar of `nanos` observed at `now` should
+    /// replace the `current` exemplar of a bucket.
+    fn replaces(self, current: Option<&BucketExemplar>, nanos: u64, now: u64) -> bool {
+        let Some(current) = current else {
+            return true;
+        };
+
+        let expired =
+            |window: Duration| Duration::from_nanos(now.saturating_sub(current.at)) >= window;
+
+        match self {
+            Self::Last => true,
+            Self::Probabilistic(probability) => random() < probability,
+            Self::Min(window) => nanos < current.nanos || expired(window),
+            Self::Max(window) => nanos > current.nanos || expired(window),
+        }
+    }
+}
+
+/// Returns a pseudo-random number between 0 and 1, from a xorshift generator
+/// seeded differently in each thread.
+fn random() -> f64 {
+    use std::cell::Cell;
+    use std::collections::hash_map::RandomState;
+    use std::hash::BuildHasher;
+
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().hash_one(0u64) | 1);
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        state.set(x);
+
+        (x >> 11) as f64 / (1u64 << 53) as f64
+    })
+}
+
+/// Returns the number of nanoseconds elapsed since an arbitrary instant
+/// shared by the whole process.
+fn monotonic_nanos() -> u64 {
+    static START: OnceLock<Instant> = OnceLock::new();
+
+    let start = *START.get_or_init(Instant::now);
+
+    start.elapsed().as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+/// The exemplar kept by a bucket.
+pub(super) struct BucketExemplar {
+    pub(super) label_set: Box<dyn Encode + Send + Sync>,
+    /// The value of the exemplar, in nanoseconds.
+    pub(super) nanos: u64,
+    /// When the exemplar was recorded, see [`monotonic_nanos`].
+    at: u64,
+}
+
+/// The storage of the exemplar of a bucket.
+///
+/// Recording an exemplar swaps it into the slot, and only if the exemplar it
+/// was sampled against is still the current one. Scrapes only hold a
+/// reference to the current exemplar and never mutate the slot, so they
+/// don't affect concurrent scrapes or sampling decisions.
+pub(super) struct ExemplarSlot {
+    exemplar: ArcSwapOption<BucketExemplar>,
+}
+
+impl ExemplarSlot {
+    pub(super) fn new() -> Self {
+        Self {
+            exemplar: ArcSwapOption::empty(),
+        }
+    }
+
+    /// Records an exemplar of `nanos` in the slot, if `sampling` lets it
+    /// replace the current one.
+    pub(super) fn record(
+        &self,
+        sampling: ExemplarSampling,
+        nanos: u64,
+        label_set: Box<dyn Encode + Send + Sync>,
+    ) {
+        let at = match sampling {
+            ExemplarSampling::Min(_) | ExemplarSampling::Max(_) => monotonic_nanos(),
+            ExemplarSampling::Last | ExemplarSampling::Probabilistic(_) => 0,
+        };
+
+        let mut label_set = Some(label_set);
+        let mut exemplar = None;
+
+        loop {
+            let current = self.exemplar.load();
+
+            if !sampling.replaces(current.as_deref(), nanos, at) {
+                return;
+            }
+
+            let new = exemplar.get_or_insert_with(|| {
+                Arc::new(BucketExemplar {
+                    label_set: label_set.take().expect("the label set is only taken once"),
+                    nanos,
+                    at,
+                })
+            });
+            let previous = self
+                .exemplar
+                .compare_and_swap(&*current, Some(Arc::clone(new)));
+
+            if as_ptr(&previous) == as_ptr(&current) {
+                return;
+            }
+        }
+    }
+
+    /// Returns the current exemplar of the slot.
+    pub(super) fn load(&self) -> Option<Arc<BucketExemplar>> {
+        self.exemplar.load_full()
+    }
+
+    /// Removes the exemplar of the slot.
+    pub(super) fn clear(&self) {
+        self.exemplar.store(None);
+    }
+}
+
+fn as_ptr(exemplar: &Option<Arc<BucketExemplar>>) -> *const BucketExemplar {
+    exemplar.as_ref().map_or(ptr::null(), Arc::as_ptr)
+}
+
+impl fmt::Debug for ExemplarSlot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExemplarSlot")
+            .field("nanos", &self.exemplar.load().as_ref().map(|e| e.nanos))
+            .finish_non_exhaustive()
+    }
+}
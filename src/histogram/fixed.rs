@@ -0,0 +1,136 @@
+use super::{duration_nanos, min_max, seconds, secs_nanos, HistogramSnapshot, NanosSum, TimeUnit};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// A lock-free histogram for tracking time, with `N` buckets stored inline.
+///
+/// Unlike [`TimeHistogram`](super::TimeHistogram), it doesn't allocate nor
+/// go through an [`Arc`](std::sync::Arc), and it can be created in a
+/// `static`. It can then be registered by reference.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::registry::Registry;
+/// # use prometools::histogram::FixedTimeHistogram;
+/// #
+/// static LATENCY: FixedTimeHistogram<3> = FixedTimeHistogram::new([0.01, 0.1, 1.0]);
+///
+/// let mut registry = <Registry>::default();
+/// registry.register("latency", "Request latency", Box::new(&LATENCY));
+///
+/// LATENCY.observe_secs(0.05);
+/// LATENCY.observe_secs(5.0);
+///
+/// let snapshot = LATENCY.snapshot();
+///
+/// assert_eq!(snapshot.count(), 2);
+/// assert_eq!(
+///     snapshot.buckets(),
+///     [(0.01, 0), (0.1, 1), (1.0, 0), (f64::MAX, 1)],
+/// );
+/// ```
+#[derive(Debug)]
+pub struct FixedTimeHistogram<const N: usize> {
+    sum: NanosSum,
+    count: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+    upper_bounds: [f64; N],
+    buckets: [AtomicU64; N],
+    /// The count of the implicit `+Inf` bucket.
+    overflow: AtomicU64,
+}
+
+impl<const N: usize> FixedTimeHistogram<N> {
+    /// Creates a histogram with the given bucket upper bounds, in seconds.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub const fn new(upper_bounds: [f64; N]) -> Self {
+        Self {
+            sum: NanosSum::new(),
+            count: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+            upper_bounds,
+            buckets: [const { AtomicU64::new(0) }; N],
+            overflow: AtomicU64::new(0),
+        }
+    }
+
+    pub fn observe(&self, nanos: u64) {
+        self.sum.add(nanos as f64);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.min.fetch_min(nanos, Ordering::Relaxed);
+        self.max.fetch_max(nanos, Ordering::Relaxed);
+
+        let secs = seconds(nanos);
+        let i = self
+            .upper_bounds
+            .partition_point(|upper_bound| *upper_bound < secs);
+
+        self.buckets
+            .get(i)
+            .unwrap_or(&self.overflow)
+            .fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let (min, max) = min_max(
+            self.min.load(Ordering::Relaxed),
+            self.max.load(Ordering::Relaxed),
+        );
+
+        HistogramSnapshot {
+            sum: self.sum.seconds(),
+            count: self.count.load(Ordering::Relaxed),
+            min,
+            max,
+            buckets: self
+                .upper_bounds
+                .iter()
+                .zip(&self.buckets)
+                .chain([(&f64::MAX, &self.overflow)])
+                .map(|(upper_bound, n)| (*upper_bound, n.load(Ordering::Relaxed)))
+                .collect(),
+        }
+    }
+}
+
+impl<const N: usize> TypedMetric for FixedTimeHistogram<N> {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl<const N: usize> EncodeMetric for FixedTimeHistogram<N> {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        self.snapshot()
+            .encode_with_exemplars(&[], TimeUnit::Seconds, &mut encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<const N: usize> EncodeMetric for &FixedTimeHistogram<N> {
+    fn encode(&self, encoder: Encoder) -> Result<(), std::io::Error> {
+        (**self).encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        FixedTimeHistogram::<N>::TYPE
+    }
+}
@@ -0,0 +1,130 @@
+use super::{Clock, TimeHistogram};
+use prometheus_client::metrics::counter::Counter;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// A future recording the time it took to complete in a [`TimeHistogram`],
+/// returned by [`TimeHistogram::time_future`].
+///
+/// By default, the wall-clock time from the first poll of the future to its
+/// completion is recorded. [`InstrumentedFuture::exclude_idle`] only
+/// records the time spent polling it instead.
+///
+/// Nothing is recorded if the future is dropped before completing.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct InstrumentedFuture<F> {
+    future: F,
+    histogram: TimeHistogram,
+    start: Option<Instant>,
+    busy: Duration,
+    exclude_idle: bool,
+}
+
+impl<F> InstrumentedFuture<F> {
+    pub(super) fn new(future: F, histogram: TimeHistogram) -> Self {
+        Self {
+            future,
+            histogram,
+            start: None,
+            busy: Duration::ZERO,
+            exclude_idle: false,
+        }
+    }
+
+    /// Only records the time spent polling the future, excluding the time
+    /// it spent waiting to be woken up.
+    pub fn exclude_idle(mut self) -> Self {
+        self.exclude_idle = true;
+        self
+    }
+}
+
+impl<F> Future for InstrumentedFuture<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // SAFETY: `future` is structurally pinned, it is never moved out of
+        // `self`, and `InstrumentedFuture` neither implements `Drop` nor
+        // `Unpin` unless `F` does.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        let clock = this.histogram.time_source;
+        let now = clock.now();
+        let start = *this.start.get_or_insert(now);
+        let poll = future.poll(cx);
+        let end = clock.now();
+
+        if this.exclude_idle {
+            this.busy += end.saturating_duration_since(now);
+        }
+
+        if poll.is_ready() {
+            let elapsed = if this.exclude_idle {
+                this.busy
+            } else {
+                end.saturating_duration_since(start)
+            };
+
+            this.histogram.observe_duration(elapsed);
+        }
+
+        poll
+    }
+}
+
+/// A future recording the duration of each of its polls in a
+/// [`TimeHistogram`], and counting them, returned by
+/// [`TimeHistogram::time_polls`].
+///
+/// Long polls block the executor thread they run on, so the tail of this
+/// histogram shows which tasks should yield more often or move their work
+/// to a blocking thread pool.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct PollTimedFuture<F> {
+    future: F,
+    histogram: TimeHistogram,
+    polls: Counter,
+}
+
+impl<F> PollTimedFuture<F> {
+    pub(super) fn new(future: F, histogram: TimeHistogram, polls: Counter) -> Self {
+        Self {
+            future,
+            histogram,
+            polls,
+        }
+    }
+}
+
+impl<F> Future for PollTimedFuture<F>
+where
+    F: Future,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<F::Output> {
+        // SAFETY: `future` is structurally pinned, it is never moved out of
+        // `self`, and `PollTimedFuture` neither implements `Drop` nor
+        // `Unpin` unless `F` does.
+        let this = unsafe { self.get_unchecked_mut() };
+        let future = unsafe { Pin::new_unchecked(&mut this.future) };
+
+        let clock = this.histogram.time_source;
+        let start = clock.now();
+        let poll = future.poll(cx);
+
+        this.histogram
+            .observe_duration(clock.now().saturating_duration_since(start));
+        this.polls.inc();
+
+        poll
+    }
+}
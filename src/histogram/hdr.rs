@@ -0,0 +1,233 @@
+use super::{duration_nanos, min_max, seconds, secs_nanos, HistogramSnapshot, NanosSum, TimeUnit};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::iter::once;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+/// Number of significant bits used by [`HdrTimeHistogram::new`].
+const DEFAULT_PRECISION: u32 = 7;
+
+/// A lock-free histogram for tracking time with a high dynamic range, whose
+/// relative error is bounded instead of depending on bucket bounds.
+///
+/// Observations are recorded with `precision` significant bits, i.e. with
+/// a relative error below `2^-precision`, from 1ns to `u64::MAX` nanoseconds.
+/// Values within a power of two are split in `2^precision` sub-buckets,
+/// allocated the first time one of their values is observed.
+///
+/// Quantiles can be computed in-process with [`HdrTimeHistogram::quantile`],
+/// while the histogram is down-sampled to the given bucket upper bounds when
+/// encoded, each sub-bucket being counted in the first bucket holding its
+/// largest value.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::HdrTimeHistogram;
+/// # use std::time::Duration;
+/// #
+/// let histogram = HdrTimeHistogram::new([0.002, 0.01].into_iter());
+///
+/// for micros in 1..=1000 {
+///     histogram.observe_duration(Duration::from_micros(micros));
+/// }
+///
+/// let p99 = histogram.quantile(0.99).unwrap();
+///
+/// assert!(p99.abs_diff(Duration::from_micros(990)) < Duration::from_micros(990) / 128);
+/// assert_eq!(histogram.snapshot().buckets()[0], (0.002, 1000));
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "hdr")))]
+pub struct HdrTimeHistogram {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    sum: NanosSum,
+    count: AtomicU64,
+    min: AtomicU64,
+    max: AtomicU64,
+    upper_bounds: Box<[f64]>,
+    precision: u32,
+    /// Sub-buckets for each power of two, see [`index`].
+    chunks: Box<[OnceLock<Box<[AtomicU64]>>]>,
+}
+
+impl HdrTimeHistogram {
+    /// Creates a histogram with the given bucket upper bounds, in seconds,
+    /// and a precision of 7 significant bits, i.e. a relative error below 1%.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub fn new(buckets: impl Iterator<Item = f64>) -> Self {
+        Self::with_precision(buckets, DEFAULT_PRECISION)
+    }
+
+    /// Creates a histogram with the given bucket upper bounds, in seconds,
+    /// and number of significant bits, clamped between 1 and 14.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub fn with_precision(buckets: impl Iterator<Item = f64>, precision: u32) -> Self {
+        let upper_bounds: Box<[f64]> = buckets.chain(once(f64::MAX)).collect();
+
+        debug_assert!(
+            upper_bounds.windows(2).all(|w| w[0] <= w[1]),
+            "bucket upper bounds must be sorted",
+        );
+
+        let precision = precision.clamp(1, 14);
+
+        Self {
+            inner: Arc::new(Inner {
+                sum: NanosSum::new(),
+                count: Default::default(),
+                min: AtomicU64::new(u64::MAX),
+                max: Default::default(),
+                upper_bounds,
+                precision,
+                chunks: (0..65 - precision).map(|_| OnceLock::new()).collect(),
+            }),
+        }
+    }
+
+    /// Returns the number of significant bits of the histogram.
+    pub fn precision(&self) -> u32 {
+        self.inner.precision
+    }
+
+    pub fn observe(&self, nanos: u64) {
+        self.inner.sum.add(nanos as f64);
+        self.inner.count.fetch_add(1, Ordering::Relaxed);
+        self.inner.min.fetch_min(nanos, Ordering::Relaxed);
+        self.inner.max.fetch_max(nanos, Ordering::Relaxed);
+
+        let precision = self.inner.precision;
+        let i = index(nanos, precision);
+        let chunk = self.inner.chunks[i >> precision]
+            .get_or_init(|| (0..1 << precision).map(|_| AtomicU64::new(0)).collect());
+
+        chunk[i & ((1 << precision) - 1)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    /// Returns the value below which the fraction `q` of the observations
+    /// fall, or `None` if nothing was observed.
+    ///
+    /// `q` is clamped between 0 and 1.
+    pub fn quantile(&self, q: f64) -> Option<Duration> {
+        let count = self.inner.count.load(Ordering::Relaxed);
+
+        if count == 0 {
+            return None;
+        }
+
+        let rank = ((q.clamp(0.0, 1.0) * count as f64).ceil() as u64).clamp(1, count);
+        let max = self.inner.max.load(Ordering::Relaxed);
+        let mut seen = 0;
+
+        for (i, n) in self.observed() {
+            seen += n;
+
+            if seen >= rank {
+                return Some(Duration::from_nanos(
+                    highest(i, self.inner.precision).min(max),
+                ));
+            }
+        }
+
+        // Observations racing with this call may have been counted but not
+        // recorded in their sub-bucket yet.
+        Some(Duration::from_nanos(max))
+    }
+
+    /// Down-samples the histogram to its bucket upper bounds.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut buckets: Vec<_> = self.inner.upper_bounds.iter().map(|b| (*b, 0)).collect();
+
+        for (i, n) in self.observed() {
+            let secs = seconds(highest(i, self.inner.precision));
+            let bucket = buckets.partition_point(|(upper_bound, _n)| *upper_bound < secs);
+
+            if let Some((_upper_bound, count)) = buckets.get_mut(bucket) {
+                *count += n;
+            }
+        }
+
+        let (min, max) = min_max(
+            self.inner.min.load(Ordering::Relaxed),
+            self.inner.max.load(Ordering::Relaxed),
+        );
+
+        HistogramSnapshot {
+            sum: self.inner.sum.seconds(),
+            count: self.inner.count.load(Ordering::Relaxed),
+            min,
+            max,
+            buckets,
+        }
+    }
+
+    /// Returns the index and count of all non-empty sub-buckets, in order.
+    fn observed(&self) -> impl Iterator<Item = (usize, u64)> + '_ {
+        let precision = self.inner.precision;
+
+        self.inner
+            .chunks
+            .iter()
+            .enumerate()
+            .filter_map(|(c, chunk)| Some((c, chunk.get()?)))
+            .flat_map(move |(c, chunk)| {
+                chunk.iter().enumerate().filter_map(move |(j, n)| {
+                    let n = n.load(Ordering::Relaxed);
+
+                    (n > 0).then_some(((c << precision) + j, n))
+                })
+            })
+    }
+}
+
+/// Returns the index of the sub-bucket holding `v`.
+///
+/// Values below `2^precision` have their own sub-bucket, then each power of
+/// two is split in `2^precision` sub-buckets of equal width.
+fn index(v: u64, precision: u32) -> usize {
+    let shift = (63 - v.max(1).leading_zeros()).saturating_sub(precision);
+
+    ((shift as usize) << precision) + (v >> shift) as usize
+}
+
+/// Returns the largest value held by the sub-bucket `i`.
+fn highest(i: usize, precision: u32) -> u64 {
+    let shift = ((i >> precision) as u32).saturating_sub(1);
+    let lowest = ((i - ((shift as usize) << precision)) as u64) << shift;
+
+    lowest + ((1 << shift) - 1)
+}
+
+impl TypedMetric for HdrTimeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for HdrTimeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        self.snapshot()
+            .encode_with_exemplars(&[], TimeUnit::Seconds, &mut encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
@@ -0,0 +1,524 @@
+//! Native histograms, with exponential buckets chosen automatically.
+
+use crate::encoding::message::{zigzag, Message};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Smallest power of two tracked by buckets, absolute values below it are
+/// counted in the zero bucket.
+const MIN_EXPONENT: i32 = -64;
+/// Largest power of two tracked by buckets, absolute values above it are
+/// counted in the last bucket.
+const MAX_EXPONENT: i32 = 64;
+/// Number of buckets allocated at once.
+const CHUNK_LEN: usize = 64;
+
+/// A histogram with exponential buckets, exposed as a Prometheus native
+/// histogram.
+///
+/// Bucket `i` counts values `v` such that `base^(i-1) < |v| <= base^i`,
+/// where `base` is `2^(2^-schema)`. Only buckets which have been observed
+/// are exposed, and they are allocated in chunks the first time one of
+/// their values is observed. Observations are lock-free.
+///
+/// Values whose absolute value is below `2^-64` are counted in the zero
+/// bucket, values above `2^64` are counted in the last bucket.
+///
+/// Native histograms can only be exposed with the protobuf format, in which
+/// [`crate::encoding::protobuf::encode_with_native`] encodes the native
+/// histograms of a registry listed in a [`NativeHistograms`]. Otherwise,
+/// the observed buckets are exposed as classic cumulative buckets.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::histogram::NativeHistogram;
+/// #
+/// let histogram = NativeHistogram::new(0);
+/// let mut registry = <Registry>::default();
+///
+/// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+///
+/// histogram.observe(0.3);
+/// histogram.observe(0.4);
+/// histogram.observe(3.0);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP latency Request latency.\n",
+///         "# TYPE latency histogram\n",
+///         "latency_sum 3.7\n",
+///         "latency_count 3\n",
+///         "latency_bucket{le=\"0.5\"} 2\n",
+///         "latency_bucket{le=\"4.0\"} 3\n",
+///         "latency_bucket{le=\"+Inf\"} 3\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct NativeHistogram {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    schema: i8,
+    count: AtomicU64,
+    /// The bits of the sum, as a `f64`.
+    sum: AtomicU64,
+    zero_count: AtomicU64,
+    positive: Buckets,
+    negative: Buckets,
+}
+
+impl NativeHistogram {
+    /// Creates a native histogram with the given schema.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `schema` is not between -4 and 8, as defined by Prometheus.
+    pub fn new(schema: i8) -> Self {
+        assert!(
+            (-4..=8).contains(&schema),
+            "native histogram schema must be between -4 and 8",
+        );
+
+        Self {
+            inner: Arc::new(Inner {
+                schema,
+                count: AtomicU64::new(0),
+                sum: AtomicU64::new(0f64.to_bits()),
+                zero_count: AtomicU64::new(0),
+                positive: Buckets::new(schema),
+                negative: Buckets::new(schema),
+            }),
+        }
+    }
+
+    /// Creates a native histogram whose consecutive buckets grow by at most
+    /// `factor`, e.g. `1.1` for buckets growing by at most 10%.
+    ///
+    /// The coarsest schema fitting `factor` is used, or the finest schema
+    /// if `factor` is too small.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not greater than 1.
+    pub fn with_growth_factor(factor: f64) -> Self {
+        assert!(factor > 1.0, "growth factor must be greater than 1");
+
+        let schema = (-4..=8).find(|&schema| base(schema) <= factor).unwrap_or(8);
+
+        Self::new(schema)
+    }
+
+    /// Returns the schema of this histogram.
+    pub fn schema(&self) -> i8 {
+        self.inner.schema
+    }
+
+    /// Returns the number of observations.
+    pub fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+
+    /// Returns the sum of all observations.
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.inner.sum.load(Ordering::Relaxed))
+    }
+
+    pub fn observe(&self, v: f64) {
+        let inner = &*self.inner;
+
+        inner.count.fetch_add(1, Ordering::Relaxed);
+
+        let _ = inner
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + v).to_bits())
+            });
+
+        if v.is_nan() {
+            return;
+        }
+
+        if v.abs() <= zero_threshold() {
+            inner.zero_count.fetch_add(1, Ordering::Relaxed);
+        } else if v > 0.0 {
+            inner.positive.increment(bucket_index(v, inner.schema));
+        } else {
+            inner.negative.increment(bucket_index(-v, inner.schema));
+        }
+    }
+
+    /// Encodes this histogram as a length-delimited protobuf
+    /// `io.prometheus.client.MetricFamily` message, as expected by
+    /// Prometheus when scraping with the
+    /// `application/vnd.google.protobuf; proto=io.prometheus.client.MetricFamily; encoding=delimited`
+    /// content type.
+    ///
+    /// As with [`prometheus_client::registry::Registry::register`], a
+    /// period is appended to `help`. This is only needed for histograms
+    /// which aren't registered, as
+    /// [`crate::encoding::protobuf::encode_with_native`] encodes the ones of
+    /// a registry as native histograms.
+    pub fn encode_protobuf(
+        &self,
+        name: &str,
+        help: &str,
+        labels: &[(&str, &str)],
+        writer: &mut dyn io::Write,
+    ) -> io::Result<()> {
+        let mut metric = Message::default();
+        for (name, value) in labels {
+            let mut label = Message::default();
+            label.string(1, name);
+            label.string(2, value);
+            metric.message(1, &label);
+        }
+        metric.message(7, &self.histogram_message());
+
+        let mut family = Message::default();
+        family.string(1, name);
+        family.string(2, &format!("{help}."));
+        family.uint64(3, 4); // HISTOGRAM
+        family.message(4, &metric);
+
+        writer.write_all(&family.delimited())
+    }
+
+    /// Returns the `io.prometheus.client.Histogram` message of this
+    /// histogram.
+    pub(crate) fn histogram_message(&self) -> Message {
+        let inner = &*self.inner;
+
+        let mut histogram = Message::default();
+        histogram.uint64(1, inner.count.load(Ordering::Relaxed));
+        histogram.double(2, f64::from_bits(inner.sum.load(Ordering::Relaxed)));
+        histogram.sint64(5, inner.schema.into());
+        histogram.double(6, zero_threshold());
+        histogram.uint64(7, inner.zero_count.load(Ordering::Relaxed));
+
+        let negative = inner.negative.observed();
+        let positive = inner.positive.observed();
+
+        if negative.is_empty() && positive.is_empty() {
+            // An empty span tells Prometheus this is a native histogram.
+            histogram.message(12, &Message::default());
+        }
+
+        encode_spans(&mut histogram, 9, 10, &negative);
+        encode_spans(&mut histogram, 12, 13, &positive);
+
+        histogram
+    }
+}
+
+impl TypedMetric for NativeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for NativeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        let inner = &*self.inner;
+        let count = inner.count.load(Ordering::Relaxed);
+
+        encoder
+            .encode_suffix("sum")?
+            .no_bucket()?
+            .encode_value(f64::from_bits(inner.sum.load(Ordering::Relaxed)))?
+            .no_exemplar()?;
+        encoder
+            .encode_suffix("count")?
+            .no_bucket()?
+            .encode_value(count)?
+            .no_exemplar()?;
+
+        let negative = inner.negative.observed().into_iter().rev().map(|(i, n)| {
+            // Negative buckets are inclusive of their lower bound.
+            (-upper_bound(i - 1, inner.schema), n)
+        });
+        let zero = Some((zero_threshold(), inner.zero_count.load(Ordering::Relaxed)))
+            .filter(|(_, n)| *n > 0);
+        let positive = inner
+            .positive
+            .observed()
+            .into_iter()
+            .map(|(i, n)| (upper_bound(i, inner.schema), n));
+
+        let mut cumulative = 0;
+        for (upper_bound, n) in negative.chain(zero).chain(positive) {
+            cumulative += n;
+            encoder
+                .encode_suffix("bucket")?
+                .encode_bucket(upper_bound)?
+                .encode_value(cumulative)?
+                .no_exemplar()?;
+        }
+
+        encoder
+            .encode_suffix("bucket")?
+            .encode_bucket(f64::MAX)?
+            .encode_value(count)?
+            .no_exemplar()?;
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// The native histograms of a registry, which
+/// [`crate::encoding::protobuf::encode_with_native`] encodes as native
+/// histograms rather than with their classic buckets.
+///
+/// prometheus-client only lets metrics encode samples in the text format,
+/// so each native histogram is listed here under the name of its family,
+/// prefix included, and its labels.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::family::Family, registry::Registry};
+/// # use prometools::encoding::protobuf::encode_with_native;
+/// # use prometools::histogram::{NativeHistogram, NativeHistograms};
+/// #
+/// let mut registry = <Registry>::default();
+/// let mut natives = NativeHistograms::default();
+/// let latency = Family::<Vec<(&str, &str)>, NativeHistogram>::new_with_constructor(|| {
+///     NativeHistogram::new(3)
+/// });
+///
+/// registry.register("latency", "Request latency", Box::new(latency.clone()));
+///
+/// for path in ["/", "/api"] {
+///     let histogram = latency.get_or_create(&vec![("path", path)]).clone();
+///
+///     natives.register("latency", &[("path", path)], histogram);
+/// }
+///
+/// let mut serialized = vec![];
+///
+/// encode_with_native(&mut serialized, &registry, &natives).unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NativeHistograms {
+    series: Vec<NativeSeries>,
+}
+
+#[derive(Clone, Debug)]
+struct NativeSeries {
+    name: String,
+    /// The labels of the series, sorted by name.
+    labels: Vec<(String, String)>,
+    histogram: NativeHistogram,
+}
+
+impl NativeHistograms {
+    /// Lists the histogram of the series of the family `name` with the given
+    /// labels, replacing the one listed for the same series if any.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        labels: &[(&str, &str)],
+        histogram: NativeHistogram,
+    ) {
+        let name = name.into();
+        let mut labels: Vec<_> = labels
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+
+        labels.sort_unstable();
+
+        match self
+            .series
+            .iter_mut()
+            .find(|series| series.name == name && series.labels == labels)
+        {
+            Some(series) => series.histogram = histogram,
+            None => self.series.push(NativeSeries {
+                name,
+                labels,
+                histogram,
+            }),
+        }
+    }
+
+    /// Returns the histogram of the series of the family `name` with the
+    /// given labels, in any order.
+    pub(crate) fn find(&self, name: &str, labels: &[&(&str, String)]) -> Option<&NativeHistogram> {
+        let mut labels: Vec<_> = labels
+            .iter()
+            .map(|(name, value)| (*name, value.as_str()))
+            .collect();
+
+        labels.sort_unstable();
+
+        self.series
+            .iter()
+            .find(|series| {
+                series.name == name
+                    && series
+                        .labels
+                        .iter()
+                        .map(|(name, value)| (name.as_str(), value.as_str()))
+                        .eq(labels.iter().copied())
+            })
+            .map(|series| &series.histogram)
+    }
+}
+
+/// Buckets for one sign of observations, allocated in chunks on demand.
+#[derive(Debug)]
+struct Buckets {
+    /// The index of the first bucket.
+    first: i32,
+    len: usize,
+    chunks: Box<[OnceLock<Box<[AtomicU64]>>]>,
+}
+
+impl Buckets {
+    fn new(schema: i8) -> Self {
+        let first = scale(MIN_EXPONENT, schema) + 1;
+        let len = (scale(MAX_EXPONENT, schema) - first + 1) as usize;
+
+        Self {
+            first,
+            len,
+            chunks: (0..len.div_ceil(CHUNK_LEN))
+                .map(|_| OnceLock::new())
+                .collect(),
+        }
+    }
+
+    fn increment(&self, index: i32) {
+        let slot = (index.max(self.first) - self.first) as usize;
+        let slot = slot.min(self.len - 1);
+
+        let chunk = self.chunks[slot / CHUNK_LEN]
+            .get_or_init(|| (0..CHUNK_LEN).map(|_| AtomicU64::new(0)).collect());
+
+        chunk[slot % CHUNK_LEN].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the index and count of all non-empty buckets, in order.
+    fn observed(&self) -> Vec<(i32, u64)> {
+        let mut observed = vec![];
+
+        for (i, chunk) in self.chunks.iter().enumerate() {
+            let Some(chunk) = chunk.get() else {
+                continue;
+            };
+
+            for (j, n) in chunk.iter().enumerate() {
+                let n = n.load(Ordering::Relaxed);
+
+                if n > 0 {
+                    observed.push((self.first + (i * CHUNK_LEN + j) as i32, n));
+                }
+            }
+        }
+
+        observed
+    }
+}
+
+/// Encodes buckets as spans and deltas, filling gaps of at most two
+/// buckets with empty ones instead of starting new spans.
+fn encode_spans(
+    histogram: &mut Message,
+    span_field: u32,
+    delta_field: u32,
+    buckets: &[(i32, u64)],
+) {
+    let mut spans = vec![];
+    let mut deltas = vec![];
+    let mut previous: Option<(i32, u64)> = None;
+
+    for &(index, n) in buckets {
+        let mut last_count = previous.map_or(0, |(_, n)| n as i64);
+
+        match previous {
+            Some((previous_index, _)) if index - previous_index <= 3 => {
+                for _ in previous_index + 1..index {
+                    deltas.push(-last_count);
+                    last_count = 0;
+                }
+
+                let (_, length) = spans.last_mut().expect("span for previous bucket");
+                *length += (index - previous_index) as u32;
+            }
+            Some((previous_index, _)) => spans.push((index - previous_index - 1, 1)),
+            None => spans.push((index, 1)),
+        }
+
+        deltas.push(n as i64 - last_count);
+        previous = Some((index, n));
+    }
+
+    for (offset, length) in spans {
+        let mut span = Message::default();
+        span.sint64(1, offset.into());
+        span.uint64(2, length.into());
+        histogram.message(span_field, &span);
+    }
+
+    if !deltas.is_empty() {
+        let mut packed = Message::default();
+        for delta in deltas {
+            packed.varint(zigzag(delta));
+        }
+        histogram.bytes(delta_field, &packed.0);
+    }
+}
+
+fn bucket_index(v: f64, schema: i8) -> i32 {
+    let index = (v.log2() * 2f64.powi(schema.into())).ceil() as i32;
+
+    // `log2` isn't exact, so values close to bucket bounds may need to be
+    // moved to the neighbouring bucket.
+    if v > upper_bound(index, schema) {
+        index + 1
+    } else if v <= upper_bound(index - 1, schema) {
+        index - 1
+    } else {
+        index
+    }
+}
+
+fn upper_bound(index: i32, schema: i8) -> f64 {
+    (f64::from(index) * 2f64.powi(-i32::from(schema))).exp2()
+}
+
+fn base(schema: i8) -> f64 {
+    2f64.powi(-i32::from(schema)).exp2()
+}
+
+/// Returns the index of the bucket whose upper bound is `2^exponent`.
+fn scale(exponent: i32, schema: i8) -> i32 {
+    if schema >= 0 {
+        exponent << schema
+    } else {
+        exponent >> -schema
+    }
+}
+
+fn zero_threshold() -> f64 {
+    2f64.powi(MIN_EXPONENT)
+}
@@ -0,0 +1,338 @@
+use super::{
+    duration_nanos, min_max, secs_nanos, Clock, HistogramSnapshot, MonotonicClock, NanosSum,
+    TimeHistogram,
+};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// A lock-free histogram for tracking time, which also keeps the
+/// distribution of the observations of a recent window of time.
+///
+/// The window is split in slices of equal duration, and each slice is reset
+/// when it gets reused for a new period of time. [`Self::window_snapshot`]
+/// aggregates the slices of the window, which covers between the window
+/// duration minus one slice and the window duration, depending on how far
+/// into the current slice the snapshot is taken. Observations racing with the
+/// reset of their slice may be missing from the window.
+///
+/// The histogram is still encoded with its cumulative values since it was
+/// created, as expected by Prometheus.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::{ManualClock, RollingTimeHistogram};
+/// # use std::time::Duration;
+/// #
+/// let clock = ManualClock::new();
+/// let histogram = RollingTimeHistogram::with_clock(
+///     [0.1, 1.0].into_iter(),
+///     Duration::from_secs(60),
+///     6,
+///     clock.clone(),
+/// );
+///
+/// histogram.observe_secs(0.5);
+/// clock.advance(Duration::from_secs(60));
+/// histogram.observe_secs(0.05);
+///
+/// assert_eq!(histogram.snapshot().count(), 2);
+/// assert_eq!(histogram.window_snapshot().count(), 1);
+/// assert_eq!(histogram.window_snapshot().buckets()[0], (0.1, 1));
+/// ```
+#[derive(Debug)]
+pub struct RollingTimeHistogram<C: Clock = MonotonicClock> {
+    inner: Arc<Inner<C>>,
+}
+
+#[derive(Debug)]
+struct Inner<C> {
+    cumulative: TimeHistogram,
+    clock: C,
+    start: Instant,
+    slice_nanos: u64,
+    slices: Box<[Slice]>,
+}
+
+#[derive(Debug)]
+struct Slice {
+    /// The index of the period of time the counters are for, plus one, or
+    /// zero if the slice was never used.
+    epoch: AtomicU64,
+    sum: NanosSum,
+    /// The counters of the slice: its count, its minimum, its maximum, then
+    /// its buckets.
+    counters: Box<[AtomicU64]>,
+}
+
+impl<C: Clock> Clone for RollingTimeHistogram<C> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl RollingTimeHistogram {
+    /// Creates a histogram with the given bucket upper bounds, in seconds,
+    /// and a window of `window` split in `slices` slices.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub fn new(buckets: impl Iterator<Item = f64>, window: Duration, slices: usize) -> Self {
+        Self::with_clock(buckets, window, slices, MonotonicClock)
+    }
+}
+
+impl<C: Clock> RollingTimeHistogram<C> {
+    /// Creates a histogram like [`RollingTimeHistogram::new`], reading time
+    /// from the given clock to rotate the slices of the window.
+    pub fn with_clock(
+        buckets: impl Iterator<Item = f64>,
+        window: Duration,
+        slices: usize,
+        clock: C,
+    ) -> Self {
+        let cumulative = TimeHistogram::new(buckets);
+        let slices = slices.max(1);
+        let counters = 3 + cumulative.inner.buckets.len();
+
+        Self {
+            inner: Arc::new(Inner {
+                cumulative,
+                start: clock.now(),
+                clock,
+                slice_nanos: (duration_nanos(window) / slices as u64).max(1),
+                slices: (0..slices)
+                    .map(|_| Slice {
+                        epoch: AtomicU64::new(0),
+                        sum: NanosSum::new(),
+                        counters: (0..counters)
+                            .map(|i| AtomicU64::new(if i == 1 { u64::MAX } else { 0 }))
+                            .collect(),
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    pub fn observe(&self, nanos: u64) {
+        let bucket = self.inner.cumulative.observe_and_bucket(nanos, 1);
+        let epoch = self.epoch();
+        let slice = &self.inner.slices[(epoch % self.inner.slices.len() as u64) as usize];
+        let seen = slice.epoch.load(Ordering::Acquire);
+
+        if seen > epoch + 1 {
+            // The slice was already reused for a later period of time.
+            return;
+        }
+
+        if seen < epoch + 1
+            && slice
+                .epoch
+                .compare_exchange(seen, epoch + 1, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+        {
+            slice.sum.reset();
+            for counter in &*slice.counters {
+                counter.store(0, Ordering::Relaxed);
+            }
+            slice.counters[1].store(u64::MAX, Ordering::Relaxed);
+        }
+
+        slice.sum.add(nanos as f64);
+        slice.counters[0].fetch_add(1, Ordering::Relaxed);
+        slice.counters[1].fetch_min(nanos, Ordering::Relaxed);
+        slice.counters[2].fetch_max(nanos, Ordering::Relaxed);
+
+        if let Some(bucket) = bucket {
+            slice.counters[3 + bucket].fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    /// Returns the cumulative values of the histogram since it was created.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        self.inner.cumulative.snapshot()
+    }
+
+    /// Aggregates the slices of the current window in a snapshot.
+    pub fn window_snapshot(&self) -> HistogramSnapshot {
+        let epoch = self.epoch();
+        let mut sum = 0.0;
+        let mut count = 0;
+        let mut min = u64::MAX;
+        let mut max = 0;
+        let mut buckets: Vec<_> = self
+            .inner
+            .cumulative
+            .upper_bounds()
+            .map(|upper_bound| (upper_bound, 0))
+            .collect();
+
+        for slice in &*self.inner.slices {
+            let in_window = match slice.epoch.load(Ordering::Acquire).checked_sub(1) {
+                Some(seen) => epoch
+                    .checked_sub(seen)
+                    .is_some_and(|age| age < self.inner.slices.len() as u64),
+                None => false,
+            };
+
+            if !in_window {
+                continue;
+            }
+
+            sum += slice.sum.load();
+            count += slice.counters[0].load(Ordering::Relaxed);
+            min = min.min(slice.counters[1].load(Ordering::Relaxed));
+            max = max.max(slice.counters[2].load(Ordering::Relaxed));
+
+            for (i, (_upper_bound, n)) in buckets.iter_mut().enumerate() {
+                *n += slice.counters[3 + i].load(Ordering::Relaxed);
+            }
+        }
+
+        let (min, max) = min_max(min, max);
+
+        HistogramSnapshot {
+            sum: sum * 1E-9,
+            count,
+            min,
+            max,
+            buckets,
+        }
+    }
+
+    /// Returns a gauge reporting the quantile `q` of the observations of the
+    /// window, see [`HistogramSnapshot::quantile`].
+    ///
+    /// The gauge can be registered as a separate metric, and also read
+    /// in-process, e.g. to shed load when latency goes up.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::histogram::RollingTimeHistogram;
+    /// # use std::time::Duration;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let histogram =
+    ///     RollingTimeHistogram::new([0.1, 1.0].into_iter(), Duration::from_secs(60), 6);
+    /// let p99 = histogram.quantile_gauge(0.99);
+    ///
+    /// registry.register("latency_p99", "Request latency p99 over a minute", Box::new(p99.clone()));
+    ///
+    /// assert_eq!(p99.get(), None);
+    ///
+    /// histogram.observe_secs(0.5);
+    ///
+    /// assert_eq!(p99.get(), Some(0.991));
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP latency_p99 Request latency p99 over a minute.\n",
+    ///         "# TYPE latency_p99 gauge\n",
+    ///         "latency_p99 0.991\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn quantile_gauge(&self, q: f64) -> QuantileGauge<C> {
+        QuantileGauge {
+            histogram: self.clone(),
+            quantile: q,
+        }
+    }
+
+    /// Returns the index of the current period of time.
+    fn epoch(&self) -> u64 {
+        let elapsed = self
+            .inner
+            .clock
+            .now()
+            .saturating_duration_since(self.inner.start);
+
+        duration_nanos(elapsed) / self.inner.slice_nanos
+    }
+}
+
+impl<C: Clock> TypedMetric for RollingTimeHistogram<C> {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl<C: Clock> EncodeMetric for RollingTimeHistogram<C> {
+    fn encode(&self, encoder: Encoder) -> Result<(), std::io::Error> {
+        self.inner.cumulative.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A gauge reporting a quantile of the window of a
+/// [`RollingTimeHistogram`], returned by
+/// [`RollingTimeHistogram::quantile_gauge`].
+///
+/// It is encoded as `NaN` while the window is empty.
+#[derive(Debug)]
+pub struct QuantileGauge<C: Clock = MonotonicClock> {
+    histogram: RollingTimeHistogram<C>,
+    quantile: f64,
+}
+
+impl<C: Clock> QuantileGauge<C> {
+    /// Returns the quantile of the window, in seconds, or `None` if the
+    /// window is empty.
+    pub fn get(&self) -> Option<f64> {
+        self.histogram.window_snapshot().quantile(self.quantile)
+    }
+}
+
+impl<C: Clock> Clone for QuantileGauge<C> {
+    fn clone(&self) -> Self {
+        Self {
+            histogram: self.histogram.clone(),
+            quantile: self.quantile,
+        }
+    }
+}
+
+impl<C: Clock> TypedMetric for QuantileGauge<C> {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl<C: Clock> EncodeMetric for QuantileGauge<C> {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(self.get().unwrap_or(f64::NAN))?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
@@ -0,0 +1,197 @@
+use super::{duration_nanos, min_max, seconds, secs_nanos, HistogramSnapshot, NanosSum, TimeUnit};
+use prometheus_client::encoding::text::{EncodeMetric, Encoder};
+use prometheus_client::metrics::{MetricType, TypedMetric};
+use std::iter::once;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Number of counters fitting in a [`Line`].
+const LINE_LEN: usize = 16;
+
+/// A lock-free histogram for tracking time, split in shards which are each
+/// observed by a subset of the threads.
+///
+/// Each thread always observes the same shard, and each shard lives on its
+/// own cache lines, so threads observing the histogram concurrently don't
+/// contend on the same counters. Shards are only aggregated when taking a
+/// snapshot or encoding the histogram, which is more expensive than with a
+/// [`TimeHistogram`](super::TimeHistogram).
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::ShardedTimeHistogram;
+/// # use std::thread;
+/// #
+/// let histogram = ShardedTimeHistogram::with_shards([0.1, 1.0].into_iter(), 4);
+///
+/// thread::scope(|scope| {
+///     for _ in 0..8 {
+///         scope.spawn(|| histogram.observe(50_000_000));
+///     }
+/// });
+///
+/// let snapshot = histogram.snapshot();
+///
+/// assert_eq!(snapshot.count(), 8);
+/// assert_eq!(snapshot.buckets()[0], (0.1, 8));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ShardedTimeHistogram {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    upper_bounds: Box<[f64]>,
+    shards: Box<[Shard]>,
+}
+
+/// A shard, aligned so that its sum doesn't share a cache line with other
+/// shards.
+#[derive(Debug)]
+#[repr(align(128))]
+struct Shard {
+    sum: NanosSum,
+    /// The counters of the shard: its count, its minimum, its maximum, then
+    /// its buckets.
+    lines: Box<[Line]>,
+}
+
+#[derive(Debug)]
+#[repr(align(128))]
+struct Line([AtomicU64; LINE_LEN]);
+
+impl ShardedTimeHistogram {
+    /// Creates a histogram with as many shards as the available
+    /// parallelism, see [`ShardedTimeHistogram::with_shards`].
+    pub fn new(buckets: impl Iterator<Item = f64>) -> Self {
+        let shards = thread::available_parallelism().map_or(1, |n| n.get());
+
+        Self::with_shards(buckets, shards)
+    }
+
+    /// Creates a histogram with the given bucket upper bounds, in seconds,
+    /// and number of shards.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub fn with_shards(buckets: impl Iterator<Item = f64>, shards: usize) -> Self {
+        let upper_bounds: Box<[f64]> = buckets.chain(once(f64::MAX)).collect();
+
+        debug_assert!(
+            upper_bounds.windows(2).all(|w| w[0] <= w[1]),
+            "bucket upper bounds must be sorted",
+        );
+
+        let lines = (3 + upper_bounds.len()).div_ceil(LINE_LEN);
+
+        Self {
+            inner: Arc::new(Inner {
+                shards: (0..shards.max(1))
+                    .map(|_| {
+                        let shard = Shard {
+                            sum: NanosSum::new(),
+                            lines: (0..lines).map(|_| Line(Default::default())).collect(),
+                        };
+                        shard.counter(1).store(u64::MAX, Ordering::Relaxed);
+                        shard
+                    })
+                    .collect(),
+                upper_bounds,
+            }),
+        }
+    }
+
+    pub fn observe(&self, nanos: u64) {
+        let shard = &self.inner.shards[shard_index() % self.inner.shards.len()];
+        let secs = seconds(nanos);
+        let bucket = self
+            .inner
+            .upper_bounds
+            .partition_point(|upper_bound| *upper_bound < secs);
+
+        shard.sum.add(nanos as f64);
+        shard.counter(0).fetch_add(1, Ordering::Relaxed);
+        shard.counter(1).fetch_min(nanos, Ordering::Relaxed);
+        shard.counter(2).fetch_max(nanos, Ordering::Relaxed);
+
+        if bucket < self.inner.upper_bounds.len() {
+            shard.counter(3 + bucket).fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Observes a duration, saturating at `u64::MAX` nanoseconds.
+    pub fn observe_duration(&self, duration: Duration) {
+        self.observe(duration_nanos(duration));
+    }
+
+    /// Observes a number of seconds, negative and NaN values are observed as
+    /// zero.
+    pub fn observe_secs(&self, secs: f64) {
+        self.observe(secs_nanos(secs));
+    }
+
+    /// Aggregates all shards in a snapshot.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        let mut sum = 0.0;
+        let mut count = 0;
+        let mut min = u64::MAX;
+        let mut max = 0;
+        let mut buckets: Vec<_> = self.inner.upper_bounds.iter().map(|b| (*b, 0)).collect();
+
+        for shard in &*self.inner.shards {
+            sum += shard.sum.load();
+            count += shard.counter(0).load(Ordering::Relaxed);
+            min = min.min(shard.counter(1).load(Ordering::Relaxed));
+            max = max.max(shard.counter(2).load(Ordering::Relaxed));
+
+            for (i, (_upper_bound, n)) in buckets.iter_mut().enumerate() {
+                *n += shard.counter(3 + i).load(Ordering::Relaxed);
+            }
+        }
+
+        let (min, max) = min_max(min, max);
+
+        HistogramSnapshot {
+            sum: sum * 1E-9,
+            count,
+            min,
+            max,
+            buckets,
+        }
+    }
+}
+
+impl Shard {
+    fn counter(&self, i: usize) -> &AtomicU64 {
+        &self.lines[i / LINE_LEN].0[i % LINE_LEN]
+    }
+}
+
+/// Returns the index of the current thread, used to pick its shard.
+fn shard_index() -> usize {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static INDEX: usize = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+
+    INDEX.with(|index| *index)
+}
+
+impl TypedMetric for ShardedTimeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for ShardedTimeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), std::io::Error> {
+        self.snapshot()
+            .encode_with_exemplars(&[], TimeUnit::Seconds, &mut encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
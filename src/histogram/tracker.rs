@@ -0,0 +1,85 @@
+use super::{HistogramTimer, TimeHistogram};
+use prometheus_client::metrics::gauge::Gauge;
+
+/// Tracks requests with a gauge of the requests in flight and a histogram of
+/// their latency.
+///
+/// [`RequestTracker::start`] increments the gauge and starts a timer, and
+/// dropping the returned [`RequestGuard`] decrements the gauge and drops the
+/// timer, which records following the drop policy of the histogram.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::metrics::gauge::Gauge;
+/// # use prometools::histogram::{RequestTracker, TimeHistogram};
+/// #
+/// let in_flight = <Gauge>::default();
+/// let latency = TimeHistogram::new([0.1, 1.0].into_iter());
+/// let tracker = RequestTracker::new(in_flight.clone(), latency.clone());
+///
+/// let request = tracker.start();
+///
+/// assert_eq!(in_flight.get(), 1);
+/// assert_eq!(latency.count(), 0);
+///
+/// drop(request);
+///
+/// assert_eq!(in_flight.get(), 0);
+/// assert_eq!(latency.count(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct RequestTracker {
+    in_flight: Gauge,
+    histogram: TimeHistogram,
+}
+
+impl RequestTracker {
+    pub fn new(in_flight: Gauge, histogram: TimeHistogram) -> Self {
+        Self {
+            in_flight,
+            histogram,
+        }
+    }
+
+    /// Returns the gauge of the requests in flight.
+    pub fn in_flight(&self) -> &Gauge {
+        &self.in_flight
+    }
+
+    /// Returns the histogram of the latency of the requests.
+    pub fn histogram(&self) -> &TimeHistogram {
+        &self.histogram
+    }
+
+    /// Starts tracking a request, until the returned guard is dropped.
+    pub fn start(&self) -> RequestGuard {
+        self.in_flight.inc();
+
+        RequestGuard {
+            in_flight: self.in_flight.clone(),
+            timer: self.histogram.start_timer(),
+        }
+    }
+}
+
+/// A request in flight, returned by [`RequestTracker::start`].
+#[must_use = "the request stops being tracked when the guard is dropped"]
+pub struct RequestGuard {
+    in_flight: Gauge,
+    timer: HistogramTimer,
+}
+
+impl RequestGuard {
+    /// Returns the timer of the request, e.g. to pause it while waiting on
+    /// the client.
+    pub fn timer_mut(&mut self) -> &mut HistogramTimer {
+        &mut self.timer
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.in_flight.dec();
+    }
+}
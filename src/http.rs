@@ -0,0 +1,106 @@
+//! The HTTP client of the push-based integrations.
+
+use std::fmt;
+use std::io;
+use std::time::Duration;
+use url::Url;
+
+/// An `http://` or `https://` URL to which requests are posted.
+#[derive(Clone, Debug)]
+pub(crate) struct Endpoint {
+    url: Url,
+    agent: ureq::Agent,
+}
+
+impl Endpoint {
+    pub(crate) fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidUrl(url.to_owned());
+        let url = Url::parse(url).map_err(|_| invalid())?;
+
+        if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            url,
+            agent: ureq::AgentBuilder::new()
+                .user_agent(concat!("prometools/", env!("CARGO_PKG_VERSION")))
+                .build(),
+        })
+    }
+
+    /// Posts `body`, with the given headers in addition to `Host`,
+    /// `Content-Length` and `User-Agent`.
+    pub(crate) fn post(
+        &self,
+        headers: &[(&str, &str)],
+        body: &[u8],
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let mut request = self.agent.request_url("POST", &self.url).timeout(timeout);
+
+        for (name, value) in headers {
+            if !is_valid_header(name, value) {
+                return Err(Error::InvalidHeader(name.to_string()));
+            }
+
+            request = request.set(name, value);
+        }
+
+        match request.send_bytes(body) {
+            Ok(_response) => Ok(()),
+            Err(ureq::Error::Status(status, _response)) => Err(Error::Status(status)),
+            Err(ureq::Error::Transport(err)) => Err(Error::Io(io::Error::other(err))),
+        }
+    }
+}
+
+/// Returns whether a header has a valid name and a value without control
+/// characters, which could otherwise inject headers into the request.
+fn is_valid_header(name: &str, value: &str) -> bool {
+    let is_tchar = |b: u8| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b);
+
+    !name.is_empty()
+        && name.bytes().all(is_tchar)
+        && value.bytes().all(|b| b == b'\t' || !b.is_ascii_control())
+}
+
+/// An error pushing metrics.
+#[derive(Debug)]
+pub enum Error {
+    /// The URL of the receiver isn't a valid `http://` or `https://` URL.
+    InvalidUrl(String),
+    /// A header added to the requests has an invalid name, or a value with
+    /// control characters such as line breaks.
+    InvalidHeader(String),
+    /// The request couldn't be sent or its response received.
+    Io(io::Error),
+    /// The receiver responded with a non-2xx status code.
+    Status(u16),
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(url) => write!(f, "invalid receiver URL {url}"),
+            Self::InvalidHeader(name) => write!(f, "invalid request header {name}"),
+            Self::Io(err) => write!(f, "push request failed: {err}"),
+            Self::Status(status) => write!(f, "receiver responded with {status}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::InvalidUrl(_) | Self::InvalidHeader(_) | Self::Status(_) => None,
+        }
+    }
+}
@@ -0,0 +1,113 @@
+//! Label helpers.
+
+use prometheus_client::{
+    encoding::text::{Encode, EncodeMetric, Encoder},
+    metrics::{MetricType, TypedMetric},
+};
+use std::{
+    io,
+    ops::{Deref, DerefMut},
+};
+
+/// A wrapper of a metric which adds a fixed label set to its samples, e.g.
+/// `shard="3"`, without a [`Family`](prometheus_client::metrics::family::Family).
+///
+/// prometheus-client only lets an encoder have one label set, so metrics
+/// adding their own labels, like families and info metrics, can't be
+/// wrapped. Register them in a sub-registry with
+/// [`Registry::sub_registry_with_label`](prometheus_client::registry::Registry::sub_registry_with_label)
+/// instead.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::counter::Counter,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::{labels::WithConstLabels, nonstandard::NonstandardUnsuffixedCounter};
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = WithConstLabels::new(<Counter>::default(), vec![("shard", "3")]);
+/// let responses = WithConstLabels::new(
+///     NonstandardUnsuffixedCounter::<u64>::default(),
+///     vec![("shard", "3"), ("role", "primary")],
+/// );
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+/// registry.register("responses", "Number of responses", Box::new(responses.clone()));
+///
+/// requests.inc();
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total{shard=\"3\"} 1\n",
+///         "# HELP responses Number of responses.\n",
+///         "# TYPE responses counter\n",
+///         "responses{shard=\"3\",role=\"primary\"} 0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct WithConstLabels<M, S> {
+    metric: M,
+    label_set: S,
+}
+
+impl<M, S> WithConstLabels<M, S>
+where
+    S: Encode,
+{
+    pub fn new(metric: M, label_set: S) -> Self {
+        Self { metric, label_set }
+    }
+
+    /// Returns the label set added to the samples of the metric.
+    pub fn label_set(&self) -> &S {
+        &self.label_set
+    }
+}
+
+impl<M, S> Deref for WithConstLabels<M, S> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.metric
+    }
+}
+
+impl<M, S> DerefMut for WithConstLabels<M, S> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.metric
+    }
+}
+
+impl<M: TypedMetric, S> TypedMetric for WithConstLabels<M, S> {
+    const TYPE: MetricType = M::TYPE;
+}
+
+impl<M, S> EncodeMetric for WithConstLabels<M, S>
+where
+    M: EncodeMetric,
+    S: Encode,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        self.metric.encode(encoder.with_label_set(&self.label_set))
+    }
+
+    fn metric_type(&self) -> MetricType {
+        self.metric.metric_type()
+    }
+}
@@ -1,7 +1,159 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 
+pub mod buckets;
+pub mod constant;
+mod created;
+pub mod encoding;
+pub mod gauge;
+pub mod graphite;
 pub mod histogram;
+#[cfg(any(feature = "otlp", feature = "remote-write"))]
+mod http;
+pub mod labels;
+pub mod lint;
 pub mod nonstandard;
+#[cfg(feature = "otlp")]
+#[cfg_attr(docsrs, doc(cfg(feature = "otlp")))]
+pub mod otlp;
+pub mod registrant;
+#[cfg(feature = "remote-write")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-write")))]
+pub mod remote_write;
 #[cfg(feature = "serde")]
 #[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
 pub mod serde;
+#[cfg(feature = "tracing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing")))]
+pub mod tracing;
+pub mod unit;
+
+/// Registers a metric with its doc comment as help text, and returns it.
+///
+/// The metric is registered under the given name, with the first paragraph
+/// of its doc comment as help text, so that the two can't drift apart.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::counter::Counter,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::register_metric;
+/// #
+/// let mut registry = <Registry>::default();
+///
+/// let requests: Counter = register_metric!(
+///     registry,
+///     /// Number of requests.
+///     ///
+///     /// This paragraph is not part of the help text.
+///     requests = Counter::default(),
+/// );
+///
+/// requests.inc();
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use prometools_derive::register_metric;
+
+/// Records the execution time of a function in a
+/// [`TimeHistogram`](histogram::TimeHistogram).
+///
+/// The `histogram` argument is an expression evaluating to the histogram,
+/// or to a reference or smart pointer to it, e.g. a path to a static. The
+/// time is recorded however the function exits, including early returns,
+/// `?` and panics. The time of an `async` function is measured from its
+/// first poll, and the [`DropPolicy`](histogram::DropPolicy) of the
+/// histogram applies if it is dropped before completing.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::{histogram::TimeHistogram, timed};
+/// # use std::sync::LazyLock;
+/// #
+/// static PARSE_DURATION: LazyLock<TimeHistogram> =
+///     LazyLock::new(|| TimeHistogram::new([0.001, 0.01].into_iter()));
+///
+/// #[timed(histogram = PARSE_DURATION)]
+/// fn parse(input: &str) -> Result<u32, std::num::ParseIntError> {
+///     let n = input.parse()?;
+///     Ok(n)
+/// }
+///
+/// assert!(parse("42").is_ok());
+/// assert!(parse("forty-two").is_err());
+/// assert_eq!(PARSE_DURATION.snapshot().count(), 2);
+/// ```
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use prometools_derive::timed;
+
+/// Starts a timer recording in a
+/// [`TimeHistogram`](histogram::TimeHistogram) when the current scope exits.
+///
+/// The histogram can be given directly, or as a
+/// [`Family`](prometheus_client::metrics::family::Family) of histograms
+/// followed by the label set of the series to record in. The timer follows
+/// the [`DropPolicy`](histogram::DropPolicy) of its histogram.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::metrics::family::Family;
+/// # use prometools::{histogram::TimeHistogram, time_scope};
+/// #
+/// let latency = TimeHistogram::new([1.0].into_iter());
+/// let family = Family::<Vec<(&str, &str)>, TimeHistogram>::default();
+///
+/// {
+///     time_scope!(latency);
+///     time_scope!(family, vec![("method", "GET")]);
+/// }
+///
+/// assert_eq!(latency.count(), 1);
+/// assert_eq!(family.get_or_create(&vec![("method", "GET")]).count(), 1);
+/// ```
+#[macro_export]
+macro_rules! time_scope {
+    ($histogram:expr $(,)?) => {
+        let _prometools_timer = $crate::histogram::TimeHistogram::start_timer(&$histogram);
+    };
+    ($family:expr, $label_set:expr $(,)?) => {
+        let _prometools_timer =
+            $crate::histogram::TimeHistogram::start_timer(&$family.get_or_create(&$label_set));
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use prometheus_client;
+
+    /// Constrains the output of an async block, so that `?` can be used in
+    /// it.
+    pub fn expect_output<T, F>(future: F) -> F
+    where
+        F: std::future::Future<Output = T>,
+    {
+        future
+    }
+}
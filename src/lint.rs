@@ -0,0 +1,374 @@
+//! Checks for the compliance of exposed metrics with OpenMetrics.
+//!
+//! This is similar to `promtool check metrics`, but runs in-process and
+//! returns structured diagnostics, so that it can be used in tests.
+
+use prometheus_client::{
+    encoding::text::{encode, EncodeMetric},
+    registry::Registry,
+};
+use std::{collections::HashSet, fmt, io};
+
+/// A problem found in the exposed metrics.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Diagnostic {
+    /// The line on which the problem was found, starting at 1.
+    pub line: usize,
+    pub kind: DiagnosticKind,
+}
+
+/// The kind of a [`Diagnostic`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[non_exhaustive]
+pub enum DiagnosticKind {
+    /// The line could not be parsed.
+    Malformed,
+    /// A metric name is not valid.
+    InvalidMetricName(String),
+    /// A label name is not valid.
+    InvalidLabelName(String),
+    /// A label name appears more than once in the same sample.
+    DuplicateLabel(String),
+    /// The metric type of a family is not known.
+    UnknownType(String),
+    /// A family was described more than once.
+    DuplicateFamily(String),
+    /// A family with a unit doesn't have the unit as a suffix of its name.
+    MissingUnitSuffix { family: String, unit: String },
+    /// A sample doesn't belong to the family described before it.
+    UnexpectedSample(String),
+    /// A sample name doesn't have a suffix allowed for its family type.
+    InvalidSuffix { family: String, sample: String },
+    /// A histogram bucket has no `le` label.
+    MissingBucketBound(String),
+    /// A sample value is not a number.
+    InvalidValue(String),
+    /// The same series appears more than once.
+    DuplicateSeries(String),
+    /// The exposition doesn't end with `# EOF`.
+    MissingEof,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => f.write_str("malformed line"),
+            Self::InvalidMetricName(name) => write!(f, "invalid metric name {name:?}"),
+            Self::InvalidLabelName(name) => write!(f, "invalid label name {name:?}"),
+            Self::DuplicateLabel(name) => write!(f, "duplicate label {name:?}"),
+            Self::UnknownType(ty) => write!(f, "unknown metric type {ty:?}"),
+            Self::DuplicateFamily(name) => write!(f, "duplicate metric family {name:?}"),
+            Self::MissingUnitSuffix { family, unit } => {
+                write!(f, "metric family {family:?} should end with unit {unit:?}")
+            }
+            Self::UnexpectedSample(name) => {
+                write!(f, "sample {name:?} doesn't belong to the current family")
+            }
+            Self::InvalidSuffix { family, sample } => {
+                write!(f, "invalid sample {sample:?} for metric family {family:?}")
+            }
+            Self::MissingBucketBound(name) => write!(f, "bucket {name:?} has no \"le\" label"),
+            Self::InvalidValue(value) => write!(f, "invalid value {value:?}"),
+            Self::DuplicateSeries(series) => write!(f, "duplicate series {series}"),
+            Self::MissingEof => f.write_str("missing \"# EOF\""),
+        }
+    }
+}
+
+/// Encodes the registry and checks the result.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::{
+/// #     lint::{self, DiagnosticKind},
+/// #     nonstandard::NonstandardUnsuffixedCounter,
+/// # };
+/// #
+/// let mut registry = <Registry>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(Counter::<u64>::default()));
+/// assert_eq!(lint::check(&registry).unwrap(), []);
+///
+/// registry.register(
+///     "responses",
+///     "Number of responses",
+///     Box::new(NonstandardUnsuffixedCounter::<u64>::default()),
+/// );
+///
+/// let diagnostics = lint::check(&registry).unwrap();
+///
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(
+///     diagnostics[0].kind,
+///     DiagnosticKind::InvalidSuffix {
+///         family: "responses".to_owned(),
+///         sample: "responses".to_owned(),
+///     },
+/// );
+/// ```
+pub fn check<M>(registry: &Registry<M>) -> io::Result<Vec<Diagnostic>>
+where
+    M: EncodeMetric,
+{
+    let mut buf = vec![];
+    encode(&mut buf, registry)?;
+
+    let text =
+        String::from_utf8(buf).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+    Ok(check_text(&text))
+}
+
+/// Checks metrics in the OpenMetrics text format.
+pub fn check_text(text: &str) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+
+    for (i, line) in text.lines().enumerate() {
+        checker.line = i + 1;
+        checker.check_line(line);
+    }
+
+    if !checker.has_eof {
+        checker.report(DiagnosticKind::MissingEof);
+    }
+
+    checker.diagnostics
+}
+
+#[derive(Default)]
+struct Checker<'a> {
+    line: usize,
+    diagnostics: Vec<Diagnostic>,
+    family: Option<Family<'a>>,
+    families: HashSet<&'a str>,
+    series: HashSet<(&'a str, Vec<(&'a str, String)>)>,
+    has_eof: bool,
+}
+
+struct Family<'a> {
+    name: &'a str,
+    ty: Option<&'a str>,
+}
+
+impl<'a> Checker<'a> {
+    fn report(&mut self, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic {
+            line: self.line,
+            kind,
+        });
+    }
+
+    fn check_line(&mut self, line: &'a str) {
+        if self.has_eof {
+            return self.report(DiagnosticKind::Malformed);
+        }
+
+        if line == "# EOF" {
+            self.has_eof = true;
+        } else if let Some(rest) = line.strip_prefix("# ") {
+            self.check_metadata(rest);
+        } else {
+            self.check_sample(line);
+        }
+    }
+
+    fn check_metadata(&mut self, line: &'a str) {
+        let mut parts = line.splitn(3, ' ');
+
+        let (Some(keyword), Some(name)) = (parts.next(), parts.next()) else {
+            return self.report(DiagnosticKind::Malformed);
+        };
+        let value = parts.next().unwrap_or("");
+
+        if !is_metric_name(name) {
+            return self.report(DiagnosticKind::InvalidMetricName(name.to_owned()));
+        }
+
+        if !matches!(&self.family, Some(family) if family.name == name) {
+            if !self.families.insert(name) {
+                self.report(DiagnosticKind::DuplicateFamily(name.to_owned()));
+            }
+
+            self.family = Some(Family { name, ty: None });
+        }
+
+        match keyword {
+            "HELP" => {}
+            "TYPE" => {
+                if !TYPES.contains(&value) {
+                    self.report(DiagnosticKind::UnknownType(value.to_owned()));
+                }
+
+                if let Some(family) = &mut self.family {
+                    family.ty = Some(value);
+                }
+            }
+            "UNIT" => {
+                if !name
+                    .strip_suffix(value)
+                    .is_some_and(|name| name.ends_with('_'))
+                {
+                    self.report(DiagnosticKind::MissingUnitSuffix {
+                        family: name.to_owned(),
+                        unit: value.to_owned(),
+                    });
+                }
+            }
+            _ => self.report(DiagnosticKind::Malformed),
+        }
+    }
+
+    fn check_sample(&mut self, line: &'a str) {
+        let name_end = line.find(['{', ' ']).unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_end);
+
+        if !is_metric_name(name) {
+            return self.report(DiagnosticKind::InvalidMetricName(name.to_owned()));
+        }
+
+        let Some((labels, rest)) = self.parse_labels(rest) else {
+            return self.report(DiagnosticKind::Malformed);
+        };
+        let series = &line[..line.len() - rest.len()];
+
+        let Some(value) = rest
+            .strip_prefix(' ')
+            .and_then(|rest| rest.split(' ').next())
+        else {
+            return self.report(DiagnosticKind::Malformed);
+        };
+
+        if value.parse::<f64>().is_err() && !["+Inf", "-Inf", "NaN"].contains(&value) {
+            self.report(DiagnosticKind::InvalidValue(value.to_owned()));
+        }
+
+        self.check_suffix(name, &labels);
+
+        let mut sorted_labels = labels;
+        sorted_labels.sort();
+
+        if !self.series.insert((name, sorted_labels)) {
+            self.report(DiagnosticKind::DuplicateSeries(series.to_owned()));
+        }
+    }
+
+    fn check_suffix(&mut self, name: &str, labels: &[(&str, String)]) {
+        let Some(family) = &self.family else {
+            return self.report(DiagnosticKind::UnexpectedSample(name.to_owned()));
+        };
+
+        let Some(suffix) = name.strip_prefix(family.name) else {
+            return self.report(DiagnosticKind::UnexpectedSample(name.to_owned()));
+        };
+
+        let allowed: &[&str] = match family.ty.unwrap_or("unknown") {
+            "counter" => &["_total", "_created"],
+            "gauge" | "unknown" | "stateset" => &[""],
+            "info" => &["_info"],
+            "histogram" => &["_bucket", "_sum", "_count", "_created"],
+            "gaugehistogram" => &["_bucket", "_gsum", "_gcount"],
+            "summary" => &["", "_sum", "_count", "_created"],
+            _ => return,
+        };
+
+        if !allowed.contains(&suffix) {
+            let family = family.name.to_owned();
+
+            return self.report(DiagnosticKind::InvalidSuffix {
+                family,
+                sample: name.to_owned(),
+            });
+        }
+
+        if suffix == "_bucket" && !labels.iter().any(|(name, _)| *name == "le") {
+            self.report(DiagnosticKind::MissingBucketBound(name.to_owned()));
+        }
+    }
+
+    /// Parses an optional label set, returning the labels with their
+    /// unescaped values, and the rest of the line.
+    fn parse_labels(&mut self, line: &'a str) -> Option<(Vec<(&'a str, String)>, &'a str)> {
+        let mut labels = vec![];
+
+        let Some(mut rest) = line.strip_prefix('{') else {
+            return Some((labels, line));
+        };
+
+        loop {
+            if let Some(after) = rest.strip_prefix('}') {
+                return Some((labels, after));
+            }
+
+            let (name, after) = rest.split_once("=\"")?;
+
+            if !is_label_name(name) {
+                self.report(DiagnosticKind::InvalidLabelName(name.to_owned()));
+            }
+
+            if labels.iter().any(|(label, _)| *label == name) {
+                self.report(DiagnosticKind::DuplicateLabel(name.to_owned()));
+            }
+
+            let mut value = String::new();
+            let mut chars = after.char_indices();
+
+            rest = loop {
+                match chars.next()? {
+                    (i, '"') => break &after[i + 1..],
+                    (_, '\\') => match chars.next()? {
+                        (_, 'n') => value.push('\n'),
+                        (_, c @ ('"' | '\\')) => value.push(c),
+                        _ => return None,
+                    },
+                    (_, c) => value.push(c),
+                }
+            };
+
+            labels.push((name, value));
+
+            rest = match rest.strip_prefix(',') {
+                Some(after) if !after.starts_with('}') => after,
+                Some(_) => return None,
+                None if rest.starts_with('}') => rest,
+                None => return None,
+            };
+        }
+    }
+}
+
+const TYPES: &[&str] = &[
+    "counter",
+    "gauge",
+    "histogram",
+    "gaugehistogram",
+    "stateset",
+    "info",
+    "summary",
+    "unknown",
+];
+
+fn is_metric_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_' || c == ':')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == ':')
+}
+
+fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
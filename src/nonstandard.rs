@@ -1,32 +1,150 @@
 //! Metric types that don't follow the OpenTelemetry standard exactly.
 
+use crate::{
+    created,
+    histogram::{HistogramSnapshot, TimeHistogram},
+};
 use prometheus_client::{
     encoding::text::{Encode, EncodeMetric, Encoder},
     metrics::{
         counter::{Atomic, Counter},
+        gauge::{self, Gauge},
         MetricType, TypedMetric,
     },
 };
 use std::{
-    io,
+    io, iter,
     ops::{Deref, DerefMut},
-    sync::atomic::AtomicU64,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 /// A wrapper of [`prometheus_client::metrics::counter::Counter`] which does
 /// not suffix the name with `_total`.
-#[repr(transparent)]
-pub struct NonstandardUnsuffixedCounter<N = u64, A = AtomicU64>(pub Counter<N, A>);
+///
+/// It also records its creation time, see
+/// [`NonstandardUnsuffixedCounter::with_created`].
+pub struct NonstandardUnsuffixedCounter<N = u64, A = AtomicU64>(pub Counter<N, A>, Created);
+
+/// The creation time of a counter, and whether it is exposed.
+#[derive(Clone, Copy, Debug)]
+struct Created {
+    at: SystemTime,
+    expose: bool,
+}
+
+impl<N, A> NonstandardUnsuffixedCounter<N, A> {
+    /// Wraps a counter, recording the current time as its creation time.
+    pub fn new(counter: Counter<N, A>) -> Self {
+        Self(
+            counter,
+            Created {
+                at: SystemTime::now(),
+                expose: false,
+            },
+        )
+    }
+
+    /// Exposes the time at which the counter was created as the OpenMetrics
+    /// `_created` sample.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::nonstandard::NonstandardUnsuffixedCounter;
+    /// # use std::time::UNIX_EPOCH;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let counter = NonstandardUnsuffixedCounter::<u64>::default().with_created();
+    ///
+    /// registry.register("responses", "Number of responses", Box::new(counter.clone()));
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// let created = serialized
+    ///     .lines()
+    ///     .find_map(|line| line.strip_prefix("responses_created "))
+    ///     .unwrap();
+    /// let expected = counter.created().duration_since(UNIX_EPOCH).unwrap();
+    ///
+    /// assert!((created.parse::<f64>().unwrap() - expected.as_secs_f64()).abs() < 1e-3);
+    /// ```
+    pub fn with_created(mut self) -> Self {
+        self.1.expose = true;
+        self
+    }
+
+    /// Returns the time at which the counter was created.
+    pub fn created(&self) -> SystemTime {
+        self.1.at
+    }
+}
+
+impl NonstandardUnsuffixedCounter<f64, AtomicU64> {
+    /// Increases the counter by a duration in seconds, e.g. CPU time, and
+    /// returns the previous value.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{encoding::text::encode, registry::Registry};
+    /// # use prometools::nonstandard::NonstandardUnsuffixedCounter;
+    /// # use std::time::Duration;
+    /// #
+    /// let mut registry = <Registry>::default();
+    /// let cpu = NonstandardUnsuffixedCounter::<f64>::default();
+    ///
+    /// registry.register("cpu_seconds", "CPU time", Box::new(cpu.clone()));
+    ///
+    /// cpu.inc_by_duration(Duration::from_millis(1500));
+    /// cpu.inc_by(0.25);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP cpu_seconds CPU time.\n",
+    ///         "# TYPE cpu_seconds counter\n",
+    ///         "cpu_seconds 1.75\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn inc_by_duration(&self, duration: Duration) -> f64 {
+        self.inc_by(duration.as_secs_f64())
+    }
+}
+
+impl<N, A> From<Counter<N, A>> for NonstandardUnsuffixedCounter<N, A> {
+    fn from(counter: Counter<N, A>) -> Self {
+        Self::new(counter)
+    }
+}
 
 impl<N, A> Clone for NonstandardUnsuffixedCounter<N, A> {
     fn clone(&self) -> Self {
-        Self(self.0.clone())
+        Self(self.0.clone(), self.1)
     }
 }
 
 impl<N, A: Default> Default for NonstandardUnsuffixedCounter<N, A> {
     fn default() -> Self {
-        Self(Counter::default())
+        Self::new(Counter::default())
     }
 }
 
@@ -58,7 +176,13 @@ where
         let mut value_encoder = bucket_encoder.no_bucket()?;
         let mut exemplar_encoder = value_encoder.encode_value(self.get())?;
 
-        exemplar_encoder.no_exemplar()
+        exemplar_encoder.no_exemplar()?;
+
+        if self.1.expose {
+            created::encode(&mut encoder, self.1.at)?;
+        }
+
+        Ok(())
     }
 
     fn metric_type(&self) -> MetricType {
@@ -107,3 +231,1244 @@ where
         Self::TYPE
     }
 }
+
+/// A wrapper of [`TimeHistogram`] which also exposes the minimum and maximum
+/// observed values as `_min` and `_max` samples, which OpenMetrics doesn't
+/// allow in histograms.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::{
+/// #     histogram::TimeHistogram,
+/// #     nonstandard::NonstandardMinMaxTimeHistogram,
+/// # };
+/// #
+/// let mut registry = <Registry>::default();
+/// let histogram = NonstandardMinMaxTimeHistogram(TimeHistogram::new([1.0].into_iter()));
+///
+/// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+///
+/// histogram.observe_secs(0.5);
+/// histogram.observe_secs(2.0);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP latency Request latency.\n",
+///         "# TYPE latency histogram\n",
+///         "latency_min 0.5\n",
+///         "latency_max 2.0\n",
+///         "latency_sum 2.5\n",
+///         "latency_count 2\n",
+///         "latency_bucket{le=\"1.0\"} 1\n",
+///         "latency_bucket{le=\"+Inf\"} 2\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct NonstandardMinMaxTimeHistogram(pub TimeHistogram);
+
+impl Deref for NonstandardMinMaxTimeHistogram {
+    type Target = TimeHistogram;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for NonstandardMinMaxTimeHistogram {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl TypedMetric for NonstandardMinMaxTimeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for NonstandardMinMaxTimeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let snapshot = self.0.snapshot();
+
+        for (suffix, value) in [("min", snapshot.min()), ("max", snapshot.max())] {
+            if let Some(value) = value {
+                encoder
+                    .encode_suffix(suffix)?
+                    .no_bucket()?
+                    .encode_value(self.0.unit().scale_secs(value))?
+                    .no_exemplar()?;
+            }
+        }
+
+        self.0.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A wrapper of [`Counter`] which is encoded with its increase since it was
+/// last encoded, for pipelines expecting delta temporality such as OTLP.
+///
+/// The increase is consumed when encoded, so the counter must only be
+/// collected by a single exporter.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::nonstandard::NonstandardDeltaCounter;
+/// #
+/// let counter = NonstandardDeltaCounter::default();
+///
+/// counter.inc_by(3);
+/// assert_eq!(counter.delta(), 3);
+///
+/// counter.inc();
+/// assert_eq!(counter.delta(), 1);
+/// assert_eq!(counter.delta(), 0);
+/// assert_eq!(counter.get(), 4);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NonstandardDeltaCounter {
+    counter: Counter,
+    /// The value of the counter when it was last encoded.
+    last: Arc<AtomicU64>,
+}
+
+impl NonstandardDeltaCounter {
+    pub fn new(counter: Counter) -> Self {
+        Self {
+            last: Arc::new(AtomicU64::new(counter.get())),
+            counter,
+        }
+    }
+
+    /// Returns the increase of the counter since the last call, and starts a
+    /// new interval.
+    pub fn delta(&self) -> u64 {
+        let value = self.counter.get();
+        // Concurrent calls may read the counter in any order, so the last
+        // value must never move backwards, or the increase between their
+        // reads would be counted twice.
+        let previous = self.last.fetch_max(value, Ordering::Relaxed);
+
+        value.saturating_sub(previous)
+    }
+}
+
+impl From<Counter> for NonstandardDeltaCounter {
+    fn from(counter: Counter) -> Self {
+        Self::new(counter)
+    }
+}
+
+impl Deref for NonstandardDeltaCounter {
+    type Target = Counter;
+
+    fn deref(&self) -> &Self::Target {
+        &self.counter
+    }
+}
+
+impl TypedMetric for NonstandardDeltaCounter {
+    const TYPE: MetricType = MetricType::Counter;
+}
+
+impl EncodeMetric for NonstandardDeltaCounter {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .encode_suffix("total")?
+            .no_bucket()?
+            .encode_value(self.delta())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A wrapper of [`TimeHistogram`] which is encoded with the observations
+/// made since it was last encoded, for pipelines expecting delta temporality
+/// such as OTLP.
+///
+/// The observations are consumed when encoded, so the histogram must only be
+/// collected by a single exporter. Exemplars are encoded as usual.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::{histogram::TimeHistogram, nonstandard::NonstandardDeltaTimeHistogram};
+/// #
+/// let mut registry = <Registry>::default();
+/// let histogram = NonstandardDeltaTimeHistogram::new(TimeHistogram::new([1.0].into_iter()));
+///
+/// registry.register("latency", "Request latency", Box::new(histogram.clone()));
+///
+/// histogram.observe_secs(0.5);
+/// histogram.observe_secs(2.0);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert!(serialized.contains("latency_count 2\n"));
+///
+/// histogram.observe_secs(0.25);
+///
+/// let interval = histogram.delta_snapshot();
+///
+/// assert_eq!(interval.count(), 1);
+/// assert_eq!(interval.buckets(), [(1.0, 1), (f64::MAX, 0)]);
+/// assert_eq!(histogram.snapshot().count(), 3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct NonstandardDeltaTimeHistogram {
+    histogram: TimeHistogram,
+    /// The snapshot of the histogram when it was last encoded.
+    last: Arc<Mutex<HistogramSnapshot>>,
+}
+
+impl NonstandardDeltaTimeHistogram {
+    pub fn new(histogram: TimeHistogram) -> Self {
+        Self {
+            last: Arc::new(Mutex::new(histogram.snapshot())),
+            histogram,
+        }
+    }
+
+    /// Returns the observations made since the last call, and starts a new
+    /// interval.
+    ///
+    /// If the histogram was reset in the meantime, all its observations are
+    /// returned.
+    pub fn delta_snapshot(&self) -> HistogramSnapshot {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        let snapshot = self.histogram.snapshot();
+        let delta = match snapshot.diff(&last) {
+            Some(delta) if snapshot.count() >= last.count() => delta,
+            _ => snapshot.clone(),
+        };
+
+        *last = snapshot;
+
+        delta
+    }
+}
+
+impl From<TimeHistogram> for NonstandardDeltaTimeHistogram {
+    fn from(histogram: TimeHistogram) -> Self {
+        Self::new(histogram)
+    }
+}
+
+impl Deref for NonstandardDeltaTimeHistogram {
+    type Target = TimeHistogram;
+
+    fn deref(&self) -> &Self::Target {
+        &self.histogram
+    }
+}
+
+impl TypedMetric for NonstandardDeltaTimeHistogram {
+    const TYPE: MetricType = MetricType::Histogram;
+}
+
+impl EncodeMetric for NonstandardDeltaTimeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        self.histogram
+            .encode_snapshot(&self.delta_snapshot(), &mut encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A summary without quantiles, only exposing the `_sum` and `_count` of the
+/// observed values, like the summaries of older clients.
+///
+/// prometheus-client can't declare the `summary` type, so it is declared as
+/// `unknown`.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::SimpleSummary;
+/// #
+/// let mut registry = <Registry>::default();
+/// let summary = SimpleSummary::default();
+///
+/// registry.register("payload_bytes", "Size of payloads", Box::new(summary.clone()));
+///
+/// summary.observe(512.0);
+/// summary.observe(1536.0);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP payload_bytes Size of payloads.\n",
+///         "# TYPE payload_bytes unknown\n",
+///         "payload_bytes_sum 2048.0\n",
+///         "payload_bytes_count 2\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SimpleSummary {
+    inner: Arc<SummaryInner>,
+}
+
+#[derive(Debug, Default)]
+struct SummaryInner {
+    /// The bits of the sum, as a `f64`.
+    sum: AtomicU64,
+    count: AtomicU64,
+}
+
+impl SimpleSummary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn observe(&self, value: f64) {
+        let _ = self
+            .inner
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+        self.inner.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn sum(&self) -> f64 {
+        f64::from_bits(self.inner.sum.load(Ordering::Relaxed))
+    }
+
+    pub fn count(&self) -> u64 {
+        self.inner.count.load(Ordering::Relaxed)
+    }
+}
+
+impl TypedMetric for SimpleSummary {
+    const TYPE: MetricType = MetricType::Unknown;
+}
+
+impl EncodeMetric for SimpleSummary {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .encode_suffix("sum")?
+            .no_bucket()?
+            .encode_value(self.sum())?
+            .no_exemplar()?;
+        encoder
+            .encode_suffix("count")?
+            .no_bucket()?
+            .encode_value(self.count())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A lock-free histogram of current values, e.g. the ages of the items of a
+/// queue, whose buckets can go down as well as up.
+///
+/// **This is not a standard Prometheus histogram.** It is exposed with the
+/// `_bucket`, `_gsum` and `_gcount` suffixes of an OpenMetrics gauge
+/// histogram, but prometheus-client can't declare the `gaugehistogram`
+/// type, so it is declared as `unknown`. Scrapers thus ingest its samples
+/// as unrelated untyped series, and its buckets can't be queried with
+/// `histogram_quantile` like the buckets of a histogram.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::GaugeHistogram;
+/// #
+/// let mut registry = <Registry>::default();
+/// let histogram = GaugeHistogram::new([1.0, 10.0].into_iter());
+///
+/// registry.register("queue_age", "Age of queued items", Box::new(histogram.clone()));
+///
+/// histogram.observe(0.5);
+/// histogram.observe(5.0);
+/// histogram.observe(20.0);
+/// histogram.remove(5.0);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP queue_age Age of queued items.\n",
+///         "# TYPE queue_age unknown\n",
+///         "queue_age_gsum 20.5\n",
+///         "queue_age_gcount 2\n",
+///         "queue_age_bucket{le=\"1.0\"} 1\n",
+///         "queue_age_bucket{le=\"10.0\"} 1\n",
+///         "queue_age_bucket{le=\"+Inf\"} 2\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct GaugeHistogram {
+    inner: Arc<GaugeHistogramInner>,
+}
+
+#[derive(Debug)]
+struct GaugeHistogramInner {
+    /// The bits of the sum, as a `f64`.
+    sum: AtomicU64,
+    count: AtomicU64,
+    buckets: Vec<(f64, AtomicU64)>,
+}
+
+impl GaugeHistogram {
+    /// Creates a histogram with the given bucket upper bounds.
+    ///
+    /// The upper bounds must be sorted in increasing order.
+    pub fn new(buckets: impl Iterator<Item = f64>) -> Self {
+        let buckets: Vec<_> = buckets
+            .chain(iter::once(f64::MAX))
+            .map(|upper_bound| (upper_bound, AtomicU64::new(0)))
+            .collect();
+
+        debug_assert!(
+            buckets.windows(2).all(|w| w[0].0 <= w[1].0),
+            "bucket upper bounds must be sorted",
+        );
+
+        Self {
+            inner: Arc::new(GaugeHistogramInner {
+                sum: AtomicU64::new(0f64.to_bits()),
+                count: Default::default(),
+                buckets,
+            }),
+        }
+    }
+
+    /// Adds a value to the histogram.
+    pub fn observe(&self, value: f64) {
+        self.add_sum(value);
+        self.inner.count.fetch_add(1, Ordering::Relaxed);
+
+        if let Some((_upper_bound, n)) = self.bucket(value) {
+            n.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Removes a value previously added with [`GaugeHistogram::observe`].
+    ///
+    /// Counts saturate at zero if more values are removed than added.
+    pub fn remove(&self, value: f64) {
+        self.add_sum(-value);
+        decrement(&self.inner.count);
+
+        if let Some((_upper_bound, n)) = self.bucket(value) {
+            decrement(n);
+        }
+    }
+
+    /// Removes all values from the histogram.
+    pub fn reset(&self) {
+        self.inner.sum.store(0f64.to_bits(), Ordering::Relaxed);
+        self.inner.count.store(0, Ordering::Relaxed);
+
+        for (_upper_bound, n) in &self.inner.buckets {
+            n.store(0, Ordering::Relaxed);
+        }
+    }
+
+    /// Returns the current values of the histogram.
+    ///
+    /// Gauge histograms don't track their minimum and maximum.
+    pub fn snapshot(&self) -> HistogramSnapshot {
+        HistogramSnapshot::from_parts(
+            f64::from_bits(self.inner.sum.load(Ordering::Relaxed)),
+            self.inner.count.load(Ordering::Relaxed),
+            self.inner
+                .buckets
+                .iter()
+                .map(|(upper_bound, n)| (*upper_bound, n.load(Ordering::Relaxed)))
+                .collect(),
+        )
+    }
+
+    fn bucket(&self, value: f64) -> Option<&(f64, AtomicU64)> {
+        let i = self
+            .inner
+            .buckets
+            .partition_point(|(upper_bound, _n)| *upper_bound < value);
+
+        self.inner.buckets.get(i)
+    }
+
+    fn add_sum(&self, value: f64) {
+        let _ = self
+            .inner
+            .sum
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |sum| {
+                Some((f64::from_bits(sum) + value).to_bits())
+            });
+    }
+}
+
+fn decrement(n: &AtomicU64) {
+    let _ = n.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+        Some(n.saturating_sub(1))
+    });
+}
+
+impl TypedMetric for GaugeHistogram {
+    const TYPE: MetricType = MetricType::Unknown;
+}
+
+impl EncodeMetric for GaugeHistogram {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let snapshot = self.snapshot();
+
+        encoder
+            .encode_suffix("gsum")?
+            .no_bucket()?
+            .encode_value(snapshot.sum())?
+            .no_exemplar()?;
+        encoder
+            .encode_suffix("gcount")?
+            .no_bucket()?
+            .encode_value(snapshot.count())?
+            .no_exemplar()?;
+
+        let mut cumulative = 0;
+        for (upper_bound, count) in snapshot.buckets() {
+            cumulative += count;
+            encoder
+                .encode_suffix("bucket")?
+                .encode_bucket(*upper_bound)?
+                .encode_value(cumulative)?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A wrapper of [`Gauge`] declared with the `unknown` type, for values whose
+/// semantics aren't known, e.g. proxied from another system.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::Unknown;
+/// #
+/// let mut registry = <Registry>::default();
+/// let value = <Unknown>::default();
+///
+/// registry.register("upstream_value", "Value reported upstream", Box::new(value.clone()));
+///
+/// value.set(1.5);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP upstream_value Value reported upstream.\n",
+///         "# TYPE upstream_value unknown\n",
+///         "upstream_value 1.5\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Unknown<N = f64, A = AtomicU64>(pub Gauge<N, A>);
+
+impl<N, A> From<Gauge<N, A>> for Unknown<N, A> {
+    fn from(gauge: Gauge<N, A>) -> Self {
+        Self(gauge)
+    }
+}
+
+impl<N, A> Clone for Unknown<N, A> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<N, A: Default> Default for Unknown<N, A> {
+    fn default() -> Self {
+        Self(Gauge::default())
+    }
+}
+
+impl<N, A> Deref for Unknown<N, A> {
+    type Target = Gauge<N, A>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<N, A> DerefMut for Unknown<N, A> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<N, A> TypedMetric for Unknown<N, A> {
+    const TYPE: MetricType = MetricType::Unknown;
+}
+
+impl<N, A> EncodeMetric for Unknown<N, A>
+where
+    N: Encode,
+    A: gauge::Atomic<N>,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(self.get())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A gauge whose value is exposed with an explicit timestamp, e.g. for values
+/// collected from another system at a known time.
+///
+/// Nothing is exposed until a value is set.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::TimestampedGauge;
+/// # use std::time::{Duration, UNIX_EPOCH};
+/// #
+/// let mut registry = <Registry>::default();
+/// let gauge = TimestampedGauge::default();
+///
+/// registry.register("replica_lag", "Lag reported by the replica", Box::new(gauge.clone()));
+///
+/// gauge.set_with_timestamp(2.5, UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP replica_lag Lag reported by the replica.\n",
+///         "# TYPE replica_lag gauge\n",
+///         "replica_lag 2.5 1700000000.0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TimestampedGauge {
+    sample: Arc<Mutex<Option<(f64, SystemTime)>>>,
+}
+
+impl TimestampedGauge {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the gauge, observed at `timestamp`.
+    pub fn set_with_timestamp(&self, value: f64, timestamp: SystemTime) {
+        *self.sample.lock().unwrap_or_else(PoisonError::into_inner) = Some((value, timestamp));
+    }
+
+    /// Returns the value of the gauge and when it was observed, unless it
+    /// was never set.
+    pub fn get(&self) -> Option<(f64, SystemTime)> {
+        *self.sample.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl TypedMetric for TimestampedGauge {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl EncodeMetric for TimestampedGauge {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let Some((value, timestamp)) = self.get() else {
+            return Ok(());
+        };
+
+        // prometheus-client can't encode timestamps, so the timestamp is
+        // written right after the value.
+        encoder
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(ValueWithTimestamp { value, timestamp })?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+struct ValueWithTimestamp {
+    value: f64,
+    timestamp: SystemTime,
+}
+
+impl Encode for ValueWithTimestamp {
+    fn encode(&self, writer: &mut dyn io::Write) -> Result<(), io::Error> {
+        let secs = self
+            .timestamp
+            .duration_since(UNIX_EPOCH)
+            .map_or(0.0, |since| since.as_secs_f64());
+
+        self.value.encode(writer)?;
+        writer.write_all(b" ")?;
+        secs.encode(writer)
+    }
+}
+
+/// What a [`MirroredCounter`] does when it is set to a value lower than its
+/// current value.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Regression {
+    /// Ignores the value, the counter keeps its current value until it is
+    /// set to a higher one.
+    #[default]
+    Ignore,
+    /// Assumes the mirrored counter was reset, and adds the value to the
+    /// counter, then keeps following the mirrored counter from there.
+    Accumulate,
+}
+
+/// A counter mirroring the absolute value of a counter from another system,
+/// which never decreases.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::nonstandard::{MirroredCounter, Regression};
+/// #
+/// let ignoring = MirroredCounter::new(Regression::Ignore);
+/// let accumulating = MirroredCounter::new(Regression::Accumulate);
+///
+/// for value in [10, 15, 4, 12] {
+///     ignoring.set(value);
+///     accumulating.set(value);
+/// }
+///
+/// assert_eq!(ignoring.get(), 15);
+/// assert_eq!(accumulating.get(), 27);
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MirroredCounter {
+    state: Arc<Mutex<Mirrored>>,
+    regression: Regression,
+}
+
+#[derive(Debug, Default)]
+struct Mirrored {
+    value: u64,
+    /// The last value of the mirrored counter.
+    upstream: u64,
+}
+
+impl MirroredCounter {
+    pub fn new(regression: Regression) -> Self {
+        Self {
+            state: Default::default(),
+            regression,
+        }
+    }
+
+    /// Sets the counter to the value of the mirrored counter, following the
+    /// [`Regression`] policy if it decreased.
+    pub fn set(&self, value: u64) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        state.value = match self.regression {
+            Regression::Ignore => state.value.max(value),
+            Regression::Accumulate if value < state.upstream => state.value.saturating_add(value),
+            Regression::Accumulate => state.value.saturating_add(value - state.upstream),
+        };
+        state.upstream = value;
+    }
+
+    pub fn get(&self) -> u64 {
+        self.state
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .value
+    }
+}
+
+impl TypedMetric for MirroredCounter {
+    const TYPE: MetricType = MetricType::Counter;
+}
+
+impl EncodeMetric for MirroredCounter {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        encoder
+            .encode_suffix("total")?
+            .no_bucket()?
+            .encode_value(self.get())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A pair of counters tracking successes out of attempts, exposed under the
+/// same name with the `_success_total` and `_attempts_total` suffixes by
+/// default, e.g. for availability ratios.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::Ratio;
+/// #
+/// let mut registry = <Registry>::default();
+/// let logins = Ratio::new();
+/// let checks = Ratio::new().with_suffixes("passed_total", "runs_total");
+///
+/// registry.register("logins", "Successful logins", Box::new(logins.clone()));
+/// registry.register("health_checks", "Passed health checks", Box::new(checks.clone()));
+///
+/// logins.observe(true);
+/// logins.observe(false);
+/// checks.observe(true);
+///
+/// assert_eq!(logins.ratio(), Some(0.5));
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP logins Successful logins.\n",
+///         "# TYPE logins counter\n",
+///         "logins_success_total 1\n",
+///         "logins_attempts_total 2\n",
+///         "# HELP health_checks Passed health checks.\n",
+///         "# TYPE health_checks counter\n",
+///         "health_checks_passed_total 1\n",
+///         "health_checks_runs_total 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct Ratio {
+    numerator: Counter,
+    denominator: Counter,
+    suffixes: (&'static str, &'static str),
+}
+
+impl Ratio {
+    pub fn new() -> Self {
+        Self {
+            numerator: Counter::default(),
+            denominator: Counter::default(),
+            suffixes: ("success_total", "attempts_total"),
+        }
+    }
+
+    /// Sets the suffixes of the numerator and denominator series.
+    pub fn with_suffixes(mut self, numerator: &'static str, denominator: &'static str) -> Self {
+        self.suffixes = (numerator, denominator);
+        self
+    }
+
+    /// Counts an attempt, and a success if `success` is true.
+    pub fn observe(&self, success: bool) {
+        // The denominator is incremented first, which makes readers seeing
+        // more successes than attempts unlikely, but not impossible as the
+        // counters are independent.
+        self.denominator.inc();
+
+        if success {
+            self.numerator.inc();
+        }
+    }
+
+    /// Returns the counter of successes.
+    pub fn numerator(&self) -> &Counter {
+        &self.numerator
+    }
+
+    /// Returns the counter of attempts.
+    pub fn denominator(&self) -> &Counter {
+        &self.denominator
+    }
+
+    /// Returns the ratio of successes, unless nothing was attempted.
+    ///
+    /// The ratio is clamped to 1, as a success may be read before its
+    /// attempt while racing with [`Ratio::observe`].
+    pub fn ratio(&self) -> Option<f64> {
+        let numerator = self.numerator.get();
+        let denominator = self.denominator.get();
+
+        (denominator > 0).then(|| (numerator as f64 / denominator as f64).min(1.0))
+    }
+}
+
+impl Default for Ratio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TypedMetric for Ratio {
+    const TYPE: MetricType = MetricType::Counter;
+}
+
+impl EncodeMetric for Ratio {
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let numerator = self.numerator.get();
+        let denominator = self.denominator.get();
+
+        for (suffix, value) in [(self.suffixes.0, numerator), (self.suffixes.1, denominator)] {
+            encoder
+                .encode_suffix(suffix)?
+                .no_bucket()?
+                .encode_value(value)?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A metric with a single sample, whose value can be read directly, see
+/// [`WithSuffix`].
+pub trait SingleValue {
+    type Value: Encode;
+
+    fn value(&self) -> Self::Value;
+}
+
+impl<N, A> SingleValue for Counter<N, A>
+where
+    N: Encode,
+    A: Atomic<N>,
+{
+    type Value = N;
+
+    fn value(&self) -> N {
+        self.get()
+    }
+}
+
+impl<N, A> SingleValue for Gauge<N, A>
+where
+    N: Encode,
+    A: gauge::Atomic<N>,
+{
+    type Value = N;
+
+    fn value(&self) -> N {
+        self.get()
+    }
+}
+
+impl<N, A> SingleValue for NonstandardUnsuffixedCounter<N, A>
+where
+    N: Encode,
+    A: Atomic<N>,
+{
+    type Value = N;
+
+    fn value(&self) -> N {
+        self.get()
+    }
+}
+
+/// A wrapper of a metric with a single sample, which replaces the suffix of
+/// the sample, e.g. to expose a counter as `_count` for an old consumer.
+///
+/// prometheus-client doesn't let encoders be intercepted, so only metrics
+/// implementing [`SingleValue`] can be wrapped.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::{counter::Counter, gauge::Gauge},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::nonstandard::WithSuffix;
+/// #
+/// let mut registry = <Registry>::default();
+/// let jobs = WithSuffix::new(<Counter>::default(), "count");
+/// let workers = WithSuffix::new(<Gauge>::default(), "current");
+/// let errors = WithSuffix::unsuffixed(<Counter>::default());
+///
+/// registry.register("jobs", "Number of jobs", Box::new(jobs.clone()));
+/// registry.register("workers", "Number of workers", Box::new(workers.clone()));
+/// registry.register("errors", "Number of errors", Box::new(errors.clone()));
+///
+/// jobs.inc();
+/// workers.set(4);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP jobs Number of jobs.\n",
+///         "# TYPE jobs counter\n",
+///         "jobs_count 1\n",
+///         "# HELP workers Number of workers.\n",
+///         "# TYPE workers gauge\n",
+///         "workers_current 4\n",
+///         "# HELP errors Number of errors.\n",
+///         "# TYPE errors counter\n",
+///         "errors 0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct WithSuffix<M> {
+    metric: M,
+    suffix: Option<&'static str>,
+}
+
+impl<M> WithSuffix<M> {
+    /// Wraps a metric, exposing its sample with the given suffix.
+    pub fn new(metric: M, suffix: &'static str) -> Self {
+        Self {
+            metric,
+            suffix: Some(suffix),
+        }
+    }
+
+    /// Wraps a metric, exposing its sample without any suffix.
+    pub fn unsuffixed(metric: M) -> Self {
+        Self {
+            metric,
+            suffix: None,
+        }
+    }
+}
+
+impl<M> Deref for WithSuffix<M> {
+    type Target = M;
+
+    fn deref(&self) -> &Self::Target {
+        &self.metric
+    }
+}
+
+impl<M> DerefMut for WithSuffix<M> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.metric
+    }
+}
+
+impl<M: TypedMetric> TypedMetric for WithSuffix<M> {
+    const TYPE: MetricType = M::TYPE;
+}
+
+impl<M> EncodeMetric for WithSuffix<M>
+where
+    M: SingleValue + TypedMetric,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        let mut bucket_encoder = match self.suffix {
+            Some(suffix) => encoder.encode_suffix(suffix)?,
+            None => encoder.no_suffix()?,
+        };
+
+        bucket_encoder
+            .no_bucket()?
+            .encode_value(self.metric.value())?
+            .no_exemplar()
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+/// A state from which several samples are computed, exposed by a
+/// [`MultiSeriesMetric`].
+pub trait MultiSeries {
+    /// Returns the suffix and value of each sample.
+    fn series(&self) -> Vec<(&'static str, f64)>;
+}
+
+/// A gauge exposing several suffixed samples computed from a shared state,
+/// e.g. `_avg`, `_min` and `_max` samples like some legacy exporters.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::nonstandard::{MultiSeries, MultiSeriesMetric};
+/// # use std::sync::Mutex;
+/// #
+/// #[derive(Default)]
+/// struct Temperatures(Mutex<Vec<f64>>);
+///
+/// impl MultiSeries for Temperatures {
+///     fn series(&self) -> Vec<(&'static str, f64)> {
+///         let values = self.0.lock().unwrap();
+///         let avg = values.iter().sum::<f64>() / values.len() as f64;
+///         let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+///         let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+///
+///         vec![("avg", avg), ("min", min), ("max", max)]
+///     }
+/// }
+///
+/// let mut registry = <Registry>::default();
+/// let temperatures = MultiSeriesMetric::new(Temperatures::default());
+///
+/// registry.register("temperature", "Temperature of the disks", Box::new(temperatures.clone()));
+///
+/// temperatures.0.lock().unwrap().extend([30.0, 40.0, 35.0]);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP temperature Temperature of the disks.\n",
+///         "# TYPE temperature gauge\n",
+///         "temperature_avg 35.0\n",
+///         "temperature_min 30.0\n",
+///         "temperature_max 40.0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct MultiSeriesMetric<T> {
+    state: Arc<T>,
+}
+
+impl<T> MultiSeriesMetric<T> {
+    pub fn new(state: T) -> Self {
+        Self {
+            state: Arc::new(state),
+        }
+    }
+}
+
+impl<T> Clone for MultiSeriesMetric<T> {
+    fn clone(&self) -> Self {
+        Self {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<T> Deref for MultiSeriesMetric<T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        &self.state
+    }
+}
+
+impl<T> TypedMetric for MultiSeriesMetric<T> {
+    const TYPE: MetricType = MetricType::Gauge;
+}
+
+impl<T> EncodeMetric for MultiSeriesMetric<T>
+where
+    T: MultiSeries,
+{
+    fn encode(&self, mut encoder: Encoder) -> Result<(), io::Error> {
+        for (suffix, value) in self.state.series() {
+            encoder
+                .encode_suffix(suffix)?
+                .no_bucket()?
+                .encode_value(value)?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
@@ -0,0 +1,395 @@
+//! Exporting metrics to an OpenTelemetry collector, with OTLP over HTTP.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then converted to OTLP metrics with cumulative temporality:
+//!
+//! - counters become monotonic sums, named after their samples without
+//!   their `_total` suffix, and started at their `_created` sample if any;
+//! - histograms become histograms with explicit bounds, their cumulative
+//!   buckets being converted to bucket counts;
+//! - other samples become gauges named after them;
+//! - labels become string attributes, and exemplars keep their `trace_id`
+//!   and `span_id` labels as trace and span IDs when they are valid
+//!   hexadecimal IDs.
+//!
+//! Data points without a `_created` sample are started at the creation of
+//! the exporter. Requests are sent with HTTP/1.1, over TLS for `https://`
+//! URLs.
+
+pub use crate::http::Error;
+
+use crate::encoding::message::Message;
+use crate::encoding::parse::{self, Exemplar, Family, Sample};
+use crate::http::Endpoint;
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Timeout used by [`OtlpExporter::new`] to connect, send and receive.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+/// `AGGREGATION_TEMPORALITY_CUMULATIVE`.
+const CUMULATIVE: u64 = 2;
+
+/// An exporter of metrics to an OpenTelemetry collector.
+///
+/// #### Examples
+///
+/// ```rust,no_run
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::otlp::OtlpExporter;
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = <Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// let exporter = OtlpExporter::new("http://localhost:4318/v1/metrics")
+///     .unwrap()
+///     .with_resource_attribute("service.name", "web");
+///
+/// // Exports every 60 seconds, until `task` is dropped.
+/// let task = exporter.spawn(Arc::new(registry), Duration::from_secs(60), |err| {
+///     eprintln!("OTLP export failed: {err}");
+/// });
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "otlp")))]
+pub struct OtlpExporter {
+    endpoint: Endpoint,
+    headers: Vec<(String, String)>,
+    resource: Vec<(String, String)>,
+    timeout: Duration,
+    start_time: SystemTime,
+}
+
+impl OtlpExporter {
+    /// Creates an exporter posting to the given `http://` or `https://` URL,
+    /// usually ending with `/v1/metrics`, with a timeout of 10 seconds.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            endpoint: Endpoint::parse(url)?,
+            headers: vec![],
+            resource: vec![],
+            timeout: DEFAULT_TIMEOUT,
+            start_time: SystemTime::now(),
+        })
+    }
+
+    /// Adds a header to the requests, e.g. for authentication.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Adds an attribute to the resource the metrics are exported for, e.g.
+    /// `service.name`.
+    pub fn with_resource_attribute(
+        mut self,
+        key: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Self {
+        self.resource.push((key.into(), value.into()));
+        self
+    }
+
+    /// Sets the timeout to connect, send and receive.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Exports the metrics of the registry.
+    pub fn export<M: EncodeMetric>(&self, registry: &Registry<M>) -> Result<(), Error> {
+        let body = self.export_request(registry, SystemTime::now())?;
+        let mut headers = vec![("Content-Type", "application/x-protobuf")];
+
+        headers.extend(self.headers.iter().map(|(name, value)| (&**name, &**value)));
+
+        self.endpoint.post(&headers, &body, self.timeout)
+    }
+
+    /// Exports the metrics of the registry every `interval` from a new
+    /// thread, until the returned task is dropped.
+    ///
+    /// Failed exports are reported to `on_error` and not retried.
+    pub fn spawn<M>(
+        self,
+        registry: Arc<Registry<M>>,
+        interval: Duration,
+        mut on_error: impl FnMut(Error) + Send + 'static,
+    ) -> OtlpTask
+    where
+        M: EncodeMetric + Send + Sync + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(err) = self.export(&registry) {
+                    on_error(err);
+                }
+            }
+        });
+
+        OtlpTask { _stop: stop }
+    }
+
+    /// Encodes the metrics of the registry as an
+    /// `ExportMetricsServiceRequest` message, with data points timestamped
+    /// with `now` unless their sample has a timestamp.
+    pub fn export_request<M: EncodeMetric>(
+        &self,
+        registry: &Registry<M>,
+        now: SystemTime,
+    ) -> io::Result<Vec<u8>> {
+        let text = parse::encode_text(registry)?;
+        let times = Times {
+            start: unix_nanos(self.start_time),
+            now: unix_nanos(now),
+        };
+        let mut resource = Message::default();
+        let mut scope = Message::default();
+        let mut scope_metrics = Message::default();
+        let mut resource_metrics = Message::default();
+        let mut request = Message::default();
+
+        for (key, value) in &self.resource {
+            resource.message(1, &key_value(key, value));
+        }
+
+        scope.string(1, "prometools");
+        scope.string(2, env!("CARGO_PKG_VERSION"));
+        scope_metrics.message(1, &scope);
+
+        for family in parse::parse(&text) {
+            for metric in convert(&family, times) {
+                scope_metrics.message(2, &metric);
+            }
+        }
+
+        resource_metrics.message(1, &resource);
+        resource_metrics.message(2, &scope_metrics);
+        request.message(1, &resource_metrics);
+
+        Ok(request.0)
+    }
+}
+
+/// A task exporting metrics periodically, see [`OtlpExporter::spawn`].
+///
+/// The task stops when dropped, without waiting for an export in progress.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "otlp")))]
+pub struct OtlpTask {
+    _stop: mpsc::Sender<()>,
+}
+
+/// The default start time and time of data points, in nanoseconds since the
+/// Unix epoch.
+#[derive(Clone, Copy)]
+struct Times {
+    start: u64,
+    now: u64,
+}
+
+/// Converts a family to `Metric` messages.
+fn convert(family: &Family<'_>, times: Times) -> Vec<Message> {
+    if family.ty != "histogram" {
+        return points(family, family.samples.iter(), family.ty, times);
+    }
+
+    let (histogram, other): (Vec<_>, Vec<_>) = family.samples.iter().partition(|sample| {
+        matches!(
+            family.suffix(sample),
+            Some("_sum" | "_count" | "_bucket" | "_created"),
+        )
+    });
+
+    let mut data = Message::default();
+
+    for series in parse::series(histogram, "le") {
+        let mut point = Message::default();
+        let mut start = times.start;
+        let mut time = None;
+        let mut bounds = vec![];
+        let mut counts = vec![];
+        let mut previous = 0;
+
+        for label in &series.labels {
+            point.message(9, &key_value(label.0, &label.1));
+        }
+
+        for sample in series.samples {
+            time = time.or(sample.timestamp.map(secs_nanos));
+
+            match family.suffix(sample) {
+                Some("_sum") => point.double(5, sample.value),
+                Some("_count") => point.fixed64(4, sample.value as u64),
+                Some("_created") => start = secs_nanos(sample.value),
+                _ => {
+                    let cumulative = sample.value as u64;
+                    let upper_bound = sample.label("le").and_then(|le| le.parse().ok());
+
+                    counts.extend_from_slice(&cumulative.saturating_sub(previous).to_le_bytes());
+                    previous = cumulative;
+
+                    if let Some(upper_bound) = upper_bound.filter(|b: &f64| b.is_finite()) {
+                        bounds.extend_from_slice(&upper_bound.to_le_bytes());
+                    }
+
+                    if let Some(exemplar) = &sample.exemplar {
+                        point.message(8, &encode_exemplar(exemplar, times));
+                    }
+                }
+            }
+        }
+
+        point.fixed64(2, start);
+        point.fixed64(3, time.unwrap_or(times.now));
+        point.bytes(6, &counts);
+        point.bytes(7, &bounds);
+        data.message(1, &point);
+    }
+
+    data.uint64(2, CUMULATIVE);
+
+    let mut metrics = vec![metric(family, family.name, 9, &data)];
+    metrics.extend(points(family, other.into_iter(), "gauge", times));
+    metrics
+}
+
+/// Converts samples of a family of type `ty` holding a single value, to
+/// sums or gauges named after them.
+fn points<'s, 'a: 's>(
+    family: &Family<'a>,
+    samples: impl Iterator<Item = &'s Sample<'a>>,
+    ty: &str,
+    times: Times,
+) -> Vec<Message> {
+    let counter = ty == "counter";
+    let mut metrics: Vec<(&str, Message)> = vec![];
+
+    for sample in samples {
+        if counter && family.suffix(sample) == Some("_created") {
+            continue;
+        }
+
+        let name = match counter {
+            true => sample.name.strip_suffix("_total").unwrap_or(sample.name),
+            false => sample.name,
+        };
+        let mut point = Message::default();
+
+        for (key, value) in &sample.labels {
+            point.message(7, &key_value(key, value));
+        }
+
+        if counter {
+            let created = family.samples.iter().find(|created| {
+                created.name.strip_suffix("_created") == Some(name)
+                    && created.labels == sample.labels
+            });
+
+            point.fixed64(2, created.map_or(times.start, |c| secs_nanos(c.value)));
+        }
+
+        point.fixed64(3, sample.timestamp.map_or(times.now, secs_nanos));
+        point.double(4, sample.value);
+
+        if let Some(exemplar) = &sample.exemplar {
+            point.message(5, &encode_exemplar(exemplar, times));
+        }
+
+        match metrics.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, data)) => data.message(1, &point),
+            None => {
+                let mut data = Message::default();
+                data.message(1, &point);
+                metrics.push((name, data));
+            }
+        }
+    }
+
+    metrics
+        .into_iter()
+        .map(|(name, mut data)| {
+            if !counter {
+                return metric(family, name, 5, &data);
+            }
+
+            data.uint64(2, CUMULATIVE);
+            data.uint64(3, 1);
+            metric(family, name, 7, &data)
+        })
+        .collect()
+}
+
+/// Returns a `Metric` message whose data is `data` in the field `field`.
+fn metric(family: &Family<'_>, name: &str, field: u32, data: &Message) -> Message {
+    let mut metric = Message::default();
+
+    metric.string(1, name);
+
+    if let Some(help) = &family.help {
+        metric.string(2, help);
+    }
+
+    if let (Some(unit), true) = (family.unit, name == family.name) {
+        metric.string(3, unit);
+    }
+
+    metric.message(field, data);
+    metric
+}
+
+fn encode_exemplar(exemplar: &Exemplar<'_>, times: Times) -> Message {
+    let mut message = Message::default();
+
+    for (key, value) in &exemplar.labels {
+        match (*key, hex(value)) {
+            ("trace_id", Some(id)) if id.len() == 16 => message.bytes(5, &id),
+            ("span_id", Some(id)) if id.len() == 8 => message.bytes(4, &id),
+            _ => message.message(7, &key_value(key, value)),
+        }
+    }
+
+    message.fixed64(2, exemplar.timestamp.map_or(times.now, secs_nanos));
+    message.double(3, exemplar.value);
+    message
+}
+
+fn key_value(key: &str, value: &str) -> Message {
+    let mut any_value = Message::default();
+    let mut key_value = Message::default();
+
+    any_value.string(1, value);
+    key_value.string(1, key);
+    key_value.message(2, &any_value);
+    key_value
+}
+
+fn hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    since.as_nanos().try_into().unwrap_or(u64::MAX)
+}
+
+fn secs_nanos(secs: f64) -> u64 {
+    (secs * 1E9).round() as u64
+}
@@ -0,0 +1,79 @@
+//! Registration of whole structs of metrics at once.
+
+use prometheus_client::registry::Registry;
+
+/// Derives [`Registrant`](trait@Registrant) for a struct of metrics.
+///
+/// Each field is cloned and registered under its own name, with the first
+/// paragraph of its doc comment as help text. The struct-level
+/// `#[registrant(prefix = "...")]` attribute registers all the metrics in a
+/// sub-registry with that prefix.
+///
+/// Fields accept the following attributes:
+///
+/// * `#[registrant(name = "...")]` overrides the name of the metric;
+/// * `#[registrant(help = "...")]` overrides the help text of the metric;
+/// * `#[registrant(skip)]` doesn't register the field.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::{counter::Counter, gauge::Gauge},
+/// #     registry::Registry,
+/// # };
+/// # use prometools::registrant::Registrant;
+/// #
+/// #[derive(Registrant)]
+/// #[registrant(prefix = "http")]
+/// struct Metrics {
+///     /// Number of requests.
+///     requests: Counter,
+///     #[registrant(name = "in_flight", help = "Number of requests in flight")]
+///     in_flight_requests: Gauge,
+///     #[registrant(skip)]
+///     _unregistered: Counter,
+/// }
+///
+/// let metrics = Metrics {
+///     requests: Counter::default(),
+///     in_flight_requests: Gauge::default(),
+///     _unregistered: Counter::default(),
+/// };
+///
+/// let mut registry = <Registry>::default();
+/// metrics.register(&mut registry);
+///
+/// metrics.requests.inc();
+///
+/// let mut buffer = vec![];
+/// encode(&mut buffer, &registry).unwrap();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// let output = unsafe { String::from_utf8_unchecked(buffer) };
+///
+/// assert_eq!(
+///     output,
+///     concat!(
+///         "# HELP http_requests Number of requests.\n",
+///         "# TYPE http_requests counter\n",
+///         "http_requests_total 1\n",
+///         "# HELP http_in_flight Number of requests in flight.\n",
+///         "# TYPE http_in_flight gauge\n",
+///         "http_in_flight 0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use prometools_derive::Registrant;
+
+/// A set of metrics that can register itself with a [`Registry`].
+///
+/// This is usually derived, see [`Registrant`](derive@Registrant).
+pub trait Registrant {
+    /// Registers all the metrics in `registry`.
+    fn register(&self, registry: &mut Registry);
+}
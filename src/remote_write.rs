@@ -0,0 +1,199 @@
+//! Pushing metrics to a Prometheus remote write receiver, e.g. Mimir or
+//! Thanos, for services which can't be scraped.
+//!
+//! The registry is encoded in the OpenMetrics text format first, labels
+//! included, then each sample is sent as a series named after it, with its
+//! exemplar if any. Samples without a timestamp are timestamped with the
+//! time of the push.
+//!
+//! Requests are sent with HTTP/1.1, over TLS for `https://` URLs.
+
+pub use crate::http::Error;
+
+use crate::encoding::message::Message;
+use crate::encoding::parse::{self, Sample};
+use crate::http::Endpoint;
+use prometheus_client::{encoding::text::EncodeMetric, registry::Registry};
+use std::io;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Timeout used by [`RemoteWrite::new`] to connect, send and receive.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A client of a Prometheus remote write receiver.
+///
+/// #### Examples
+///
+/// ```rust,no_run
+/// # use prometheus_client::{metrics::counter::Counter, registry::Registry};
+/// # use prometools::remote_write::RemoteWrite;
+/// # use std::sync::Arc;
+/// # use std::time::Duration;
+/// #
+/// let mut registry = <Registry>::default();
+/// let requests = <Counter>::default();
+///
+/// registry.register("requests", "Number of requests", Box::new(requests.clone()));
+///
+/// let remote_write = RemoteWrite::new("http://localhost:9009/api/v1/push")
+///     .unwrap()
+///     .with_header("X-Scope-OrgID", "tenant");
+///
+/// // Pushes every 15 seconds, until `task` is dropped.
+/// let task = remote_write.spawn(Arc::new(registry), Duration::from_secs(15), |err| {
+///     eprintln!("remote write failed: {err}");
+/// });
+/// ```
+#[derive(Clone, Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-write")))]
+pub struct RemoteWrite {
+    endpoint: Endpoint,
+    headers: Vec<(String, String)>,
+    timeout: Duration,
+}
+
+impl RemoteWrite {
+    /// Creates a client pushing to the given `http://` or `https://` URL,
+    /// with a timeout of 30 seconds.
+    pub fn new(url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            endpoint: Endpoint::parse(url)?,
+            headers: vec![],
+            timeout: DEFAULT_TIMEOUT,
+        })
+    }
+
+    /// Adds a header to the requests, e.g. for authentication or to select
+    /// a tenant.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets the timeout to connect, send and receive.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Pushes the samples of the registry.
+    pub fn push<M: EncodeMetric>(&self, registry: &Registry<M>) -> Result<(), Error> {
+        let body = snap::raw::Encoder::new()
+            .compress_vec(&write_request(registry)?)
+            .map_err(io::Error::other)?;
+        let mut headers = vec![
+            ("Content-Encoding", "snappy"),
+            ("Content-Type", "application/x-protobuf"),
+            ("X-Prometheus-Remote-Write-Version", "0.1.0"),
+        ];
+
+        headers.extend(self.headers.iter().map(|(name, value)| (&**name, &**value)));
+
+        self.endpoint.post(&headers, &body, self.timeout)
+    }
+
+    /// Pushes the samples of the registry every `interval` from a new
+    /// thread, until the returned task is dropped.
+    ///
+    /// Failed pushes are reported to `on_error` and not retried.
+    pub fn spawn<M>(
+        self,
+        registry: Arc<Registry<M>>,
+        interval: Duration,
+        mut on_error: impl FnMut(Error) + Send + 'static,
+    ) -> RemoteWriteTask
+    where
+        M: EncodeMetric + Send + Sync + 'static,
+    {
+        let (stop, stopped) = mpsc::channel();
+
+        thread::spawn(move || {
+            while let Err(RecvTimeoutError::Timeout) = stopped.recv_timeout(interval) {
+                if let Err(err) = self.push(&registry) {
+                    on_error(err);
+                }
+            }
+        });
+
+        RemoteWriteTask { _stop: stop }
+    }
+}
+
+/// A task pushing metrics periodically, see [`RemoteWrite::spawn`].
+///
+/// The task stops when dropped, without waiting for a push in progress.
+#[derive(Debug)]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-write")))]
+pub struct RemoteWriteTask {
+    _stop: mpsc::Sender<()>,
+}
+
+/// Encodes the samples of the registry as an uncompressed remote write
+/// `WriteRequest` message.
+#[cfg_attr(docsrs, doc(cfg(feature = "remote-write")))]
+pub fn write_request<M: EncodeMetric>(registry: &Registry<M>) -> io::Result<Vec<u8>> {
+    let text = parse::encode_text(registry)?;
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs_f64();
+    let mut request = Message::default();
+
+    for family in parse::parse(&text) {
+        for sample in &family.samples {
+            request.message(1, &time_series(sample, now));
+        }
+    }
+
+    Ok(request.0)
+}
+
+fn time_series(sample: &Sample<'_>, now: f64) -> Message {
+    let mut labels: Vec<_> = sample
+        .labels
+        .iter()
+        .map(|(name, value)| (*name, &**value))
+        .chain([("__name__", sample.name)])
+        .collect();
+    let mut series = Message::default();
+    let mut value = Message::default();
+
+    labels.sort_unstable_by_key(|(name, _value)| *name);
+
+    for label in &labels {
+        series.message(1, &label_pair(label));
+    }
+
+    value.double(1, sample.value);
+    value.int64(2, millis(sample.timestamp.unwrap_or(now)));
+    series.message(2, &value);
+
+    if let Some(exemplar) = &sample.exemplar {
+        let mut message = Message::default();
+
+        for (name, value) in &exemplar.labels {
+            message.message(1, &label_pair(&(name, value)));
+        }
+
+        message.double(2, exemplar.value);
+        message.int64(3, millis(exemplar.timestamp.unwrap_or(now)));
+        series.message(3, &message);
+    }
+
+    series
+}
+
+fn label_pair((name, value): &(&str, &str)) -> Message {
+    let mut label = Message::default();
+
+    label.string(1, name);
+    label.string(2, value);
+    label
+}
+
+fn millis(secs: f64) -> i64 {
+    (secs * 1E3).round() as i64
+}
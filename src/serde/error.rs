@@ -69,6 +69,7 @@ pub(crate) enum Unexpected {
     Char(char),
     Str,
     Bytes,
+    Unit,
     Map(Option<usize>),
     Seq(Option<usize>),
     Struct(&'static str),
@@ -93,6 +94,7 @@ impl fmt::Display for Unexpected {
             Unexpected::Char(c) => write!(f, "char {c:?}"),
             Unexpected::Str => f.write_str("string"),
             Unexpected::Bytes => f.write_str("bytes"),
+            Unexpected::Unit => f.write_str("unit"),
         }
     }
 }
@@ -0,0 +1,394 @@
+use super::{str, top, Options};
+use prometheus_client::{
+    encoding::text::{Encode, EncodeMetric, Encoder},
+    metrics::{
+        counter::{Atomic, Counter},
+        exemplar::{
+            CounterWithExemplar as InnerCounterWithExemplar,
+            HistogramWithExemplars as InnerHistogramWithExemplars,
+        },
+        histogram::Histogram,
+        MetricType, TypedMetric,
+    },
+};
+use serde::ser::Serialize;
+use std::{fmt, io, sync::atomic::AtomicU64};
+
+/// A wrapper around
+/// [`prometheus_client::metrics::exemplar::CounterWithExemplar`] which
+/// encodes its exemplar labels with [`Serialize`] instead of [`Encode`].
+///
+/// #### Examples
+///
+/// Basic usage:
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::{CounterWithExemplar, Family};
+/// # use serde::Serialize;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// struct Labels {
+///     method: &'static str,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Trace {
+///     trace_id: &'static str,
+/// }
+///
+/// let family = <Family<Labels, CounterWithExemplar<Trace>>>::default();
+/// let mut registry = Registry::default();
+///
+/// registry.register("requests", "Number of requests", family.clone());
+///
+/// family
+///     .get_or_create(&Labels { method: "GET" })
+///     .inc_by(1, Some(Trace { trace_id: "3fa9" }));
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP requests Number of requests.\n",
+///         "# TYPE requests counter\n",
+///         "requests_total{method=\"GET\"} 1 # {trace_id=\"3fa9\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+pub struct CounterWithExemplar<S, N = u64, A = AtomicU64> {
+    inner: InnerCounterWithExemplar<ExemplarLabels<S>, N, A>,
+}
+
+impl<S, N, A> CounterWithExemplar<S, N, A>
+where
+    N: Clone,
+    A: Atomic<N>,
+{
+    /// Increases the counter by `v`, updating its exemplar if a label set
+    /// is provided, and returns the previous value.
+    pub fn inc_by(&self, v: N, label_set: Option<S>) -> N {
+        self.inner.inc_by(v, label_set.map(ExemplarLabels))
+    }
+
+    /// Increases the counter by one, updating its exemplar, and returns the
+    /// previous value.
+    pub fn inc_with_exemplar(&self, label_set: S) -> N
+    where
+        N: From<u8>,
+    {
+        self.inc_by(N::from(1), Some(label_set))
+    }
+
+    /// Returns the current value of the counter.
+    pub fn get(&self) -> N {
+        self.inner.get().0
+    }
+}
+
+impl<S, N, A> Clone for CounterWithExemplar<S, N, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, N, A> Default for CounterWithExemplar<S, N, A>
+where
+    A: Default,
+{
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<S, N, A> fmt::Debug for CounterWithExemplar<S, N, A>
+where
+    S: fmt::Debug,
+    N: fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<S, N, A> EncodeMetric for CounterWithExemplar<S, N, A>
+where
+    S: Serialize,
+    N: Encode + Clone,
+    A: Atomic<N>,
+{
+    fn encode(&self, encoder: Encoder) -> io::Result<()> {
+        self.inner.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S, N, A> TypedMetric for CounterWithExemplar<S, N, A> {
+    const TYPE: MetricType = <Counter<N, A> as TypedMetric>::TYPE;
+}
+
+/// Like [`CounterWithExemplar`], but does not suffix the name with `_total`,
+/// like [`NonstandardUnsuffixedCounter`](crate::nonstandard::NonstandardUnsuffixedCounter).
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::NonstandardUnsuffixedCounterWithExemplar;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Serialize)]
+/// struct Trace {
+///     trace_id: &'static str,
+/// }
+///
+/// let counter = <NonstandardUnsuffixedCounterWithExemplar<Trace>>::default();
+/// let mut registry = Registry::default();
+///
+/// registry.register("responses", "Number of responses", counter.clone());
+///
+/// counter.inc_by(2, None);
+/// counter.inc_with_exemplar(Trace { trace_id: "3fa9" });
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP responses Number of responses.\n",
+///         "# TYPE responses counter\n",
+///         "responses 3 # {trace_id=\"3fa9\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+pub struct NonstandardUnsuffixedCounterWithExemplar<S, N = u64, A = AtomicU64> {
+    inner: InnerCounterWithExemplar<ExemplarLabels<S>, N, A>,
+}
+
+impl<S, N, A> NonstandardUnsuffixedCounterWithExemplar<S, N, A>
+where
+    N: Clone,
+    A: Atomic<N>,
+{
+    /// Increases the counter by `v`, updating its exemplar if a label set
+    /// is provided, and returns the previous value.
+    pub fn inc_by(&self, v: N, label_set: Option<S>) -> N {
+        self.inner.inc_by(v, label_set.map(ExemplarLabels))
+    }
+
+    /// Increases the counter by one, updating its exemplar, and returns the
+    /// previous value.
+    pub fn inc_with_exemplar(&self, label_set: S) -> N
+    where
+        N: From<u8>,
+    {
+        self.inc_by(N::from(1), Some(label_set))
+    }
+
+    /// Returns the current value of the counter.
+    pub fn get(&self) -> N {
+        self.inner.get().0
+    }
+}
+
+impl<S, N, A> Clone for NonstandardUnsuffixedCounterWithExemplar<S, N, A> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S, N, A> Default for NonstandardUnsuffixedCounterWithExemplar<S, N, A>
+where
+    A: Default,
+{
+    fn default() -> Self {
+        Self {
+            inner: Default::default(),
+        }
+    }
+}
+
+impl<S, N, A> fmt::Debug for NonstandardUnsuffixedCounterWithExemplar<S, N, A>
+where
+    S: fmt::Debug,
+    N: fmt::Debug,
+    A: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<S, N, A> EncodeMetric for NonstandardUnsuffixedCounterWithExemplar<S, N, A>
+where
+    S: Serialize,
+    N: Encode + Clone,
+    A: Atomic<N>,
+{
+    fn encode(&self, mut encoder: Encoder) -> io::Result<()> {
+        let (value, exemplar) = self.inner.get();
+        let mut bucket_encoder = encoder.no_suffix()?;
+        let mut value_encoder = bucket_encoder.no_bucket()?;
+        let mut exemplar_encoder = value_encoder.encode_value(value)?;
+
+        match exemplar.as_ref() {
+            Some(exemplar) => exemplar_encoder.encode_exemplar(exemplar),
+            None => exemplar_encoder.no_exemplar(),
+        }
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S, N, A> TypedMetric for NonstandardUnsuffixedCounterWithExemplar<S, N, A> {
+    const TYPE: MetricType = <Counter<N, A> as TypedMetric>::TYPE;
+}
+
+/// A wrapper around
+/// [`prometheus_client::metrics::exemplar::HistogramWithExemplars`] which
+/// encodes its exemplar labels with [`Serialize`] instead of [`Encode`].
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::HistogramWithExemplars;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Serialize)]
+/// struct Trace {
+///     trace_id: &'static str,
+/// }
+///
+/// let histogram = HistogramWithExemplars::new([0.5, 1.0].into_iter());
+/// let mut registry = Registry::default();
+///
+/// registry.register("latency", "Request latency", histogram.clone());
+///
+/// histogram.observe(0.3, Some(Trace { trace_id: "3fa9" }));
+/// histogram.observe(0.8, None);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP latency Request latency.\n",
+///         "# TYPE latency histogram\n",
+///         "latency_sum 1.1\n",
+///         "latency_count 2\n",
+///         "latency_bucket{le=\"0.5\"} 1 # {trace_id=\"3fa9\"} 0.3\n",
+///         "latency_bucket{le=\"1.0\"} 2\n",
+///         "latency_bucket{le=\"+Inf\"} 2\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+pub struct HistogramWithExemplars<S> {
+    inner: InnerHistogramWithExemplars<ExemplarLabels<S>>,
+}
+
+impl<S> HistogramWithExemplars<S> {
+    pub fn new(buckets: impl Iterator<Item = f64>) -> Self {
+        Self {
+            inner: InnerHistogramWithExemplars::new(buckets),
+        }
+    }
+
+    /// Observes a value, updating the exemplar of its bucket if a label set
+    /// is provided.
+    pub fn observe(&self, v: f64, label_set: Option<S>) {
+        self.inner.observe(v, label_set.map(ExemplarLabels))
+    }
+}
+
+impl<S> Clone for HistogramWithExemplars<S> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<S> fmt::Debug for HistogramWithExemplars<S>
+where
+    S: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+impl<S> EncodeMetric for HistogramWithExemplars<S>
+where
+    S: Serialize,
+{
+    fn encode(&self, encoder: Encoder) -> io::Result<()> {
+        self.inner.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S> TypedMetric for HistogramWithExemplars<S> {
+    const TYPE: MetricType = <Histogram as TypedMetric>::TYPE;
+}
+
+/// An exemplar label set, serialized with the default [`Options`].
+#[derive(Debug)]
+struct ExemplarLabels<S>(S);
+
+impl<S> Encode for ExemplarLabels<S>
+where
+    S: Serialize,
+{
+    fn encode(&self, writer: &mut dyn io::Write) -> io::Result<()> {
+        self.0.serialize(top::serializer(
+            str::Writer::new(writer),
+            &Options::default(),
+        ))?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use super::error::{Error, Unexpected};
+use super::top::StructSerializer;
+use serde::ser::{Impossible, Serialize, Serializer};
+use std::error;
+use std::fmt;
+
+#[inline]
+pub(super) fn serializer<'a, 'w>(parent: &'a mut StructSerializer<'w>) -> KeySerializer<'a, 'w> {
+    KeySerializer { parent }
+}
+
+/// Serializes map keys as label names.
+pub(super) struct KeySerializer<'a, 'w> {
+    parent: &'a mut StructSerializer<'w>,
+}
+
+macro_rules! unsupported_scalars {
+    ($($($method:ident: $kind:ident($ty:ty)),+ $(,)?)?) => {$($(
+        #[inline]
+        fn $method(self, v: $ty) -> Result<Self::Ok, Error> {
+            Err(unsupported(Unexpected::$kind(v as _)))
+        }
+    )+)?}
+}
+
+impl<'a, 'w> Serializer for KeySerializer<'a, 'w> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Impossible<(), Error>;
+    type SerializeTupleStruct = Impossible<(), Error>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    unsupported_scalars! {
+        serialize_bool: Bool(bool),
+        serialize_i8: Signed(i8),
+        serialize_i16: Signed(i16),
+        serialize_i32: Signed(i32),
+        serialize_i64: Signed(i64),
+        serialize_u8: Unsigned(u8),
+        serialize_u16: Unsigned(u16),
+        serialize_u32: Unsigned(u32),
+        serialize_u64: Unsigned(u64),
+        serialize_f32: Float(f32),
+        serialize_f64: Float(f64),
+    }
+
+    #[inline]
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.parent.write_key(v.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    fn serialize_str(self, value: &str) -> Result<(), Error> {
+        self.parent.write_key(value)
+    }
+
+    #[inline]
+    fn serialize_bytes(self, _value: &[u8]) -> Result<(), Error> {
+        Err(unsupported(Unexpected::Bytes))
+    }
+
+    #[inline]
+    fn serialize_unit(self) -> Result<(), Error> {
+        Err(unsupported(Unexpected::Unit))
+    }
+
+    #[inline]
+    fn serialize_unit_struct(self, name: &'static str) -> Result<(), Error> {
+        Err(unsupported(Unexpected::Struct(name)))
+    }
+
+    #[inline]
+    fn serialize_unit_variant(
+        self,
+        _ty: &'static str,
+        _index: u32,
+        name: &'static str,
+    ) -> Result<(), Error> {
+        self.parent.write_key(name)
+    }
+
+    #[inline]
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_newtype_variant<T>(
+        self,
+        ty: &'static str,
+        _index: u32,
+        name: &'static str,
+        _value: &T,
+    ) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(unsupported(Unexpected::Variant(ty, name)))
+    }
+
+    #[inline]
+    fn serialize_none(self) -> Result<(), Error> {
+        Err(unsupported(Unexpected::Unit))
+    }
+
+    #[inline]
+    fn serialize_some<T>(self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    #[inline]
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Error> {
+        Err(unsupported(Unexpected::Seq(len)))
+    }
+
+    #[inline]
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+        Err(unsupported(Unexpected::Tuple(len)))
+    }
+
+    #[inline]
+    fn serialize_tuple_struct(
+        self,
+        ty: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        Err(unsupported(Unexpected::Struct(ty)))
+    }
+
+    #[inline]
+    fn serialize_tuple_variant(
+        self,
+        ty: &'static str,
+        _index: u32,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        Err(unsupported(Unexpected::Variant(ty, name)))
+    }
+
+    #[inline]
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Err(unsupported(Unexpected::Map(len)))
+    }
+
+    #[inline]
+    fn serialize_struct(
+        self,
+        ty: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        Err(unsupported(Unexpected::Struct(ty)))
+    }
+
+    #[inline]
+    fn serialize_struct_variant(
+        self,
+        ty: &'static str,
+        _index: u32,
+        name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        Err(unsupported(Unexpected::Variant(ty, name)))
+    }
+}
+
+fn unsupported(kind: Unexpected) -> Error {
+    #[derive(Debug)]
+    struct UnsupportedKeyError(Unexpected);
+
+    impl error::Error for UnsupportedKeyError {
+        #[allow(deprecated)]
+        fn description(&self) -> &str {
+            "unsupported key"
+        }
+    }
+
+    impl fmt::Display for UnsupportedKeyError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "unsupported {} as key", self.0)
+        }
+    }
+
+    Error::invalid_input(UnsupportedKeyError(kind))
+}
@@ -0,0 +1,91 @@
+use super::{str, value, Options};
+use serde::ser::Serialize;
+use std::io;
+
+/// A label set which can be encoded without going through the generic
+/// [`Serialize`] machinery for its label names.
+///
+/// This is usually derived, the derived implementation writes the label
+/// names of the struct as static strings and only serializes the values of
+/// its fields, which must produce the same output as [`Serialize`] does.
+/// [`Family::with_derived_encoder`](super::Family::with_derived_encoder)
+/// makes a family use it instead of [`Serialize`].
+///
+/// The derive macro supports the `rename` and `skip` serde attributes on
+/// fields, and rejects any other serde attribute which could make its output
+/// diverge from the one of [`Serialize`].
+pub trait EncodeLabelSet: Serialize {
+    /// Writes the label set, without the surrounding curly braces.
+    fn encode_label_set(&self, writer: &mut dyn io::Write, options: &Options) -> io::Result<()>;
+}
+
+/// Derives [`EncodeLabelSet`](trait@EncodeLabelSet) for a struct which
+/// also derives [`Serialize`].
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::counter::Counter,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::{EncodeLabelSet, Family};
+/// # use serde::Serialize;
+/// #
+/// #[derive(Clone, EncodeLabelSet, Eq, Hash, PartialEq, Serialize)]
+/// struct Labels {
+///     #[serde(rename = "method")]
+///     verb: &'static str,
+///     status: u16,
+///     #[serde(skip)]
+///     _internal: (),
+/// }
+///
+/// let family = <Family<Labels, Counter>>::default().with_derived_encoder();
+/// let mut registry = Registry::default();
+///
+/// registry.register("responses", "Number of responses", family.clone());
+///
+/// family
+///     .get_or_create(&Labels {
+///         verb: "GET",
+///         status: 200,
+///         _internal: (),
+///     })
+///     .inc();
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP responses Number of responses.\n",
+///         "# TYPE responses counter\n",
+///         "responses_total{method=\"GET\",status=\"200\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[cfg(feature = "derive")]
+#[cfg_attr(docsrs, doc(cfg(feature = "derive")))]
+pub use prometools_derive::EncodeLabelSet;
+
+#[doc(hidden)]
+pub fn encode_label_value<T>(
+    value: &T,
+    writer: &mut dyn io::Write,
+    options: &Options,
+) -> io::Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    value.serialize(value::serializer(str::Writer::new(writer), options))?;
+
+    Ok(())
+}
@@ -1,24 +1,51 @@
 //! Serde bridge.
 
 use crate::nonstandard::InfoGauge as InnerInfoGauge;
-use parking_lot::MappedRwLockReadGuard;
+use hashbrown::HashMap;
+use parking_lot::{MappedRwLockReadGuard, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use prometheus_client::{
     encoding::text::{Encode, EncodeMetric, Encoder},
-    metrics::{
-        family::{Family as InnerFamily, MetricConstructor},
-        MetricType, TypedMetric,
-    },
+    metrics::{family::MetricConstructor, MetricType, TypedMetric},
 };
 use serde::ser::Serialize;
-use std::{fmt, hash::Hash, io};
+use std::{
+    collections::{hash_map::RandomState, HashSet},
+    fmt,
+    hash::Hash,
+    io, mem,
+    ops::Deref,
+    sync::{Arc, Weak},
+};
 
 mod error;
+mod exemplar;
+mod key;
+mod label_set;
+mod options;
+mod outcome;
+mod statsd;
 mod str;
+mod target_info;
 mod top;
 mod value;
 
-/// A wrapper around [`prometheus_client::metrics::family::Family`] which
-/// encodes its labels with [`Serialize`] instead of [`Encode`].
+pub use self::exemplar::{
+    CounterWithExemplar, HistogramWithExemplars, NonstandardUnsuffixedCounterWithExemplar,
+};
+#[doc(hidden)]
+pub use self::label_set::encode_label_value;
+pub use self::label_set::EncodeLabelSet;
+pub use self::options::{BoolFormat, BytesFormat, FloatFormat, NewtypeVariantFormat, Options};
+pub use self::outcome::{Outcome, OutcomeCounter};
+pub use self::statsd::{Statsd, StatsdCounter, StatsdFlavor, StatsdHistogram};
+pub use self::target_info::TargetInfo;
+pub use hashbrown::Equivalent;
+
+/// A family of metrics, like [`prometheus_client::metrics::family::Family`],
+/// which encodes its labels with [`Serialize`] instead of [`Encode`].
+///
+/// Its series are kept in its own map, shared by its clones, in which label
+/// sets serialized the same way with lossy [`Options`] share their series.
 ///
 /// #### Examples
 ///
@@ -77,9 +104,173 @@ mod value;
 ///     ),
 /// );
 /// ```
+///
+/// Maps can be used as label sets when the label names are only known at
+/// runtime:
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::gauge::Gauge,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::Family;
+/// # use std::collections::BTreeMap;
+/// #
+/// let family = <Family<BTreeMap<String, String>, Gauge>>::default();
+/// let mut registry = Registry::default();
+///
+/// registry.register("queue_depth", "Depth of each queue", family.clone());
+///
+/// let labels = BTreeMap::from([
+///     ("queue".to_owned(), "emails".to_owned()),
+///     ("region".to_owned(), "eu".to_owned()),
+/// ]);
+///
+/// family.get_or_create(&labels).set(3);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP queue_depth Depth of each queue.\n",
+///         "# TYPE queue_depth gauge\n",
+///         "queue_depth{queue=\"emails\",region=\"eu\"} 3\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
 #[derive(Debug)]
 pub struct Family<S, M, C = fn() -> M> {
-    inner: InnerFamily<Bridge<S>, M, C>,
+    metrics: Arc<RwLock<SeriesMap<S, M>>>,
+    constructor: C,
+    options: Options,
+    label_set_encoder: Option<LabelSetEncoder<S>>,
+    merge_key: Option<MergeKey<S>>,
+}
+
+type LabelSetEncoder<S> = fn(&S, &mut dyn io::Write, &Options) -> io::Result<()>;
+
+/// Serializes a label set, to merge the series of label sets serialized the
+/// same way.
+type MergeKey<S> = fn(&S, &Options, Option<LabelSetEncoder<S>>) -> Option<Vec<u8>>;
+
+/// The series of a [`Family`].
+#[derive(Debug)]
+struct SeriesMap<S, M> {
+    /// The series by the label set they were created with.
+    series: HashMap<S, Series<S, M>, RandomState>,
+    /// The label set of each series by serialization, only maintained when
+    /// different label sets may be serialized the same way, see
+    /// [`Family::with_options`].
+    serialized: HashMap<Vec<u8>, S, RandomState>,
+}
+
+impl<S, M> SeriesMap<S, M>
+where
+    S: Clone + Eq + Hash,
+{
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            series: HashMap::with_capacity_and_hasher(capacity, RandomState::new()),
+            serialized: HashMap::default(),
+        }
+    }
+
+    /// Returns the series of `label_set`, or of the label set serialized
+    /// the same way according to `serialize`.
+    fn find(
+        &self,
+        label_set: &S,
+        serialize: impl FnOnce(&S) -> Option<Vec<u8>>,
+    ) -> Option<&Series<S, M>> {
+        if let Some(series) = self.series.get(label_set) {
+            return Some(series);
+        }
+
+        if self.serialized.is_empty() {
+            return None;
+        }
+
+        self.series
+            .get(self.serialized.get(&serialize(label_set)?)?)
+    }
+
+    /// Returns the series of `label_set`, or of the label set serialized
+    /// the same way according to `serialize`, along with the label set it
+    /// was created with, creating it with `new_metric` if needed.
+    fn get_or_create(
+        &mut self,
+        mut label_set: S,
+        serialize: impl FnOnce(&S) -> Option<Vec<u8>>,
+        new_metric: impl FnOnce() -> M,
+    ) -> (&S, &mut Series<S, M>) {
+        if !self.series.contains_key(&label_set) {
+            let serialized = serialize(&label_set);
+
+            match serialized.as_ref().and_then(|key| self.serialized.get(key)) {
+                Some(merged) => label_set = merged.clone(),
+                None => {
+                    if let Some(serialized) = &serialized {
+                        self.serialized
+                            .insert(serialized.clone(), label_set.clone());
+                    }
+
+                    self.series.insert(
+                        label_set.clone(),
+                        Series {
+                            metric: new_metric(),
+                            scope: Weak::new(),
+                            serialized,
+                        },
+                    );
+                }
+            }
+        }
+
+        self.series
+            .get_key_value_mut(&label_set)
+            .expect("series should exist after creating it")
+    }
+}
+
+impl<S, M> SeriesMap<S, M>
+where
+    S: Eq + Hash,
+{
+    fn remove(&mut self, label_set: &S) {
+        let serialized = self
+            .series
+            .remove(label_set)
+            .and_then(|series| series.serialized);
+
+        if let Some(serialized) = serialized {
+            self.serialized.remove(&serialized);
+        }
+    }
+}
+
+impl<S, M> Default for SeriesMap<S, M> {
+    fn default() -> Self {
+        Self {
+            series: HashMap::default(),
+            serialized: HashMap::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Series<S, M> {
+    metric: M,
+    scope: Weak<Scope<S, M>>,
+    /// The key of the series in [`SeriesMap::serialized`], if any.
+    serialized: Option<Vec<u8>>,
 }
 
 impl<S, M, C> Family<S, M, C>
@@ -88,9 +279,100 @@ where
 {
     pub fn new_with_constructor(constructor: C) -> Self {
         Self {
-            inner: InnerFamily::new_with_constructor(constructor),
+            metrics: Default::default(),
+            constructor,
+            options: Options::default(),
+            label_set_encoder: None,
+            merge_key: None,
         }
     }
+
+    /// Creates an empty family with space for at least `capacity` series
+    /// before needing to grow.
+    pub fn with_capacity_and_constructor(capacity: usize, constructor: C) -> Self {
+        Self {
+            metrics: Arc::new(RwLock::new(SeriesMap::with_capacity(capacity))),
+            constructor,
+            options: Options::default(),
+            label_set_encoder: None,
+            merge_key: None,
+        }
+    }
+
+    /// Sets the options used to serialize the label sets of this family.
+    ///
+    /// If the options let different label sets be serialized the same way,
+    /// e.g. by formatting floats with fewer decimals or truncating values,
+    /// the series of such label sets are merged into the first one of them
+    /// to be created, so that no series is encoded twice.
+    pub fn with_options(mut self, options: Options) -> Self
+    where
+        S: Serialize,
+    {
+        self.merge_key = options.is_lossy().then_some(merge_key::<S> as MergeKey<S>);
+        self.options = options;
+        self
+    }
+
+    /// Encodes the label sets of this family with their [`EncodeLabelSet`]
+    /// implementation instead of their [`Serialize`] one.
+    pub fn with_derived_encoder(mut self) -> Self
+    where
+        S: EncodeLabelSet,
+    {
+        self.label_set_encoder = Some(S::encode_label_set);
+        self
+    }
+
+    /// Removes all series from the family, returning them.
+    ///
+    /// The series are all taken at once, so no update to the family can be
+    /// lost between reading a series and resetting it.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::counter::Counter;
+    /// # use prometools::serde::Family;
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     method: &'static str,
+    /// }
+    ///
+    /// let family = <Family<Labels, Counter>>::default();
+    ///
+    /// family.get_or_create(&Labels { method: "GET" }).inc();
+    ///
+    /// let drained = family.drain();
+    ///
+    /// assert_eq!(drained.len(), 1);
+    /// assert_eq!(drained[0].0, Labels { method: "GET" });
+    /// assert_eq!(drained[0].1.get(), 1);
+    /// assert_eq!(family.get_or_create(&Labels { method: "GET" }).get(), 0);
+    /// ```
+    pub fn drain(&self) -> Vec<(S, M)> {
+        let metrics = mem::take(&mut *self.metrics.write());
+
+        metrics
+            .series
+            .into_iter()
+            .map(|(label_set, series)| (label_set, series.metric))
+            .collect()
+    }
+}
+
+impl<S, M> Family<S, M>
+where
+    S: Clone + Eq + Hash,
+    M: Default,
+{
+    /// Creates an empty family with space for at least `capacity` series
+    /// before needing to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_and_constructor(capacity, M::default)
+    }
 }
 
 impl<S, M> Default for Family<S, M>
@@ -99,9 +381,7 @@ where
     M: Default,
 {
     fn default() -> Self {
-        Self {
-            inner: Default::default(),
-        }
+        Self::new_with_constructor(M::default)
     }
 }
 
@@ -110,8 +390,294 @@ where
     S: Clone + Eq + Hash,
     C: MetricConstructor<M>,
 {
-    pub fn get_or_create(&self, label_set: &S) -> MappedRwLockReadGuard<M> {
-        self.inner.get_or_create(Bridge::from_ref(label_set))
+    pub fn get_or_create(&self, label_set: &S) -> MappedRwLockReadGuard<'_, M> {
+        self.find(label_set)
+            .unwrap_or_else(|| self.create(label_set.clone()))
+    }
+
+    /// Like [`Family::get_or_create`], but looks the series up with a
+    /// borrowed form of the label set, which is only converted to `S` if
+    /// the series does not exist yet.
+    ///
+    /// The borrowed form must hash like `S` does, which is the case when
+    /// its fields hash like the ones of `S`, e.g. `&str` and `String`.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::counter::Counter;
+    /// # use prometools::serde::{Equivalent, Family};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     path: String,
+    /// }
+    ///
+    /// #[derive(Hash)]
+    /// struct LabelsRef<'a> {
+    ///     path: &'a str,
+    /// }
+    ///
+    /// impl Equivalent<Labels> for LabelsRef<'_> {
+    ///     fn equivalent(&self, labels: &Labels) -> bool {
+    ///         self.path == labels.path
+    ///     }
+    /// }
+    ///
+    /// impl From<&LabelsRef<'_>> for Labels {
+    ///     fn from(labels: &LabelsRef<'_>) -> Self {
+    ///         Self {
+    ///             path: labels.path.to_owned(),
+    ///         }
+    ///     }
+    /// }
+    ///
+    /// let family = <Family<Labels, Counter>>::default();
+    ///
+    /// family.get_or_create_borrowed(&LabelsRef { path: "/" }).inc();
+    /// family.get_or_create_borrowed(&LabelsRef { path: "/" }).inc();
+    ///
+    /// let labels = Labels {
+    ///     path: "/".to_owned(),
+    /// };
+    /// assert_eq!(family.get_or_create(&labels).get(), 2);
+    /// ```
+    pub fn get_or_create_borrowed<Q>(&self, label_set: &Q) -> MappedRwLockReadGuard<'_, M>
+    where
+        Q: ?Sized + Hash + Equivalent<S>,
+        for<'q> &'q Q: Into<S>,
+    {
+        self.get_or_create_with_key(label_set, || label_set.into())
+    }
+
+    /// Like [`Family::get_or_create_borrowed`], but builds the label set with
+    /// `make_label_set` if the series does not exist yet, instead of
+    /// converting the key.
+    ///
+    /// This is useful when building the label set is expensive, e.g. when it
+    /// requires formatting or normalizing some values. As with
+    /// [`Family::get_or_create_borrowed`], the key must hash like the label
+    /// set it is equivalent to, and the label set returned by
+    /// `make_label_set` should be equivalent to the key. If it isn't, the
+    /// series of that label set is returned, and created if needed.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::counter::Counter;
+    /// # use prometools::serde::{Equivalent, Family};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     host: String,
+    /// }
+    ///
+    /// #[derive(Hash)]
+    /// struct Host<'a>(&'a str);
+    ///
+    /// impl Equivalent<Labels> for Host<'_> {
+    ///     fn equivalent(&self, labels: &Labels) -> bool {
+    ///         self.0 == labels.host
+    ///     }
+    /// }
+    ///
+    /// let family = <Family<Labels, Counter>>::default();
+    /// let host = "example.org";
+    ///
+    /// for _ in 0..2 {
+    ///     family
+    ///         .get_or_create_with_key(&Host(host), || Labels {
+    ///             host: host.to_owned(),
+    ///         })
+    ///         .inc();
+    /// }
+    ///
+    /// let labels = Labels {
+    ///     host: host.to_owned(),
+    /// };
+    /// assert_eq!(family.get_or_create(&labels).get(), 2);
+    /// ```
+    pub fn get_or_create_with_key<Q>(
+        &self,
+        key: &Q,
+        make_label_set: impl FnOnce() -> S,
+    ) -> MappedRwLockReadGuard<'_, M>
+    where
+        Q: ?Sized + Hash + Equivalent<S>,
+    {
+        if let Ok(metric) = RwLockReadGuard::try_map(self.metrics.read(), |metrics| {
+            metrics.series.get(key).map(|series| &series.metric)
+        }) {
+            return metric;
+        }
+
+        // The label set is looked up rather than the key, in case they
+        // aren't equivalent, so that an existing series is never replaced.
+        let label_set = make_label_set();
+
+        self.find(&label_set)
+            .unwrap_or_else(|| self.create(label_set))
+    }
+
+    /// Eagerly creates the series for all the given label sets, leaving
+    /// the ones that already exist untouched.
+    ///
+    /// This avoids paying for the creation of a series when it is first
+    /// used, and makes the series show up in the output even if they were
+    /// never updated.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     metrics::counter::Counter,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::Family;
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     status: u16,
+    /// }
+    ///
+    /// let family = <Family<Labels, Counter>>::with_capacity(1);
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("responses", "Number of responses", family.clone());
+    ///
+    /// family.populate([Labels { status: 200 }]);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP responses Number of responses.\n",
+    ///         "# TYPE responses counter\n",
+    ///         "responses_total{status=\"200\"} 0\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn populate(&self, label_sets: impl IntoIterator<Item = S>) {
+        let label_sets = label_sets.into_iter();
+        let mut write_guard = self.metrics.write();
+
+        write_guard.series.reserve(label_sets.size_hint().0);
+
+        for label_set in label_sets {
+            write_guard.get_or_create(
+                label_set,
+                |label_set| self.merge_key(label_set),
+                || self.constructor.new_metric(),
+            );
+        }
+    }
+
+    /// Returns the series of `label_set`, if it exists.
+    fn find(&self, label_set: &S) -> Option<MappedRwLockReadGuard<'_, M>> {
+        RwLockReadGuard::try_map(self.metrics.read(), |metrics| {
+            metrics
+                .find(label_set, |label_set| self.merge_key(label_set))
+                .map(|series| &series.metric)
+        })
+        .ok()
+    }
+
+    /// Returns the series of `label_set`, creating it if needed.
+    fn create(&self, label_set: S) -> MappedRwLockReadGuard<'_, M> {
+        let mut write_guard = self.metrics.write();
+        let (label_set, _series) = write_guard.get_or_create(
+            label_set,
+            |label_set| self.merge_key(label_set),
+            || self.constructor.new_metric(),
+        );
+        let label_set = label_set.clone();
+        let read_guard = RwLockWriteGuard::downgrade(write_guard);
+
+        RwLockReadGuard::map(read_guard, |metrics| {
+            &metrics
+                .series
+                .get(&label_set)
+                .expect("series should exist after creating it")
+                .metric
+        })
+    }
+
+    fn merge_key(&self, label_set: &S) -> Option<Vec<u8>> {
+        (self.merge_key?)(label_set, &self.options, self.label_set_encoder)
+    }
+
+    /// Returns a handle to the series with the given label set, creating it
+    /// if it does not yet exist.
+    ///
+    /// The series is removed from the family when the last handle to it is
+    /// dropped, which is useful for series tied to the lifetime of some
+    /// other resource, such as a connection. The handle holds a clone of the
+    /// metric, so this is only meaningful for metrics whose clones share
+    /// their state, like the ones from `prometheus-client`.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::metrics::gauge::Gauge;
+    /// # use prometools::serde::Family;
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     peer: &'static str,
+    /// }
+    ///
+    /// let family = <Family<Labels, Gauge>>::default();
+    /// let labels = Labels { peer: "10.0.0.1" };
+    ///
+    /// let first = family.get_or_create_scoped(&labels);
+    /// let second = family.get_or_create_scoped(&labels);
+    ///
+    /// first.inc();
+    /// second.inc();
+    /// assert_eq!(family.get_or_create(&labels).get(), 2);
+    ///
+    /// drop(first);
+    /// assert_eq!(family.get_or_create(&labels).get(), 2);
+    ///
+    /// drop(second);
+    /// assert_eq!(family.get_or_create(&labels).get(), 0);
+    /// ```
+    pub fn get_or_create_scoped(&self, label_set: &S) -> ScopedMetric<S, M>
+    where
+        M: Clone,
+    {
+        let mut write_guard = self.metrics.write();
+
+        let (label_set, series) = write_guard.get_or_create(
+            label_set.clone(),
+            |label_set| self.merge_key(label_set),
+            || self.constructor.new_metric(),
+        );
+
+        if let Some(scope) = series.scope.upgrade() {
+            return ScopedMetric { scope: Some(scope) };
+        }
+
+        let scope = Arc::new(Scope {
+            metrics: Arc::downgrade(&self.metrics),
+            label_set: label_set.clone(),
+            metric: series.metric.clone(),
+        });
+        series.scope = Arc::downgrade(&scope);
+
+        ScopedMetric { scope: Some(scope) }
     }
 }
 
@@ -121,8 +687,17 @@ where
     M: EncodeMetric + TypedMetric,
     C: MetricConstructor<M>,
 {
-    fn encode(&self, encoder: Encoder) -> io::Result<()> {
-        self.inner.encode(encoder)
+    fn encode(&self, mut encoder: Encoder) -> io::Result<()> {
+        for (label_set, series) in self.metrics.read().series.iter() {
+            let label_set = Bridge {
+                label_set,
+                options: &self.options,
+                encoder: self.label_set_encoder,
+            };
+            series.metric.encode(encoder.with_label_set(&label_set))?;
+        }
+
+        Ok(())
     }
 
     fn metric_type(&self) -> MetricType {
@@ -143,11 +718,117 @@ where
 {
     fn clone(&self) -> Self {
         Self {
-            inner: self.inner.clone(),
+            metrics: self.metrics.clone(),
+            constructor: self.constructor.clone(),
+            options: self.options.clone(),
+            label_set_encoder: self.label_set_encoder,
+            merge_key: self.merge_key,
         }
     }
 }
 
+/// A handle to a series of a [`Family`], returned by
+/// [`Family::get_or_create_scoped`].
+///
+/// Cloning the handle doesn't create a new series, and the series is removed
+/// from its family once the last handle to it is dropped.
+pub struct ScopedMetric<S, M>
+where
+    S: Eq + Hash,
+{
+    // Always `Some(_)` until dropped.
+    scope: Option<Arc<Scope<S, M>>>,
+}
+
+struct Scope<S, M> {
+    metrics: Weak<RwLock<SeriesMap<S, M>>>,
+    label_set: S,
+    metric: M,
+}
+
+impl<S, M> ScopedMetric<S, M>
+where
+    S: Eq + Hash,
+{
+    fn scope(&self) -> &Scope<S, M> {
+        self.scope
+            .as_ref()
+            .expect("scope should not be dropped yet")
+    }
+}
+
+impl<S, M> Clone for ScopedMetric<S, M>
+where
+    S: Eq + Hash,
+{
+    fn clone(&self) -> Self {
+        Self {
+            scope: self.scope.clone(),
+        }
+    }
+}
+
+impl<S, M> Deref for ScopedMetric<S, M>
+where
+    S: Eq + Hash,
+{
+    type Target = M;
+
+    fn deref(&self) -> &M {
+        &self.scope().metric
+    }
+}
+
+impl<S, M> Drop for ScopedMetric<S, M>
+where
+    S: Eq + Hash,
+{
+    fn drop(&mut self) {
+        let Some(scope) = self.scope.take() else {
+            return;
+        };
+
+        // This weak reference keeps the allocation of the scope alive, so
+        // that its address can't be reused by a new scope, even if the
+        // series was drained from the family in the meantime.
+        let weak = Arc::downgrade(&scope);
+
+        let Some(scope) = Arc::into_inner(scope) else {
+            return;
+        };
+
+        let Some(metrics) = scope.metrics.upgrade() else {
+            return;
+        };
+
+        let mut write_guard = metrics.write();
+
+        // The series may have been removed and recreated by someone else
+        // since this scope was created, in which case it must be left alone.
+        let is_current = write_guard
+            .series
+            .get(&scope.label_set)
+            .is_some_and(|series| series.scope.ptr_eq(&weak));
+
+        if is_current {
+            write_guard.remove(&scope.label_set);
+        }
+    }
+}
+
+impl<S, M> fmt::Debug for ScopedMetric<S, M>
+where
+    S: Eq + Hash + fmt::Debug,
+    M: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScopedMetric")
+            .field("label_set", &self.scope().label_set)
+            .field("metric", &self.scope().metric)
+            .finish()
+    }
+}
+
 /// A wrapper around [`crate::nonstandard::InfoGauge`] which
 /// encodes its labels with [`Serialize`] instead of [`Encode`].
 ///
@@ -208,7 +889,8 @@ where
 /// ```
 #[derive(Debug)]
 pub struct InfoGauge<S> {
-    inner: InnerInfoGauge<Bridge<S>>,
+    label_set: Arc<RwLock<S>>,
+    options: Options,
 }
 
 impl<S> InfoGauge<S>
@@ -217,7 +899,68 @@ where
 {
     pub fn new(label_set: S) -> Self {
         Self {
-            inner: InnerInfoGauge::new(Bridge(label_set)),
+            label_set: Arc::new(RwLock::new(label_set)),
+            options: Options::default(),
+        }
+    }
+
+    /// Sets the options used to serialize the label set of this info gauge.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Replaces the label set of this info gauge, and of all its clones.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::InfoGauge;
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Serialize)]
+    /// struct Config {
+    ///     log_level: &'static str,
+    /// }
+    ///
+    /// let info = InfoGauge::new(Config { log_level: "info" });
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("config", "Runtime configuration", info.clone());
+    ///
+    /// info.set(Config { log_level: "debug" });
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP config Runtime configuration.\n",
+    ///         "# TYPE config gauge\n",
+    ///         "config{log_level=\"debug\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn set(&self, label_set: S) {
+        *self.label_set.write() = label_set;
+    }
+}
+
+impl<S> Clone for InfoGauge<S> {
+    fn clone(&self) -> Self {
+        Self {
+            label_set: self.label_set.clone(),
+            options: self.options.clone(),
         }
     }
 }
@@ -226,8 +969,15 @@ impl<S> EncodeMetric for InfoGauge<S>
 where
     S: Serialize,
 {
-    fn encode(&self, encoder: Encoder) -> io::Result<()> {
-        self.inner.encode(encoder)
+    fn encode(&self, mut encoder: Encoder) -> io::Result<()> {
+        encoder
+            .with_label_set(&Bridge::new(&*self.label_set.read(), &self.options))
+            .no_suffix()?
+            .no_bucket()?
+            .encode_value(1u32)?
+            .no_exemplar()?;
+
+        Ok(())
     }
 
     fn metric_type(&self) -> MetricType {
@@ -242,34 +992,298 @@ where
     const TYPE: MetricType = <InnerInfoGauge<S> as TypedMetric>::TYPE;
 }
 
-#[derive(Clone, Eq, Hash, PartialEq)]
-#[repr(transparent)]
-struct Bridge<S>(S);
+/// An info metric, like [`prometheus_client::metrics::info::Info`], which
+/// encodes its labels with [`Serialize`] instead of [`Encode`].
+///
+/// Unlike [`InfoGauge`], it follows OpenMetrics: it is declared with the
+/// `info` type and its sample has the `_info` suffix, so it must be
+/// registered without that suffix.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::Info;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Serialize)]
+/// struct BuildInfo {
+///     version: &'static str,
+///     commit: &'static str,
+/// }
+///
+/// let info = Info::new(BuildInfo {
+///     version: "1.2.3",
+///     commit: "4f1c2e9",
+/// });
+///
+/// let mut registry = Registry::default();
+///
+/// registry.register("build", "Build information", info.clone());
+///
+/// info.set(BuildInfo {
+///     version: "1.2.4",
+///     commit: "a7d03b1",
+/// });
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP build Build information.\n",
+///         "# TYPE build info\n",
+///         "build_info{version=\"1.2.4\",commit=\"a7d03b1\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct Info<S> {
+    label_set: Arc<RwLock<S>>,
+    options: Options,
+}
+
+impl<S> Info<S>
+where
+    S: Serialize,
+{
+    pub fn new(label_set: S) -> Self {
+        Self {
+            label_set: Arc::new(RwLock::new(label_set)),
+            options: Options::default(),
+        }
+    }
+
+    /// Sets the options used to serialize the label set of this info metric.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
 
-impl<S> Bridge<S> {
-    fn from_ref(label_set: &S) -> &Self {
-        // SAFETY: `Self` is a transparent newtype wrapper.
-        unsafe { &*(label_set as *const S as *const Bridge<S>) }
+    /// Replaces the label set of this info metric, and of all its clones.
+    pub fn set(&self, label_set: S) {
+        *self.label_set.write() = label_set;
     }
 }
 
-impl<S> Encode for Bridge<S>
+impl<S> Clone for Info<S> {
+    fn clone(&self) -> Self {
+        Self {
+            label_set: self.label_set.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<S> EncodeMetric for Info<S>
 where
     S: Serialize,
 {
-    fn encode(&self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
-        self.0
-            .serialize(top::serializer(str::Writer::new(writer)))?;
+    fn encode(&self, mut encoder: Encoder) -> io::Result<()> {
+        encoder
+            .with_label_set(&Bridge::new(&*self.label_set.read(), &self.options))
+            .encode_suffix("info")?
+            .no_bucket()?
+            .encode_value(1u32)?
+            .no_exemplar()?;
 
         Ok(())
     }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
 }
 
-impl<S> fmt::Debug for Bridge<S>
+impl<S> TypedMetric for Info<S>
 where
-    S: fmt::Debug,
+    S: Serialize,
 {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        self.0.fmt(f)
+    const TYPE: MetricType = MetricType::Info;
+}
+
+/// A set of info gauges sharing the same name, each of them encoding its
+/// labels with [`Serialize`].
+///
+/// Every label set in the set is collected as its own GAUGE sample with
+/// value 1, like [`InfoGauge`].
+///
+/// #### Examples
+///
+/// Basic usage:
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::InfoGaugeSet;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// struct Target {
+///     name: &'static str,
+/// }
+///
+/// let targets = InfoGaugeSet::default();
+/// let mut registry = Registry::default();
+///
+/// registry.register("target", "Attached targets", targets.clone());
+///
+/// targets.insert(Target { name: "primary" });
+/// targets.insert(Target { name: "replica" });
+/// targets.remove(&Target { name: "replica" });
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP target Attached targets.\n",
+///         "# TYPE target gauge\n",
+///         "target{name=\"primary\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct InfoGaugeSet<S> {
+    label_sets: Arc<RwLock<HashSet<S>>>,
+    options: Options,
+}
+
+impl<S> InfoGaugeSet<S>
+where
+    S: Eq + Hash,
+{
+    /// Sets the options used to serialize the label sets of this set.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds a label set to the set, returning whether it was newly inserted.
+    pub fn insert(&self, label_set: S) -> bool {
+        self.label_sets.write().insert(label_set)
+    }
+
+    /// Removes a label set from the set, returning whether it was present.
+    pub fn remove(&self, label_set: &S) -> bool {
+        self.label_sets.write().remove(label_set)
+    }
+
+    /// Removes all label sets from the set.
+    pub fn clear(&self) {
+        self.label_sets.write().clear()
+    }
+}
+
+impl<S> Default for InfoGaugeSet<S> {
+    fn default() -> Self {
+        Self {
+            label_sets: Default::default(),
+            options: Options::default(),
+        }
+    }
+}
+
+impl<S> Clone for InfoGaugeSet<S> {
+    fn clone(&self) -> Self {
+        Self {
+            label_sets: self.label_sets.clone(),
+            options: self.options.clone(),
+        }
+    }
+}
+
+impl<S> EncodeMetric for InfoGaugeSet<S>
+where
+    S: Serialize,
+{
+    fn encode(&self, mut encoder: Encoder) -> io::Result<()> {
+        for label_set in self.label_sets.read().iter() {
+            encoder
+                .with_label_set(&Bridge::new(label_set, &self.options))
+                .no_suffix()?
+                .no_bucket()?
+                .encode_value(1u32)?
+                .no_exemplar()?;
+        }
+
+        Ok(())
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S> TypedMetric for InfoGaugeSet<S> {
+    const TYPE: MetricType = <InnerInfoGauge<S> as TypedMetric>::TYPE;
+}
+
+struct Bridge<'a, S> {
+    label_set: &'a S,
+    options: &'a Options,
+    encoder: Option<LabelSetEncoder<S>>,
+}
+
+impl<'a, S> Bridge<'a, S> {
+    fn new(label_set: &'a S, options: &'a Options) -> Self {
+        Self {
+            label_set,
+            options,
+            encoder: None,
+        }
+    }
+}
+
+fn merge_key<S>(
+    label_set: &S,
+    options: &Options,
+    encoder: Option<LabelSetEncoder<S>>,
+) -> Option<Vec<u8>>
+where
+    S: Serialize,
+{
+    let mut serialized = vec![];
+    let bridge = Bridge {
+        label_set,
+        options,
+        encoder,
+    };
+
+    bridge.encode(&mut serialized).ok()?;
+
+    Some(serialized)
+}
+
+impl<S> Encode for Bridge<'_, S>
+where
+    S: Serialize,
+{
+    fn encode(&self, writer: &mut dyn io::Write) -> Result<(), std::io::Error> {
+        if let Some(encoder) = self.encoder {
+            return encoder(self.label_set, writer, self.options);
+        }
+
+        self.label_set
+            .serialize(top::serializer(str::Writer::new(writer), self.options))?;
+
+        Ok(())
     }
 }
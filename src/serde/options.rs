@@ -0,0 +1,460 @@
+/// Options controlling how label sets are serialized.
+///
+/// #### Examples
+///
+/// Struct variants can be used as label sets by naming a label for the
+/// variant name:
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::counter::Counter,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::{Family, Options};
+/// # use serde::Serialize;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// #[serde(rename_all = "lowercase")]
+/// enum Kind {
+///     Read { table: &'static str },
+///     Write { table: &'static str },
+/// }
+///
+/// let family = <Family<Kind, Counter>>::default()
+///     .with_options(Options::default().variant_key("kind"));
+/// let mut registry = Registry::default();
+///
+/// registry.register("queries", "Number of queries", family.clone());
+///
+/// family.get_or_create(&Kind::Read { table: "users" }).inc();
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP queries Number of queries.\n",
+///         "# TYPE queries counter\n",
+///         "queries_total{kind=\"read\",table=\"users\"} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+    pub(super) variant_key: Option<&'static str>,
+    pub(super) bool_format: BoolFormat,
+    pub(super) value_limit: Option<(usize, &'static str)>,
+    pub(super) sanitize_keys: bool,
+    pub(super) float_format: FloatFormat,
+    pub(super) bytes_format: BytesFormat,
+    pub(super) newtype_variant_format: NewtypeVariantFormat,
+}
+
+/// How floating-point label values are serialized.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum FloatFormat {
+    /// The shortest representation that roundtrips, e.g. `0.30000000000000004`.
+    #[default]
+    Shortest,
+    /// Exactly that many decimals, e.g. `0.30` for 2 decimals.
+    Fixed(usize),
+    /// At most that many decimals, without trailing zeros, e.g. `0.3` for
+    /// 2 decimals.
+    MaxDecimals(usize),
+}
+
+/// How byte slice label values are serialized.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BytesFormat {
+    /// Byte slices are rejected.
+    #[default]
+    Reject,
+    /// Lowercase hexadecimal, e.g. `c0ffee`.
+    Hex,
+    /// Standard base64 with padding, e.g. `wP/u`.
+    Base64,
+}
+
+/// How newtype variant label values are serialized.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum NewtypeVariantFormat {
+    /// Newtype variants are rejected.
+    #[default]
+    Reject,
+    /// The inner value alone, e.g. `example.org`.
+    ///
+    /// Different variants may then be serialized the same way, e.g. a unit
+    /// variant and a newtype variant whose inner value is its name, whose
+    /// series a [`Family`](super::Family) merges.
+    Inner,
+    /// The variant name, followed by `:` and the inner value, e.g.
+    /// `Upstream:example.org`.
+    Prefixed,
+}
+
+/// How boolean label values are serialized.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum BoolFormat {
+    /// `true` and `false`.
+    #[default]
+    Literal,
+    /// `1` and `0`.
+    Numeric,
+}
+
+impl Options {
+    /// Allows struct variants as label sets, serializing the variant name
+    /// as the value of the label `key`, followed by the variant's fields.
+    ///
+    /// Without this option, struct variants are rejected like any other
+    /// enum variant.
+    pub fn variant_key(mut self, key: &'static str) -> Self {
+        self.variant_key = Some(key);
+        self
+    }
+
+    /// Sets how boolean label values are serialized, which defaults to
+    /// [`BoolFormat::Literal`].
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{BoolFormat, InfoGauge, Options};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Serialize)]
+    /// struct Features {
+    ///     tls: bool,
+    /// }
+    ///
+    /// let info = InfoGauge::new(Features { tls: true })
+    ///     .with_options(Options::default().bool_format(BoolFormat::Numeric));
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("features", "Enabled features", info);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP features Enabled features.\n",
+    ///         "# TYPE features gauge\n",
+    ///         "features{tls=\"1\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn bool_format(mut self, format: BoolFormat) -> Self {
+        self.bool_format = format;
+        self
+    }
+
+    /// Truncates string label values longer than `max_len` bytes, appending
+    /// `marker` to them.
+    ///
+    /// Values are truncated on a char boundary, so they can end up shorter
+    /// than `max_len` bytes, not counting the marker.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{InfoGauge, Options};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Serialize)]
+    /// struct LastError {
+    ///     message: &'static str,
+    /// }
+    ///
+    /// let info = InfoGauge::new(LastError {
+    ///     message: "connection reset by peer",
+    /// })
+    /// .with_options(Options::default().truncate_values(10, "..."));
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("last_error", "Last error", info);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP last_error Last error.\n",
+    ///         "# TYPE last_error gauge\n",
+    ///         "last_error{message=\"connection...\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn truncate_values(mut self, max_len: usize, marker: &'static str) -> Self {
+        self.value_limit = Some((max_len, marker));
+        self
+    }
+
+    /// Sets whether invalid label names are sanitized instead of being
+    /// rejected, which is disabled by default.
+    ///
+    /// When enabled, chars other than ASCII alphanumerics, `_` and `:` are
+    /// replaced by `_`, and names starting with a digit are prefixed by `_`.
+    /// A [`Family`](super::Family) merges the series of label sets whose
+    /// names are sanitized to the same ones.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{InfoGauge, Options};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Serialize)]
+    /// struct Versions {
+    ///     #[serde(rename = "http-version")]
+    ///     http: &'static str,
+    ///     #[serde(rename = "2fa")]
+    ///     two_factor: bool,
+    /// }
+    ///
+    /// let info = InfoGauge::new(Versions {
+    ///     http: "1.1",
+    ///     two_factor: true,
+    /// })
+    /// .with_options(Options::default().sanitize_keys(true));
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("versions", "Versions", info);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP versions Versions.\n",
+    ///         "# TYPE versions gauge\n",
+    ///         "versions{http_version=\"1.1\",_2fa=\"true\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn sanitize_keys(mut self, sanitize: bool) -> Self {
+        self.sanitize_keys = sanitize;
+        self
+    }
+
+    /// Sets how floating-point label values are serialized, which defaults
+    /// to [`FloatFormat::Shortest`].
+    ///
+    /// This avoids exposing spurious precision in values resulting from
+    /// computations. A [`Family`](super::Family) merges the series of label
+    /// sets which only differ by values rounding to the same representation.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     metrics::counter::Counter,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{Family, FloatFormat, Options};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     ratio: f64,
+    /// }
+    /// #
+    /// # impl Eq for Labels {}
+    /// #
+    /// # impl std::hash::Hash for Labels {
+    /// #     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    /// #         self.ratio.to_bits().hash(state)
+    /// #     }
+    /// # }
+    ///
+    /// let family = <Family<Labels, Counter>>::default()
+    ///     .with_options(Options::default().float_format(FloatFormat::MaxDecimals(2)));
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("samples", "Number of samples", family.clone());
+    ///
+    /// family.get_or_create(&Labels { ratio: 0.1 + 0.2 }).inc();
+    /// family.get_or_create(&Labels { ratio: 0.3 }).inc();
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP samples Number of samples.\n",
+    ///         "# TYPE samples counter\n",
+    ///         "samples_total{ratio=\"0.3\"} 2\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn float_format(mut self, format: FloatFormat) -> Self {
+        self.float_format = format;
+        self
+    }
+
+    /// Sets how byte slice label values are serialized, which defaults to
+    /// [`BytesFormat::Reject`].
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{BytesFormat, InfoGauge, Options};
+    /// # use serde::{Serialize, Serializer};
+    /// #
+    /// #[derive(Serialize)]
+    /// struct Build {
+    ///     #[serde(serialize_with = "as_bytes")]
+    ///     commit: [u8; 4],
+    /// }
+    ///
+    /// fn as_bytes<S: Serializer>(bytes: &[u8; 4], serializer: S) -> Result<S::Ok, S::Error> {
+    ///     serializer.serialize_bytes(bytes)
+    /// }
+    ///
+    /// let info = InfoGauge::new(Build {
+    ///     commit: [0xde, 0xad, 0xbe, 0xef],
+    /// })
+    /// .with_options(Options::default().bytes_format(BytesFormat::Hex));
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("build", "Build information", info);
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP build Build information.\n",
+    ///         "# TYPE build gauge\n",
+    ///         "build{commit=\"deadbeef\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn bytes_format(mut self, format: BytesFormat) -> Self {
+        self.bytes_format = format;
+        self
+    }
+
+    /// Sets how newtype variant label values are serialized, which defaults
+    /// to [`NewtypeVariantFormat::Reject`].
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometheus_client::{
+    /// #     encoding::text::encode,
+    /// #     metrics::counter::Counter,
+    /// #     registry::Registry,
+    /// # };
+    /// # use prometools::serde::{Family, NewtypeVariantFormat, Options};
+    /// # use serde::Serialize;
+    /// #
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// struct Labels {
+    ///     source: Source,
+    /// }
+    ///
+    /// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    /// #[serde(rename_all = "lowercase")]
+    /// enum Source {
+    ///     Static,
+    ///     Upstream(String),
+    /// }
+    ///
+    /// let family = <Family<Labels, Counter>>::default().with_options(
+    ///     Options::default().newtype_variant_format(NewtypeVariantFormat::Prefixed),
+    /// );
+    /// let mut registry = Registry::default();
+    ///
+    /// registry.register("responses", "Number of responses", family.clone());
+    ///
+    /// family
+    ///     .get_or_create(&Labels {
+    ///         source: Source::Upstream("example.org".to_owned()),
+    ///     })
+    ///     .inc();
+    ///
+    /// let mut serialized = String::new();
+    ///
+    /// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+    /// unsafe {
+    ///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+    /// }
+    ///
+    /// assert_eq!(
+    ///     serialized,
+    ///     concat!(
+    ///         "# HELP responses Number of responses.\n",
+    ///         "# TYPE responses counter\n",
+    ///         "responses_total{source=\"upstream:example.org\"} 1\n",
+    ///         "# EOF\n",
+    ///     ),
+    /// );
+    /// ```
+    pub fn newtype_variant_format(mut self, format: NewtypeVariantFormat) -> Self {
+        self.newtype_variant_format = format;
+        self
+    }
+
+    /// Returns whether different values may be serialized the same way.
+    pub(super) fn is_lossy(&self) -> bool {
+        self.sanitize_keys
+            || self.float_format != FloatFormat::Shortest
+            || self.newtype_variant_format == NewtypeVariantFormat::Inner
+            || self.value_limit.is_some()
+    }
+}
@@ -0,0 +1,334 @@
+use super::{Equivalent, Family, Options};
+use prometheus_client::{
+    encoding::text::{EncodeMetric, Encoder},
+    metrics::{counter::Counter, MetricType, TypedMetric},
+};
+use serde::ser::{Error as _, Impossible, Serialize, SerializeMap, SerializeStruct, Serializer};
+use std::{fmt, hash::Hash, io};
+
+/// A family of counters of successes and failures, distinguished by an
+/// `outcome` label whose value is `ok` or `err`, in addition to the labels
+/// of `S`.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{encoding::text::encode, registry::Registry};
+/// # use prometools::serde::OutcomeCounter;
+/// # use serde::Serialize;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// struct Labels {
+///     method: &'static str,
+/// }
+///
+/// let requests = <OutcomeCounter<Labels>>::default();
+/// let mut registry = Registry::default();
+///
+/// registry.register("requests", "Number of requests", requests.clone());
+///
+/// let get = Labels { method: "GET" };
+///
+/// requests.ok(&get);
+/// requests.observe(&get, &"42".parse::<u32>());
+/// requests.observe(&get, &"forty-two".parse::<u32>());
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert!(serialized.contains("requests_total{method=\"GET\",outcome=\"ok\"} 2\n"));
+/// assert!(serialized.contains("requests_total{method=\"GET\",outcome=\"err\"} 1\n"));
+/// ```
+#[derive(Debug)]
+pub struct OutcomeCounter<S> {
+    family: Family<OutcomeLabels<S>, Counter>,
+}
+
+/// The value of the `outcome` label of an [`OutcomeCounter`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Outcome {
+    Ok,
+    Err,
+}
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct OutcomeLabels<S> {
+    labels: S,
+    outcome: Outcome,
+}
+
+// Serialize is implemented by hand so that the `serde` feature doesn't need
+// serde's derive macros.
+impl Serialize for Outcome {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Ok => serializer.serialize_unit_variant("Outcome", 0, "ok"),
+            Self::Err => serializer.serialize_unit_variant("Outcome", 1, "err"),
+        }
+    }
+}
+
+impl<S: Serialize> Serialize for OutcomeLabels<S> {
+    fn serialize<T>(&self, serializer: T) -> Result<T::Ok, T::Error>
+    where
+        T: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        self.labels.serialize(Flatten(&mut map))?;
+        map.serialize_entry("outcome", &self.outcome)?;
+        map.end()
+    }
+}
+
+/// A serializer writing the fields of a struct or map to an outer map, like
+/// `#[serde(flatten)]`.
+struct Flatten<'a, M>(&'a mut M);
+
+macro_rules! unflattenable {
+    ($($method:ident($($ty:ty),*) -> $ok:ty;)+) => {$(
+        fn $method(self, $(_: $ty),*) -> Result<$ok, M::Error> {
+            Err(M::Error::custom("only structs and maps can be flattened"))
+        }
+    )+}
+}
+
+impl<M> Serializer for Flatten<'_, M>
+where
+    M: SerializeMap,
+{
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Self;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    unflattenable! {
+        serialize_bool(bool) -> ();
+        serialize_i8(i8) -> ();
+        serialize_i16(i16) -> ();
+        serialize_i32(i32) -> ();
+        serialize_i64(i64) -> ();
+        serialize_u8(u8) -> ();
+        serialize_u16(u16) -> ();
+        serialize_u32(u32) -> ();
+        serialize_u64(u64) -> ();
+        serialize_f32(f32) -> ();
+        serialize_f64(f64) -> ();
+        serialize_char(char) -> ();
+        serialize_str(&str) -> ();
+        serialize_bytes(&[u8]) -> ();
+        serialize_unit_variant(&'static str, u32, &'static str) -> ();
+        serialize_seq(Option<usize>) -> Self::SerializeSeq;
+        serialize_tuple(usize) -> Self::SerializeTuple;
+        serialize_tuple_struct(&'static str, usize) -> Self::SerializeTupleStruct;
+        serialize_tuple_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeTupleVariant;
+        serialize_struct_variant(&'static str, u32, &'static str, usize)
+            -> Self::SerializeStructVariant;
+    }
+
+    fn serialize_unit(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _ty: &'static str,
+        _index: u32,
+        _name: &'static str,
+        _value: &T,
+    ) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        Err(M::Error::custom("only structs and maps can be flattened"))
+    }
+
+    fn serialize_none(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _ty: &'static str, _len: usize) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+
+    fn collect_str<T>(self, _value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + fmt::Display,
+    {
+        Err(M::Error::custom("only structs and maps can be flattened"))
+    }
+}
+
+impl<M: SerializeMap> SerializeMap for Flatten<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_key(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_value(value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+impl<M: SerializeMap> SerializeStruct for Flatten<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), M::Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.0.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+/// A borrowed [`OutcomeLabels`], hashing like it.
+#[derive(Hash)]
+struct OutcomeKey<'a, S> {
+    labels: &'a S,
+    outcome: Outcome,
+}
+
+impl<S: PartialEq> Equivalent<OutcomeLabels<S>> for OutcomeKey<'_, S> {
+    fn equivalent(&self, key: &OutcomeLabels<S>) -> bool {
+        *self.labels == key.labels && self.outcome == key.outcome
+    }
+}
+
+impl<S> OutcomeCounter<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    /// Sets the options used to serialize the label sets of the counters.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.family = self.family.with_options(options);
+        self
+    }
+
+    /// Counts a success.
+    pub fn ok(&self, labels: &S) {
+        self.inc(labels, Outcome::Ok);
+    }
+
+    /// Counts a failure.
+    pub fn err(&self, labels: &S) {
+        self.inc(labels, Outcome::Err);
+    }
+
+    /// Counts a success or a failure, depending on `result`.
+    pub fn observe<T, E>(&self, labels: &S, result: &Result<T, E>) {
+        let outcome = match result {
+            Ok(_) => Outcome::Ok,
+            Err(_) => Outcome::Err,
+        };
+
+        self.inc(labels, outcome);
+    }
+
+    /// Returns the counter of the given outcome.
+    pub fn get(&self, labels: &S, outcome: Outcome) -> u64 {
+        self.counter(labels, outcome).get()
+    }
+
+    fn inc(&self, labels: &S, outcome: Outcome) {
+        self.counter(labels, outcome).inc();
+    }
+
+    fn counter(&self, labels: &S, outcome: Outcome) -> Counter {
+        let key = OutcomeKey { labels, outcome };
+
+        self.family
+            .get_or_create_with_key(&key, || OutcomeLabels {
+                labels: labels.clone(),
+                outcome,
+            })
+            .clone()
+    }
+}
+
+impl<S> Default for OutcomeCounter<S>
+where
+    S: Clone + Eq + Hash,
+{
+    fn default() -> Self {
+        Self {
+            family: Family::default(),
+        }
+    }
+}
+
+impl<S> Clone for OutcomeCounter<S> {
+    fn clone(&self) -> Self {
+        Self {
+            family: self.family.clone(),
+        }
+    }
+}
+
+impl<S> EncodeMetric for OutcomeCounter<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    fn encode(&self, encoder: Encoder) -> io::Result<()> {
+        self.family.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S> TypedMetric for OutcomeCounter<S> {
+    const TYPE: MetricType = MetricType::Counter;
+}
@@ -0,0 +1,400 @@
+use super::{str, top, Family, Options};
+use crate::encoding::parse;
+use crate::histogram::TimeHistogram;
+use prometheus_client::{
+    encoding::text::{EncodeMetric, Encoder},
+    metrics::{counter::Counter, family::MetricConstructor, MetricType, TypedMetric},
+};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::hash::Hash;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The dialect of the packets sent by a [`Statsd`] emitter.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum StatsdFlavor {
+    /// Plain StatsD, which has no tags, so label sets are dropped.
+    #[default]
+    Statsd,
+    /// DogStatsD, with label sets written as tags.
+    DogStatsd,
+}
+
+/// An emitter of StatsD packets over UDP, e.g. to double-write metrics
+/// during a migration.
+///
+/// Label sets are serialized like the label sets of a [`Family`], then
+/// written as DogStatsD tags such as `#method:GET,status:200`, with `|`,
+/// `,` and `#` replaced by `_` in label values.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::serde::{Statsd, StatsdFlavor};
+/// # use serde::Serialize;
+/// # use std::net::UdpSocket;
+/// # use std::time::Duration;
+/// #
+/// #[derive(Serialize)]
+/// struct Labels {
+///     method: &'static str,
+/// }
+///
+/// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let statsd = Statsd::new(agent.local_addr().unwrap())
+///     .unwrap()
+///     .with_prefix("web")
+///     .with_flavor(StatsdFlavor::DogStatsd);
+///
+/// statsd.count("requests", &Labels { method: "GET" }, 1).unwrap();
+/// statsd.timing("latency", &Labels { method: "GET" }, Duration::from_micros(2500)).unwrap();
+///
+/// let mut packet = [0; 512];
+/// let len = agent.recv(&mut packet).unwrap();
+///
+/// assert_eq!(&packet[..len], b"web.requests:1|c|#method:GET");
+///
+/// let len = agent.recv(&mut packet).unwrap();
+///
+/// assert_eq!(&packet[..len], b"web.latency:2.5|ms|#method:GET");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Statsd {
+    socket: Arc<UdpSocket>,
+    prefix: Option<Arc<str>>,
+    flavor: StatsdFlavor,
+    options: Options,
+}
+
+impl Statsd {
+    /// Creates an emitter sending packets to the given address, from an
+    /// ephemeral port.
+    pub fn new(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let addr = addr
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no address to send to"))?;
+        let local: SocketAddr = match addr {
+            SocketAddr::V4(_) => ([0, 0, 0, 0], 0).into(),
+            SocketAddr::V6(_) => ([0; 8], 0).into(),
+        };
+        let socket = UdpSocket::bind(local)?;
+
+        socket.connect(addr)?;
+
+        Ok(Self {
+            socket: Arc::new(socket),
+            prefix: None,
+            flavor: StatsdFlavor::default(),
+            options: Options::default(),
+        })
+    }
+
+    /// Prefixes the name of all metrics, followed by a dot.
+    pub fn with_prefix(mut self, prefix: impl Into<Arc<str>>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    pub fn with_flavor(mut self, flavor: StatsdFlavor) -> Self {
+        self.flavor = flavor;
+        self
+    }
+
+    /// Sets the options used to serialize label sets.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sends a counter increment.
+    pub fn count<S: Serialize>(&self, name: &str, label_set: &S, value: u64) -> io::Result<()> {
+        self.send(name, label_set, value, "c")
+    }
+
+    /// Sends a duration, in milliseconds.
+    pub fn timing<S: Serialize>(
+        &self,
+        name: &str,
+        label_set: &S,
+        duration: Duration,
+    ) -> io::Result<()> {
+        self.send(name, label_set, duration.as_secs_f64() * 1E3, "ms")
+    }
+
+    /// Sends a histogram observation, which plain StatsD sends as a timing.
+    pub fn histogram<S: Serialize>(&self, name: &str, label_set: &S, value: f64) -> io::Result<()> {
+        let ty = match self.flavor {
+            StatsdFlavor::Statsd => "ms",
+            StatsdFlavor::DogStatsd => "h",
+        };
+
+        self.send(name, label_set, value, ty)
+    }
+
+    fn send<S, V>(&self, name: &str, label_set: &S, value: V, ty: &str) -> io::Result<()>
+    where
+        S: Serialize,
+        V: std::fmt::Display,
+    {
+        let mut packet = String::new();
+
+        if let Some(prefix) = &self.prefix {
+            packet.push_str(prefix);
+            packet.push('.');
+        }
+
+        // Writing to a `String` can't fail.
+        let _ = write!(packet, "{name}:{value}|{ty}");
+
+        if self.flavor == StatsdFlavor::DogStatsd {
+            self.write_tags(&mut packet, label_set)?;
+        }
+
+        self.socket.send(packet.as_bytes())?;
+
+        Ok(())
+    }
+
+    fn write_tags<S: Serialize>(&self, packet: &mut String, label_set: &S) -> io::Result<()> {
+        let mut serialized = b"{".to_vec();
+
+        label_set.serialize(top::serializer(
+            str::Writer::new(&mut serialized),
+            &self.options,
+        ))?;
+        serialized.push(b'}');
+
+        let serialized = String::from_utf8(serialized)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        let (labels, _rest) = parse::parse_labels(&serialized)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid label set"))?;
+
+        for (i, (name, value)) in labels.iter().enumerate() {
+            packet.push_str(if i == 0 { "|#" } else { "," });
+            packet.push_str(name);
+            packet.push(':');
+            packet.extend(value.chars().map(|c| match c {
+                '|' | ',' | '#' | '\n' => '_',
+                c => c,
+            }));
+        }
+
+        Ok(())
+    }
+}
+
+/// A family of counters whose increments are also sent to StatsD.
+///
+/// Failing to send an increment doesn't fail the increment itself.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::registry::Registry;
+/// # use prometools::serde::{Statsd, StatsdCounter, StatsdFlavor};
+/// # use serde::Serialize;
+/// # use std::net::UdpSocket;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// struct Labels {
+///     method: &'static str,
+/// }
+///
+/// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let statsd = Statsd::new(agent.local_addr().unwrap())
+///     .unwrap()
+///     .with_flavor(StatsdFlavor::DogStatsd);
+/// let requests = StatsdCounter::new(statsd, "requests");
+/// let mut registry = Registry::default();
+///
+/// registry.register("requests", "Number of requests", requests.clone());
+///
+/// requests.inc_by(&Labels { method: "GET" }, 2);
+///
+/// let mut packet = [0; 512];
+/// let len = agent.recv(&mut packet).unwrap();
+///
+/// assert_eq!(&packet[..len], b"requests:2|c|#method:GET");
+/// assert_eq!(requests.get(&Labels { method: "GET" }), 2);
+/// ```
+#[derive(Debug)]
+pub struct StatsdCounter<S> {
+    family: Family<S, Counter>,
+    statsd: Statsd,
+    name: Arc<str>,
+}
+
+impl<S> StatsdCounter<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    /// Creates a family whose increments are sent as the StatsD counter
+    /// `name`.
+    pub fn new(statsd: Statsd, name: impl Into<Arc<str>>) -> Self {
+        Self {
+            family: Family::default(),
+            statsd,
+            name: name.into(),
+        }
+    }
+
+    /// Sets the options used to serialize the label sets of the counters.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.family = self.family.with_options(options);
+        self
+    }
+
+    pub fn inc(&self, label_set: &S) {
+        self.inc_by(label_set, 1);
+    }
+
+    pub fn inc_by(&self, label_set: &S, v: u64) {
+        self.family.get_or_create(label_set).inc_by(v);
+        let _ = self.statsd.count(&self.name, label_set, v);
+    }
+
+    pub fn get(&self, label_set: &S) -> u64 {
+        self.family.get_or_create(label_set).get()
+    }
+}
+
+impl<S> Clone for StatsdCounter<S> {
+    fn clone(&self) -> Self {
+        Self {
+            family: self.family.clone(),
+            statsd: self.statsd.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl<S> EncodeMetric for StatsdCounter<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    fn encode(&self, encoder: Encoder) -> io::Result<()> {
+        self.family.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S> TypedMetric for StatsdCounter<S> {
+    const TYPE: MetricType = MetricType::Counter;
+}
+
+/// A family of histograms whose observations are also sent to StatsD, as
+/// timings in milliseconds.
+///
+/// Failing to send an observation doesn't fail the observation itself.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::histogram::TimeHistogram;
+/// # use prometools::serde::{Statsd, StatsdHistogram};
+/// # use serde::Serialize;
+/// # use std::net::UdpSocket;
+/// # use std::time::Duration;
+/// #
+/// #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+/// struct Labels {
+///     method: &'static str,
+/// }
+///
+/// let agent = UdpSocket::bind("127.0.0.1:0").unwrap();
+/// let statsd = Statsd::new(agent.local_addr().unwrap()).unwrap();
+/// let buckets = TimeHistogram::new([0.1, 1.0].into_iter());
+/// let latency = StatsdHistogram::new(statsd, "latency", buckets);
+///
+/// latency.observe_duration(&Labels { method: "GET" }, Duration::from_millis(250));
+///
+/// let mut packet = [0; 512];
+/// let len = agent.recv(&mut packet).unwrap();
+///
+/// assert_eq!(&packet[..len], b"latency:250|ms");
+/// assert_eq!(latency.count(&Labels { method: "GET" }), 1);
+/// ```
+#[derive(Debug)]
+pub struct StatsdHistogram<S> {
+    family: Family<S, TimeHistogram, SharedBuckets>,
+    statsd: Statsd,
+    name: Arc<str>,
+}
+
+/// Creates histograms with the bucket upper bounds of a histogram.
+#[derive(Clone, Debug)]
+struct SharedBuckets(TimeHistogram);
+
+impl MetricConstructor<TimeHistogram> for SharedBuckets {
+    fn new_metric(&self) -> TimeHistogram {
+        self.0.with_shared_buckets()
+    }
+}
+
+impl<S> StatsdHistogram<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    /// Creates a family whose observations are sent as the StatsD timing
+    /// `name`, and whose histograms have the same bucket upper bounds as
+    /// `buckets`.
+    pub fn new(statsd: Statsd, name: impl Into<Arc<str>>, buckets: TimeHistogram) -> Self {
+        Self {
+            family: Family::new_with_constructor(SharedBuckets(buckets)),
+            statsd,
+            name: name.into(),
+        }
+    }
+
+    /// Sets the options used to serialize the label sets of the histograms.
+    pub fn with_options(mut self, options: Options) -> Self {
+        self.family = self.family.with_options(options);
+        self
+    }
+
+    pub fn observe_duration(&self, label_set: &S, duration: Duration) {
+        self.family
+            .get_or_create(label_set)
+            .observe_duration(duration);
+        let _ = self.statsd.timing(&self.name, label_set, duration);
+    }
+
+    /// Returns the number of observations of the histogram of `label_set`.
+    pub fn count(&self, label_set: &S) -> u64 {
+        self.family.get_or_create(label_set).count()
+    }
+}
+
+impl<S> Clone for StatsdHistogram<S> {
+    fn clone(&self) -> Self {
+        Self {
+            family: self.family.clone(),
+            statsd: self.statsd.clone(),
+            name: self.name.clone(),
+        }
+    }
+}
+
+impl<S> EncodeMetric for StatsdHistogram<S>
+where
+    S: Clone + Eq + Hash + Serialize,
+{
+    fn encode(&self, encoder: Encoder) -> io::Result<()> {
+        self.family.encode(encoder)
+    }
+
+    fn metric_type(&self) -> MetricType {
+        Self::TYPE
+    }
+}
+
+impl<S> TypedMetric for StatsdHistogram<S> {
+    const TYPE: MetricType = MetricType::Histogram;
+}
@@ -0,0 +1,179 @@
+use super::{InfoGauge, Options};
+use prometheus_client::registry::Registry;
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::env;
+
+/// Resource labels describing the target exposing the metrics, collected as
+/// the conventional `target_info` series.
+///
+/// Labels follow the OpenTelemetry resource semantic conventions, with dots
+/// replaced by underscores, e.g. `service.name` is exposed as
+/// `service_name`.
+///
+/// #### Examples
+///
+/// Basic usage:
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     registry::Registry,
+/// # };
+/// # use prometools::serde::TargetInfo;
+/// #
+/// let mut registry = Registry::default();
+///
+/// TargetInfo::new()
+///     .service_name("checkout")
+///     .service_version("1.2.3")
+///     .service_instance_id("checkout-0")
+///     .service_namespace("shop")
+///     .label("deployment.environment", "production")
+///     .register(&mut registry);
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP target_info Target metadata.\n",
+///         "# TYPE target_info gauge\n",
+///         "target_info{",
+///         "service_name=\"checkout\",",
+///         "service_version=\"1.2.3\",",
+///         "service_instance_id=\"checkout-0\",",
+///         "service_namespace=\"shop\",",
+///         "deployment_environment=\"production\"",
+///         "} 1\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct TargetInfo {
+    labels: Vec<(String, String)>,
+}
+
+impl TargetInfo {
+    /// Creates an empty set of resource labels.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects resource labels from the environment.
+    ///
+    /// Labels are read from the comma-separated `key=value` pairs of
+    /// `OTEL_RESOURCE_ATTRIBUTES`, then `service.name` is overridden by
+    /// `OTEL_SERVICE_NAME` and `service.instance.id` defaults to `HOSTNAME`,
+    /// if these variables are set.
+    ///
+    /// #### Examples
+    ///
+    /// ```rust
+    /// # use prometools::serde::TargetInfo;
+    /// #
+    /// std::env::set_var("OTEL_RESOURCE_ATTRIBUTES", "service.name=cart,service.namespace=shop");
+    /// std::env::set_var("OTEL_SERVICE_NAME", "checkout");
+    /// std::env::set_var("HOSTNAME", "checkout-0");
+    ///
+    /// assert_eq!(
+    ///     TargetInfo::from_env(),
+    ///     TargetInfo::new()
+    ///         .service_name("checkout")
+    ///         .service_namespace("shop")
+    ///         .service_instance_id("checkout-0"),
+    /// );
+    /// ```
+    pub fn from_env() -> Self {
+        let mut info = Self::new();
+
+        if let Ok(attributes) = env::var("OTEL_RESOURCE_ATTRIBUTES") {
+            for (name, value) in attributes.split(',').filter_map(|kv| kv.split_once('=')) {
+                info = info.label(name.trim(), value.trim());
+            }
+        }
+
+        if let Ok(name) = env::var("OTEL_SERVICE_NAME") {
+            info = info.service_name(name);
+        }
+
+        if !info
+            .labels
+            .iter()
+            .any(|(name, _)| name == "service.instance.id")
+        {
+            if let Ok(hostname) = env::var("HOSTNAME") {
+                info = info.service_instance_id(hostname);
+            }
+        }
+
+        info
+    }
+
+    /// Sets the `service.name` label.
+    pub fn service_name(self, value: impl Into<String>) -> Self {
+        self.label("service.name", value)
+    }
+
+    /// Sets the `service.version` label.
+    pub fn service_version(self, value: impl Into<String>) -> Self {
+        self.label("service.version", value)
+    }
+
+    /// Sets the `service.instance.id` label.
+    pub fn service_instance_id(self, value: impl Into<String>) -> Self {
+        self.label("service.instance.id", value)
+    }
+
+    /// Sets the `service.namespace` label.
+    pub fn service_namespace(self, value: impl Into<String>) -> Self {
+        self.label("service.namespace", value)
+    }
+
+    /// Sets an arbitrary label, replacing its previous value if any.
+    pub fn label(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        let name = name.into();
+        let value = value.into();
+
+        match self.labels.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.labels.push((name, value)),
+        }
+
+        self
+    }
+
+    /// Wraps these labels in an [`InfoGauge`], sanitizing their names.
+    pub fn into_gauge(self) -> InfoGauge<Self> {
+        InfoGauge::new(self).with_options(Options::default().sanitize_keys(true))
+    }
+
+    /// Registers these labels as `target_info` in `registry`.
+    pub fn register(self, registry: &mut Registry) {
+        registry.register(
+            "target_info",
+            "Target metadata",
+            Box::new(self.into_gauge()),
+        );
+    }
+}
+
+impl Serialize for TargetInfo {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(Some(self.labels.len()))?;
+
+        for (name, value) in &self.labels {
+            map.serialize_entry(name, value)?;
+        }
+
+        map.end()
+    }
+}
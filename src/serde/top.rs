@@ -1,17 +1,26 @@
 use super::error::{Error, Unexpected};
+use super::key;
+use super::options::Options;
 use super::str::Writer;
 use super::value;
-use serde::ser::{Impossible, Serialize, SerializeStruct, Serializer};
+use serde::ser::{
+    Impossible, Serialize, SerializeMap, SerializeStruct, SerializeStructVariant, Serializer,
+};
 use std::error;
 use std::fmt;
+use std::io;
 
 #[inline]
-pub(super) fn serializer(writer: Writer<'_>) -> impl '_ + Serializer<Ok = (), Error = Error> {
-    TopSerializer { writer }
+pub(super) fn serializer<'w>(
+    writer: Writer<'w>,
+    options: &'w Options,
+) -> impl 'w + Serializer<Ok = (), Error = Error> {
+    TopSerializer { writer, options }
 }
 
 pub(super) struct TopSerializer<'w> {
     writer: Writer<'w>,
+    options: &'w Options,
 }
 
 macro_rules! unsupported_scalars {
@@ -30,9 +39,9 @@ impl<'w> Serializer for TopSerializer<'w> {
     type SerializeTuple = Impossible<(), Error>;
     type SerializeTupleStruct = Impossible<(), Error>;
     type SerializeTupleVariant = Impossible<(), Error>;
-    type SerializeMap = Impossible<(), Error>;
+    type SerializeMap = StructSerializer<'w>;
     type SerializeStruct = StructSerializer<'w>;
-    type SerializeStructVariant = Impossible<(), Error>;
+    type SerializeStructVariant = StructSerializer<'w>;
 
     unsupported_scalars! {
         serialize_bool: Bool(bool),
@@ -145,8 +154,12 @@ impl<'w> Serializer for TopSerializer<'w> {
     }
 
     #[inline]
-    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Error> {
-        Err(unsupported(Unexpected::Map(len)))
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        Ok(StructSerializer {
+            has_written_anything: false,
+            writer: self.writer,
+            options: self.options,
+        })
     }
 
     #[inline]
@@ -158,6 +171,7 @@ impl<'w> Serializer for TopSerializer<'w> {
         Ok(StructSerializer {
             has_written_anything: false,
             writer: self.writer,
+            options: self.options,
         })
     }
 
@@ -167,26 +181,32 @@ impl<'w> Serializer for TopSerializer<'w> {
         ty: &'static str,
         _index: u32,
         name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeStructVariant, Error> {
-        Err(unsupported(Unexpected::Variant(ty, name)))
+        let key = self
+            .options
+            .variant_key
+            .ok_or_else(|| unsupported(Unexpected::Variant(ty, name)))?;
+
+        let mut serializer = self.serialize_struct(ty, len)?;
+        SerializeStruct::serialize_field(&mut serializer, key, name)?;
+
+        Ok(serializer)
     }
 }
 
 pub(super) struct StructSerializer<'w> {
     has_written_anything: bool,
     writer: Writer<'w>,
+    options: &'w Options,
 }
 
-impl SerializeStruct for StructSerializer<'_> {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
-    where
-        T: ?Sized + Serialize,
-    {
-        check_key(key)?;
+impl StructSerializer<'_> {
+    /// Writes a label name, followed by the start of its value.
+    pub(super) fn write_key(&mut self, key: &str) -> Result<(), Error> {
+        if !self.options.sanitize_keys {
+            check_key(key)?;
+        }
 
         if self.has_written_anything {
             self.writer.write_str("\",").map_err(Error::new)?;
@@ -194,10 +214,33 @@ impl SerializeStruct for StructSerializer<'_> {
             self.has_written_anything = true;
         }
 
-        self.writer.write_str(key).map_err(Error::new)?;
-        self.writer.write_str("=\"").map_err(Error::new)?;
+        if self.options.sanitize_keys {
+            write_sanitized_key(self.writer.reborrow(), key).map_err(Error::new)?;
+        } else {
+            self.writer.write_str(key).map_err(Error::new)?;
+        }
+
+        self.writer.write_str("=\"").map_err(Error::new)
+    }
 
-        value.serialize(value::serializer(self.writer.reborrow()))
+    fn write_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        value.serialize(value::serializer(self.writer.reborrow(), self.options))
+    }
+}
+
+impl SerializeStruct for StructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_key(key)?;
+        self.write_value(value)
     }
 
     #[inline]
@@ -210,7 +253,51 @@ impl SerializeStruct for StructSerializer<'_> {
     }
 }
 
-fn check_key(key: &'static str) -> Result<(), Error> {
+impl SerializeMap for StructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        key.serialize(key::serializer(self))
+    }
+
+    #[inline]
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.write_value(value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        SerializeStruct::end(self)
+    }
+}
+
+impl SerializeStructVariant for StructSerializer<'_> {
+    type Ok = ();
+    type Error = Error;
+
+    #[inline]
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        SerializeStruct::serialize_field(self, key, value)
+    }
+
+    #[inline]
+    fn end(self) -> Result<(), Error> {
+        SerializeStruct::end(self)
+    }
+}
+
+fn check_key(key: &str) -> Result<(), Error> {
     let mut chars = key.chars();
 
     chars
@@ -224,9 +311,28 @@ fn check_key(key: &'static str) -> Result<(), Error> {
         .ok_or_else(|| invalid_key(key))
 }
 
-fn invalid_key(key: &'static str) -> Error {
+/// Writes `key`, replacing chars that are not allowed in label names by `_`
+/// and prefixing it with `_` if it starts with a digit.
+fn write_sanitized_key(mut writer: Writer<'_>, mut key: &str) -> io::Result<()> {
+    if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit()) {
+        writer.write_str("_")?;
+    }
+
+    while let Some((i, c)) = key
+        .char_indices()
+        .find(|(_, c)| !c.is_ascii_alphanumeric() && *c != '_' && *c != ':')
+    {
+        writer.write_str(&key[..i])?;
+        writer.write_str("_")?;
+        key = &key[i + c.len_utf8()..];
+    }
+
+    writer.write_str(key)
+}
+
+fn invalid_key(key: &str) -> Error {
     #[derive(Debug)]
-    struct InvalidKeyError(&'static str);
+    struct InvalidKeyError(String);
 
     impl error::Error for InvalidKeyError {
         #[allow(deprecated)]
@@ -241,7 +347,7 @@ fn invalid_key(key: &'static str) -> Error {
         }
     }
 
-    Error::invalid_input(InvalidKeyError(key))
+    Error::invalid_input(InvalidKeyError(key.to_owned()))
 }
 
 fn unsupported(kind: Unexpected) -> Error {
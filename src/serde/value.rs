@@ -1,15 +1,28 @@
 use super::error::{Error, Unexpected};
+use super::options::{BoolFormat, BytesFormat, FloatFormat, NewtypeVariantFormat, Options};
 use super::str::{AsciiPattern, Writer};
 use serde::ser::{Impossible, Serialize, Serializer};
 use std::{error, fmt, io, str};
 
 #[inline]
-pub(super) fn serializer(writer: Writer<'_>) -> impl '_ + Serializer<Ok = (), Error = Error> {
-    ValueSerializer { writer }
+pub(super) fn serializer<'w>(
+    writer: Writer<'w>,
+    options: &'w Options,
+) -> impl 'w + Serializer<Ok = (), Error = Error> {
+    ValueSerializer {
+        writer,
+        options,
+        remaining: options.value_limit.map(|(max_len, _)| max_len),
+        truncated: false,
+    }
 }
 
 struct ValueSerializer<'w> {
     writer: Writer<'w>,
+    options: &'w Options,
+    /// How many more bytes of string data can be written, if limited.
+    remaining: Option<usize>,
+    truncated: bool,
 }
 
 macro_rules! delegate {
@@ -33,7 +46,12 @@ impl<'w> Serializer for ValueSerializer<'w> {
     type SerializeStructVariant = Impossible<Self::Ok, Error>;
 
     fn serialize_bool(mut self, v: bool) -> Result<Self::Ok, Error> {
-        self.write_unchecked(if v { "true" } else { "false" })
+        self.write_unchecked(match (self.options.bool_format, v) {
+            (BoolFormat::Literal, true) => "true",
+            (BoolFormat::Literal, false) => "false",
+            (BoolFormat::Numeric, true) => "1",
+            (BoolFormat::Numeric, false) => "0",
+        })
     }
 
     delegate! {
@@ -75,11 +93,19 @@ impl<'w> Serializer for ValueSerializer<'w> {
     }
 
     fn serialize_str(mut self, value: &str) -> Result<Self::Ok, Error> {
-        write_escaped(self.writer.reborrow(), value).map_err(Error::new)
+        self.write_limited(value)?;
+        self.end()
     }
 
-    fn serialize_bytes(self, _value: &[u8]) -> Result<Self::Ok, Error> {
-        Err(self.unexpected(Unexpected::Bytes))
+    fn serialize_bytes(self, value: &[u8]) -> Result<Self::Ok, Error> {
+        // Both encodings produce 64 chars per chunk, and base64 chunks are
+        // a multiple of 3 bytes long so that padding is only ever needed at
+        // the very end.
+        match self.options.bytes_format {
+            BytesFormat::Reject => Err(self.unexpected(Unexpected::Bytes)),
+            BytesFormat::Hex => self.write_encoded(value, 32, encode_hex),
+            BytesFormat::Base64 => self.write_encoded(value, 48, encode_base64),
+        }
     }
 
     fn serialize_unit(self) -> Result<Self::Ok, Error> {
@@ -103,16 +129,29 @@ impl<'w> Serializer for ValueSerializer<'w> {
     }
 
     fn serialize_newtype_variant<T>(
-        self,
+        mut self,
         ty: &'static str,
         _index: u32,
         name: &'static str,
-        _value: &T,
+        value: &T,
     ) -> Result<Self::Ok, Error>
     where
         T: ?Sized + Serialize,
     {
-        Err(self.unexpected(Unexpected::Variant(ty, name)))
+        match self.options.newtype_variant_format {
+            NewtypeVariantFormat::Reject => Err(self.unexpected(Unexpected::Variant(ty, name))),
+            NewtypeVariantFormat::Inner => value.serialize(self),
+            NewtypeVariantFormat::Prefixed => {
+                self.write_limited(name)?;
+                self.write_limited(":")?;
+
+                if self.truncated {
+                    return self.end();
+                }
+
+                value.serialize(self)
+            }
+        }
     }
 
     fn serialize_none(self) -> Result<Self::Ok, Error> {
@@ -178,17 +217,17 @@ impl<'w> Serializer for ValueSerializer<'w> {
     where
         T: ?Sized + fmt::Display,
     {
-        struct Adapter<'w> {
-            writer: Writer<'w>,
+        struct Adapter<'a, 'w> {
+            serializer: &'a mut ValueSerializer<'w>,
             error: Option<Error>,
         }
 
-        impl<'w> fmt::Write for Adapter<'w> {
+        impl fmt::Write for Adapter<'_, '_> {
             fn write_str(&mut self, s: &str) -> fmt::Result {
                 debug_assert!(self.error.is_none());
 
-                write_escaped(self.writer.reborrow(), s).map_err(|err| {
-                    self.error = Some(Error::new(err));
+                self.serializer.write_limited(s).map_err(|err| {
+                    self.error = Some(err);
 
                     fmt::Error
                 })
@@ -196,7 +235,7 @@ impl<'w> Serializer for ValueSerializer<'w> {
         }
 
         let mut adapter = Adapter {
-            writer: self.writer.reborrow(),
+            serializer: &mut self,
             error: None,
         };
 
@@ -204,7 +243,7 @@ impl<'w> Serializer for ValueSerializer<'w> {
             Ok(()) => {
                 debug_assert!(adapter.error.is_none());
 
-                Ok(())
+                self.end()
             }
             Err(fmt::Error) => Err(adapter.error.expect("there should be an error")),
         }
@@ -228,12 +267,78 @@ impl<'w> ValueSerializer<'w> {
 
     fn serialize_floating<F>(mut self, value: F) -> Result<(), Error>
     where
-        F: ryu::Float,
+        F: ryu::Float + Into<f64>,
     {
-        let mut buf = ryu::Buffer::new();
-        let part = buf.format(value);
+        match self.options.float_format {
+            FloatFormat::Shortest => {
+                let mut buf = ryu::Buffer::new();
+                let part = buf.format(value);
 
-        self.write_unchecked(part)
+                self.write_unchecked(part)
+            }
+            FloatFormat::Fixed(decimals) => {
+                self.write_unchecked(&format!("{:.*}", decimals, value.into()))
+            }
+            FloatFormat::MaxDecimals(decimals) => {
+                let mut part = format!("{:.*}", decimals, value.into());
+
+                if part.contains('.') {
+                    part.truncate(part.trim_end_matches('0').trim_end_matches('.').len());
+                }
+
+                self.write_unchecked(&part)
+            }
+        }
+    }
+
+    fn write_encoded(
+        mut self,
+        bytes: &[u8],
+        chunk_len: usize,
+        encode: fn(&[u8], &mut [u8; 64]) -> usize,
+    ) -> Result<(), Error> {
+        for chunk in bytes.chunks(chunk_len) {
+            let mut buf = [0; 64];
+            let len = encode(chunk, &mut buf);
+
+            // SAFETY: Both encodings only produce ASCII chars.
+            self.write_limited(unsafe { str::from_utf8_unchecked(&buf[..len]) })?;
+        }
+
+        self.end()
+    }
+
+    /// Writes string data, truncating it if it goes over the limit set in
+    /// the options.
+    fn write_limited(&mut self, mut s: &str) -> Result<(), Error> {
+        if let Some(remaining) = &mut self.remaining {
+            if s.len() > *remaining {
+                let mut end = *remaining;
+                while !s.is_char_boundary(end) {
+                    end -= 1;
+                }
+
+                s = &s[..end];
+                self.truncated = true;
+                // Nothing can be written after a truncation, even if a char
+                // boundary was found before the limit.
+                *remaining = 0;
+            } else {
+                *remaining -= s.len();
+            }
+        }
+
+        write_escaped(self.writer.reborrow(), s).map_err(Error::new)
+    }
+
+    /// Writes the truncation marker if string data was truncated.
+    fn end(mut self) -> Result<(), Error> {
+        match self.options.value_limit {
+            Some((_, marker)) if self.truncated => {
+                write_escaped(self.writer.reborrow(), marker).map_err(Error::new)
+            }
+            _ => Ok(()),
+        }
     }
 
     fn write_unchecked(&mut self, raw: &str) -> Result<(), Error> {
@@ -261,6 +366,46 @@ impl<'w> ValueSerializer<'w> {
     }
 }
 
+fn encode_hex(bytes: &[u8], buf: &mut [u8; 64]) -> usize {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    debug_assert!(bytes.len() <= 32);
+
+    for (byte, out) in bytes.iter().zip(buf.chunks_exact_mut(2)) {
+        out[0] = DIGITS[usize::from(byte >> 4)];
+        out[1] = DIGITS[usize::from(byte & 0xf)];
+    }
+
+    bytes.len() * 2
+}
+
+fn encode_base64(bytes: &[u8], buf: &mut [u8; 64]) -> usize {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    debug_assert!(bytes.len() <= 48);
+
+    let mut len = 0;
+
+    for group in bytes.chunks(3) {
+        let n = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, byte)| n | u32::from(*byte) << (16 - 8 * i));
+
+        for i in 0..4 {
+            buf[len + i] = if i <= group.len() {
+                ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize]
+            } else {
+                b'='
+            };
+        }
+
+        len += 4;
+    }
+
+    len
+}
+
 fn write_escaped(mut writer: Writer<'_>, mut s: &str) -> Result<(), io::Error> {
     const PATTERN: AsciiPattern = AsciiPattern::new(b"\"\\\n");
 
@@ -0,0 +1,107 @@
+//! Timers driven by the lifetime of `tracing` spans.
+//!
+//! A [`HistogramTimer`] attached to a span with [`SpanExt::attach_timer`] is
+//! stopped and recorded by a [`TimerLayer`] when the span closes, so span
+//! lifetimes drive latency metrics without plumbing timers through every
+//! handler.
+//!
+//! Timers are stored in the extensions of the span, so the subscriber must
+//! be built on the [`Registry`] of `tracing-subscriber`.
+
+use crate::histogram::HistogramTimer;
+use tracing::{span::Id, Span, Subscriber};
+use tracing_subscriber::{
+    layer::Context,
+    registry::{LookupSpan, Registry},
+    Layer,
+};
+
+/// A layer recording the timers attached to spans when they close.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometools::{
+/// #     histogram::TimeHistogram,
+/// #     tracing::{SpanExt, TimerLayer},
+/// # };
+/// # use tracing_subscriber::layer::SubscriberExt;
+/// #
+/// let histogram = TimeHistogram::new([0.1, 1.0].into_iter());
+/// let subscriber = tracing_subscriber::registry().with(TimerLayer::new());
+///
+/// tracing::subscriber::with_default(subscriber, || {
+///     let span = tracing::info_span!("request");
+///
+/// assert!(span.attach_timer(histogram.start_timer()).is_ok());
+///
+///     assert_eq!(histogram.snapshot().count(), 0);
+///
+///     drop(span);
+/// });
+///
+/// assert_eq!(histogram.snapshot().count(), 1);
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimerLayer {
+    _private: (),
+}
+
+impl TimerLayer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<S> Layer<S> for TimerLayer
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(&id) else {
+            return;
+        };
+        let timers = span.extensions_mut().remove::<SpanTimers>();
+
+        for timer in timers.into_iter().flat_map(|timers| timers.0) {
+            timer.stop_and_record();
+        }
+    }
+}
+
+/// The timers attached to a span.
+struct SpanTimers(Vec<HistogramTimer>);
+
+/// Extension methods of [`Span`].
+pub trait SpanExt {
+    /// Attaches a timer to the span, which is recorded when the span closes
+    /// if its subscriber has a [`TimerLayer`].
+    ///
+    /// Returns the timer if the span is disabled, or if its subscriber isn't
+    /// built on the [`Registry`] of `tracing-subscriber`.
+    fn attach_timer(&self, timer: HistogramTimer) -> Result<(), HistogramTimer>;
+}
+
+impl SpanExt for Span {
+    fn attach_timer(&self, timer: HistogramTimer) -> Result<(), HistogramTimer> {
+        let mut timer = Some(timer);
+
+        self.with_subscriber(|(id, dispatch)| {
+            let Some(span) = dispatch
+                .downcast_ref::<Registry>()
+                .and_then(|registry| registry.span(id))
+            else {
+                return;
+            };
+            let mut extensions = span.extensions_mut();
+            let timer = timer.take().expect("timer should only be attached once");
+
+            match extensions.get_mut::<SpanTimers>() {
+                Some(timers) => timers.0.push(timer),
+                None => extensions.insert(SpanTimers(vec![timer])),
+            }
+        });
+
+        timer.map_or(Ok(()), Err)
+    }
+}
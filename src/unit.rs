@@ -0,0 +1,155 @@
+//! Registration of metrics with units.
+//!
+//! [`Registry::register_with_unit`] always appends the unit to the name of
+//! the metric, so registering `latency_seconds` with [`Unit::Seconds`] ends
+//! up exposing `latency_seconds_seconds`. [`UnitRegistry`] strips the unit
+//! suffix from names instead, so that a unit is always exposed exactly once,
+//! along with its `# UNIT` line.
+
+use prometheus_client::{
+    encoding::text::SendSyncEncodeMetric,
+    registry::{Registry, Unit},
+};
+
+/// A wrapper around a [`Registry`] which records the unit of each metric
+/// consistently.
+///
+/// #### Examples
+///
+/// ```rust
+/// # use prometheus_client::{
+/// #     encoding::text::encode,
+/// #     metrics::gauge::Gauge,
+/// #     registry::{Registry, Unit},
+/// # };
+/// # use prometools::{histogram::TimeHistogram, unit::UnitRegistry};
+/// #
+/// let mut registry = <Registry>::default();
+/// let mut units = UnitRegistry::new(&mut registry);
+///
+/// units.register(
+///     "latency_seconds",
+///     "Request latency",
+///     Box::new(TimeHistogram::new([1.0].into_iter())),
+/// );
+/// units.register_with_unit(
+///     "body_size_bytes",
+///     "Request body size",
+///     Unit::Bytes,
+///     Box::new(Gauge::<u64>::default()),
+/// );
+///
+/// let mut serialized = String::new();
+///
+/// // SAFETY: We know prometheus-client only writes UTF-8 slices.
+/// unsafe {
+///     encode(&mut serialized.as_mut_vec(), &registry).unwrap();
+/// }
+///
+/// assert_eq!(
+///     serialized,
+///     concat!(
+///         "# HELP latency_seconds Request latency.\n",
+///         "# TYPE latency_seconds histogram\n",
+///         "# UNIT latency_seconds seconds\n",
+///         "latency_seconds_sum 0.0\n",
+///         "latency_seconds_count 0\n",
+///         "latency_seconds_bucket{le=\"1.0\"} 0\n",
+///         "latency_seconds_bucket{le=\"+Inf\"} 0\n",
+///         "# HELP body_size_bytes Request body size.\n",
+///         "# TYPE body_size_bytes gauge\n",
+///         "# UNIT body_size_bytes bytes\n",
+///         "body_size_bytes 0\n",
+///         "# EOF\n",
+///     ),
+/// );
+/// ```
+#[derive(Debug)]
+pub struct UnitRegistry<'a, M = Box<dyn SendSyncEncodeMetric>> {
+    registry: &'a mut Registry<M>,
+}
+
+impl<'a, M> UnitRegistry<'a, M> {
+    pub fn new(registry: &'a mut Registry<M>) -> Self {
+        Self { registry }
+    }
+
+    /// Registers a metric, inferring its unit from the suffix of its name.
+    ///
+    /// Names ending with one of the units known by OpenMetrics, e.g.
+    /// `_seconds` or `_bytes`, are registered with that unit. Other names
+    /// are registered without any unit.
+    pub fn register(&mut self, name: &str, help: impl Into<String>, metric: M) {
+        let unit = name
+            .rsplit_once('_')
+            .filter(|(name, _)| !name.is_empty())
+            .and_then(|(_, unit)| known_unit(unit));
+
+        match unit {
+            Some(unit) => self.register_with_unit(name, help, unit, metric),
+            None => self.registry.register(name, help, metric),
+        }
+    }
+
+    /// Registers a metric with the given unit, stripping the unit from the
+    /// end of its name if it is already there.
+    pub fn register_with_unit(
+        &mut self,
+        name: &str,
+        help: impl Into<String>,
+        unit: Unit,
+        metric: M,
+    ) {
+        let name = strip_unit(name, &unit).unwrap_or(name);
+
+        self.registry.register_with_unit(name, help, unit, metric)
+    }
+
+    /// Creates a sub-registry with a common prefix, see
+    /// [`Registry::sub_registry_with_prefix`].
+    pub fn sub_registry_with_prefix(&mut self, prefix: impl AsRef<str>) -> UnitRegistry<'_, M> {
+        UnitRegistry::new(self.registry.sub_registry_with_prefix(prefix))
+    }
+
+    /// Returns the wrapped registry.
+    pub fn registry(&mut self) -> &mut Registry<M> {
+        self.registry
+    }
+}
+
+/// Returns `name` without its `_{unit}` suffix, if it has one.
+fn strip_unit<'n>(name: &'n str, unit: &Unit) -> Option<&'n str> {
+    name.strip_suffix(unit_name(unit))?
+        .strip_suffix('_')
+        .filter(|name| !name.is_empty())
+}
+
+fn unit_name(unit: &Unit) -> &str {
+    match unit {
+        Unit::Amperes => "amperes",
+        Unit::Bytes => "bytes",
+        Unit::Celsius => "celsius",
+        Unit::Grams => "grams",
+        Unit::Joules => "joules",
+        Unit::Meters => "meters",
+        Unit::Ratios => "ratios",
+        Unit::Seconds => "seconds",
+        Unit::Volts => "volts",
+        Unit::Other(other) => other,
+    }
+}
+
+fn known_unit(name: &str) -> Option<Unit> {
+    Some(match name {
+        "amperes" => Unit::Amperes,
+        "bytes" => Unit::Bytes,
+        "celsius" => Unit::Celsius,
+        "grams" => Unit::Grams,
+        "joules" => Unit::Joules,
+        "meters" => Unit::Meters,
+        "ratios" => Unit::Ratios,
+        "seconds" => Unit::Seconds,
+        "volts" => Unit::Volts,
+        _ => return None,
+    })
+}
@@ -0,0 +1,132 @@
+//! Helpers shared by the integration tests, each of which only uses some of
+//! them.
+
+#![allow(dead_code)]
+
+use prometheus_client::{encoding::text, registry::Registry};
+use std::io;
+
+/// Encodes the registry in the OpenMetrics text format.
+pub fn encode_to_string(registry: &Registry) -> String {
+    encode_to_string_with(registry, text::encode)
+}
+
+/// Encodes the registry with `encode`, which must write UTF-8.
+pub fn encode_to_string_with(
+    registry: &Registry,
+    encode: impl FnOnce(&mut Vec<u8>, &Registry) -> io::Result<()>,
+) -> String {
+    let mut serialized = vec![];
+
+    encode(&mut serialized, registry).unwrap();
+
+    String::from_utf8(serialized).unwrap()
+}
+
+/// A decoded protobuf field.
+#[derive(Debug, PartialEq)]
+pub enum Field {
+    Varint(u64),
+    /// A 64-bit field, i.e. a `double` or a `fixed64`.
+    Double(f64),
+    Bytes(Vec<u8>),
+}
+
+impl Field {
+    pub fn message(&self) -> Vec<(u64, Field)> {
+        match self {
+            Field::Bytes(bytes) => decode(bytes),
+            _ => panic!("not a message: {self:?}"),
+        }
+    }
+
+    pub fn string(&self) -> &str {
+        match self {
+            Field::Bytes(bytes) => std::str::from_utf8(bytes).unwrap(),
+            _ => panic!("not a string: {self:?}"),
+        }
+    }
+
+    pub fn varint(&self) -> u64 {
+        match self {
+            Field::Varint(v) => *v,
+            _ => panic!("not a varint: {self:?}"),
+        }
+    }
+
+    pub fn double(&self) -> f64 {
+        match self {
+            Field::Double(v) => *v,
+            _ => panic!("not a double: {self:?}"),
+        }
+    }
+
+    pub fn fixed64(&self) -> u64 {
+        match self {
+            Field::Double(v) => v.to_bits(),
+            _ => panic!("not a fixed64: {self:?}"),
+        }
+    }
+}
+
+/// Returns the first field of the message with the given number.
+pub fn field(message: &[(u64, Field)], number: u64) -> &Field {
+    &message.iter().find(|(n, _)| *n == number).unwrap().1
+}
+
+pub fn varint(bytes: &mut &[u8]) -> u64 {
+    let mut v = 0;
+
+    for shift in (0..).step_by(7) {
+        let byte = bytes[0];
+        *bytes = &bytes[1..];
+        v |= u64::from(byte & 0x7f) << shift;
+
+        if byte < 0x80 {
+            break;
+        }
+    }
+
+    v
+}
+
+pub fn decode(mut bytes: &[u8]) -> Vec<(u64, Field)> {
+    let mut fields = vec![];
+
+    while !bytes.is_empty() {
+        let key = varint(&mut bytes);
+        let field = match key & 7 {
+            0 => Field::Varint(varint(&mut bytes)),
+            1 => {
+                let (double, rest) = bytes.split_at(8);
+                bytes = rest;
+                Field::Double(f64::from_le_bytes(double.try_into().unwrap()))
+            }
+            2 => {
+                let len = varint(&mut bytes) as usize;
+                let (message, rest) = bytes.split_at(len);
+                bytes = rest;
+                Field::Bytes(message.to_vec())
+            }
+            wire_type => panic!("unexpected wire type {wire_type}"),
+        };
+
+        fields.push((key >> 3, field));
+    }
+
+    fields
+}
+
+/// Decodes length-delimited messages.
+pub fn decode_delimited(mut bytes: &[u8]) -> Vec<Vec<(u64, Field)>> {
+    let mut messages = vec![];
+
+    while !bytes.is_empty() {
+        let len = varint(&mut bytes) as usize;
+        let (message, rest) = bytes.split_at(len);
+        messages.push(decode(message));
+        bytes = rest;
+    }
+
+    messages
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use common::encode_to_string;
+use prometheus_client::{
+    metrics::counter::Counter,
+    registry::{Registry, Unit},
+};
+use prometools::nonstandard::NonstandardUnsuffixedCounter;
+use std::sync::atomic::AtomicU64;
+
+#[test]
+fn float_counters() {
+    let mut registry = <Registry>::default();
+    let transferred = Counter::<f64, AtomicU64>::default();
+    let unsuffixed = NonstandardUnsuffixedCounter::<f64>::from(Counter::default());
+
+    registry.register_with_unit(
+        "transferred",
+        "Data transferred",
+        Unit::Other("gigabytes".to_owned()),
+        Box::new(transferred.clone()),
+    );
+    registry.register("busy_seconds", "Busy time", Box::new(unsuffixed.clone()));
+
+    transferred.inc_by(1536.0 / 1024.0);
+    unsuffixed.inc_by(0.5);
+    unsuffixed.inc();
+
+    assert_eq!(
+        encode_to_string(&registry),
+        concat!(
+            "# HELP transferred_gigabytes Data transferred.\n",
+            "# TYPE transferred_gigabytes counter\n",
+            "# UNIT transferred_gigabytes gigabytes\n",
+            "transferred_gigabytes_total 1.5\n",
+            "# HELP busy_seconds Busy time.\n",
+            "# TYPE busy_seconds counter\n",
+            "busy_seconds 1.5\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn float_counter_families() {
+    use prometools::serde::{CounterWithExemplar, Family};
+    use serde::Serialize;
+
+    #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    struct Labels {
+        core: u8,
+    }
+
+    #[derive(Serialize)]
+    struct Trace {
+        trace_id: &'static str,
+    }
+
+    let mut registry = <Registry>::default();
+    let cpu = <Family<Labels, Counter<f64, AtomicU64>>>::default();
+    let unsuffixed = <Family<Labels, NonstandardUnsuffixedCounter<f64>>>::default();
+    let traced = <Family<Labels, CounterWithExemplar<Trace, f64>>>::default();
+
+    registry.register("cpu_seconds", "CPU time", Box::new(cpu.clone()));
+    registry.register("idle_seconds", "Idle time", Box::new(unsuffixed.clone()));
+    registry.register("gc_seconds", "GC time", Box::new(traced.clone()));
+
+    cpu.get_or_create(&Labels { core: 0 }).inc_by(0.25);
+    unsuffixed.get_or_create(&Labels { core: 1 }).inc_by(2.5);
+    traced
+        .get_or_create(&Labels { core: 0 })
+        .inc_by(0.125, Some(Trace { trace_id: "3fa9" }));
+
+    assert_eq!(
+        encode_to_string(&registry),
+        concat!(
+            "# HELP cpu_seconds CPU time.\n",
+            "# TYPE cpu_seconds counter\n",
+            "cpu_seconds_total{core=\"0\"} 0.25\n",
+            "# HELP idle_seconds Idle time.\n",
+            "# TYPE idle_seconds counter\n",
+            "idle_seconds{core=\"1\"} 2.5\n",
+            "# HELP gc_seconds GC time.\n",
+            "# TYPE gc_seconds counter\n",
+            "gc_seconds_total{core=\"0\"} 0.125 # {trace_id=\"3fa9\"} 0.125\n",
+            "# EOF\n",
+        ),
+    );
+}
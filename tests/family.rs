@@ -0,0 +1,153 @@
+#![cfg(feature = "serde")]
+
+use prometheus_client::metrics::counter::Counter;
+use prometools::serde::{Equivalent, Family, FloatFormat, NewtypeVariantFormat, Options};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Serialize)]
+struct Labels {
+    host: String,
+}
+
+#[test]
+fn label_sets_not_equivalent_to_their_key_keep_their_series() {
+    #[derive(Hash)]
+    struct Host<'a>(&'a str);
+
+    impl Equivalent<Labels> for Host<'_> {
+        fn equivalent(&self, labels: &Labels) -> bool {
+            self.0 == labels.host
+        }
+    }
+
+    let family = <Family<Labels, Counter>>::default();
+    let labels = Labels {
+        host: "example.org".to_owned(),
+    };
+
+    family.get_or_create(&labels).inc();
+
+    // The label set isn't equivalent to the key, which is thus never found.
+    for _ in 0..2 {
+        family
+            .get_or_create_with_key(&Host("EXAMPLE.ORG"), || labels.clone())
+            .inc();
+    }
+
+    assert_eq!(family.get_or_create(&labels).get(), 3);
+}
+
+#[test]
+fn dropping_a_drained_scope_keeps_the_new_series() {
+    let family = <Family<Labels, Counter>>::default();
+    let labels = Labels {
+        host: "example.org".to_owned(),
+    };
+    let old = family.get_or_create_scoped(&labels);
+
+    family.drain();
+
+    let new = family.get_or_create_scoped(&labels);
+
+    new.inc();
+    drop(old);
+
+    assert_eq!(family.get_or_create(&labels).get(), 1);
+    drop(new);
+    assert_eq!(family.get_or_create(&labels).get(), 0);
+}
+
+#[test]
+fn label_sets_rounding_to_the_same_values_share_their_series() {
+    #[derive(Clone, PartialEq, Serialize)]
+    struct Labels {
+        ratio: f64,
+    }
+
+    impl Eq for Labels {}
+
+    impl std::hash::Hash for Labels {
+        fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+            self.ratio.to_bits().hash(state)
+        }
+    }
+
+    let family = <Family<Labels, Counter>>::default()
+        .with_options(Options::default().float_format(FloatFormat::Fixed(1)));
+    let rounded = Labels { ratio: 0.1 + 0.2 };
+    let exact = Labels { ratio: 0.3 };
+
+    family.get_or_create(&rounded).inc();
+    family.get_or_create(&exact).inc();
+
+    assert_eq!(family.get_or_create(&rounded).get(), 2);
+    assert_eq!(family.get_or_create(&exact).get(), 2);
+
+    for i in 0..1000 {
+        let ratio = 0.3 + f64::from(i) * 1E-6;
+
+        family.get_or_create(&Labels { ratio }).inc();
+    }
+
+    assert_eq!(family.drain().len(), 1);
+
+    family.get_or_create(&exact).inc();
+
+    assert_eq!(family.get_or_create(&exact).get(), 1);
+    assert_eq!(family.get_or_create(&rounded).get(), 1);
+
+    family.drain();
+
+    let first = family.get_or_create_scoped(&rounded);
+    let second = family.get_or_create_scoped(&exact);
+
+    first.inc();
+    second.inc();
+    drop((first, second));
+
+    assert_eq!(family.get_or_create(&exact).get(), 0);
+}
+
+#[test]
+fn label_names_sanitized_the_same_way_share_their_series() {
+    let family = <Family<BTreeMap<&str, &str>, Counter>>::default()
+        .with_options(Options::default().sanitize_keys(true));
+    let dashed = BTreeMap::from([("a-b", "x")]);
+    let dotted = BTreeMap::from([("a.b", "x")]);
+
+    family.get_or_create(&dashed).inc();
+    family.get_or_create(&dotted).inc();
+
+    assert_eq!(family.get_or_create(&dotted).get(), 2);
+    assert_eq!(family.drain().len(), 1);
+}
+
+#[test]
+fn variants_serialized_the_same_way_share_their_series() {
+    #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    enum Source {
+        Static,
+        Upstream(String),
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    struct Labels {
+        source: Source,
+    }
+
+    let family = <Family<Labels, Counter>>::default()
+        .with_options(Options::default().newtype_variant_format(NewtypeVariantFormat::Inner));
+    let unit = Labels {
+        source: Source::Static,
+    };
+    let newtype = Labels {
+        source: Source::Upstream("Static".to_owned()),
+    };
+
+    family.get_or_create(&unit).inc();
+    family.get_or_create(&newtype).inc();
+
+    assert_eq!(family.get_or_create(&unit).get(), 2);
+    assert_eq!(family.drain().len(), 1);
+}
@@ -0,0 +1,84 @@
+mod common;
+
+use common::encode_to_string_with;
+use prometheus_client::metrics::{counter::Counter, family::Family, histogram::Histogram};
+use prometheus_client::registry::Registry;
+use prometools::encoding::filter::Filter;
+
+fn encode_filtered(filter: &Filter, registry: &Registry) -> String {
+    encode_to_string_with(registry, |writer, registry| filter.encode(writer, registry))
+}
+
+#[test]
+fn allowed_families_keep_all_their_samples() {
+    let mut registry = <Registry>::default();
+    let latency = Histogram::new([0.1].into_iter());
+    let requests = Counter::<u64>::default();
+
+    registry.register("http_latency", "Latency", Box::new(latency.clone()));
+    registry.register("requests", "Requests", Box::new(requests));
+
+    latency.observe(0.05);
+
+    assert_eq!(
+        encode_filtered(&Filter::default().allow("http_*"), &registry),
+        concat!(
+            "# HELP http_latency Latency.\n",
+            "# TYPE http_latency histogram\n",
+            "http_latency_sum 0.05\n",
+            "http_latency_count 1\n",
+            "http_latency_bucket{le=\"0.1\"} 1\n",
+            "http_latency_bucket{le=\"+Inf\"} 1\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[test]
+fn families_without_matching_series_are_skipped() {
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+    let errors = Family::<Vec<(&str, &str)>, Counter>::default();
+    let empty = Family::<Vec<(&str, &str)>, Counter>::default();
+
+    registry.register("requests", "Requests", Box::new(requests.clone()));
+    registry.register("errors", "Errors", Box::new(errors.clone()));
+    registry.register("empty", "Empty", Box::new(empty));
+
+    requests.get_or_create(&vec![("region", "eu-west-1")]).inc();
+    requests.get_or_create(&vec![("region", "us-east-1")]).inc();
+    errors.get_or_create(&vec![]).inc();
+
+    assert_eq!(
+        encode_filtered(
+            &Filter::default().require_label("region", "eu-*-1"),
+            &registry
+        ),
+        concat!(
+            "# HELP requests Requests.\n",
+            "# TYPE requests counter\n",
+            "requests_total{region=\"eu-west-1\"} 1\n",
+            "# HELP empty Empty.\n",
+            "# TYPE empty counter\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[test]
+fn denied_families_win_over_allowed_ones() {
+    let mut registry = <Registry>::default();
+
+    registry.register("a_b", "A", Box::new(Counter::<u64>::default()));
+    registry.register("a_c", "A", Box::new(Counter::<u64>::default()));
+
+    assert_eq!(
+        encode_filtered(&Filter::default().allow("a_*").deny("*c"), &registry),
+        concat!(
+            "# HELP a_b A.\n",
+            "# TYPE a_b counter\n",
+            "a_b_total 0\n",
+            "# EOF\n",
+        ),
+    );
+}
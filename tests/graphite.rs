@@ -0,0 +1,42 @@
+use prometheus_client::metrics::{family::Family, gauge::Gauge};
+use prometheus_client::registry::Registry;
+use prometools::graphite::{Graphite, LabelScheme};
+use std::io::Read;
+use std::net::TcpListener;
+use std::thread;
+
+#[test]
+fn push() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut registry = <Registry>::default();
+    let temperature = Family::<Vec<(&str, &str)>, Gauge>::default();
+
+    registry.register("temperature", "Temperature", Box::new(temperature.clone()));
+
+    temperature
+        .get_or_create(&vec![("room", "living room"), ("floor", "1")])
+        .set(21);
+
+    let receiver = thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut lines = String::new();
+
+        stream.read_to_string(&mut lines).unwrap();
+        lines
+    });
+
+    Graphite::new(addr.to_string())
+        .with_label_scheme(LabelScheme::Values)
+        .push(&registry)
+        .unwrap();
+
+    let lines = receiver.join().unwrap();
+    let (path, rest) = lines.split_once(' ').unwrap();
+    let (value, timestamp) = rest.split_once(' ').unwrap();
+
+    assert_eq!(path, "temperature.living_room.1");
+    assert_eq!(value, "21");
+    assert!(timestamp.trim_end().parse::<u64>().unwrap() > 0);
+    assert!(lines.ends_with('\n'));
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "gzip")]
+
+use flate2::read::GzDecoder;
+use prometheus_client::encoding::text::encode;
+use prometheus_client::metrics::{counter::Counter, family::Family};
+use prometheus_client::registry::Registry;
+use prometools::encoding::gzip::{self, GzipWriter};
+use std::io::{Read, Write};
+
+/// Decompresses gzip data with a reference decoder.
+fn gunzip(gzip: &[u8]) -> Vec<u8> {
+    let mut output = vec![];
+
+    GzDecoder::new(gzip).read_to_end(&mut output).unwrap();
+    output
+}
+
+#[test]
+fn round_trip() {
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(String, String)>, Counter>::default();
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+
+    for i in 0..5000 {
+        requests
+            .get_or_create(&vec![("path".to_owned(), format!("/api/{i}"))])
+            .inc_by(i);
+    }
+
+    let mut text = vec![];
+    encode(&mut text, &registry).unwrap();
+
+    let compressed = gzip::encode_to_vec(&registry).unwrap();
+
+    assert!(text.len() > 128 * 1024);
+    assert!(compressed.len() < text.len() / 4);
+    assert_eq!(gunzip(&compressed), text);
+}
+
+#[test]
+fn empty_and_small_writes() {
+    assert_eq!(gunzip(&GzipWriter::new(vec![]).finish().unwrap()), b"");
+
+    let mut writer = GzipWriter::new(vec![]);
+
+    for _ in 0..1000 {
+        writer.write_all(b"a").unwrap();
+    }
+
+    assert_eq!(gunzip(&writer.finish().unwrap()), [b'a'; 1000]);
+}
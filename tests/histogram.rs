@@ -1,6 +1,15 @@
+use prometheus_client::encoding::text::encode;
 use prometheus_client::metrics::histogram::{exponential_buckets, linear_buckets};
-use prometools::histogram::TimeHistogram;
-use std::thread::sleep;
+use prometheus_client::registry::Registry;
+use prometools::histogram::{
+    AdaptiveTimeHistogram, ExemplarSampling, FixedTimeHistogram, HistogramTimer, ManualClock,
+    NativeHistogram, RollingTimeHistogram, ShardedTimeHistogram, TimeHistogram, TimeSource,
+};
+use prometools::nonstandard::GaugeHistogram;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, sleep};
 use std::time::Duration;
 
 #[test]
@@ -22,40 +31,242 @@ fn histogram() {
     assert_eq!(snapshot.buckets()[4].1, 1);
 }
 
+#[test]
+fn exemplars_keep_last_per_bucket() {
+    let histogram = TimeHistogram::new([1.0, 2.0].into_iter());
+    let mut registry = <Registry>::default();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    histogram.observe_with_exemplar(500_000_000, vec![("trace_id".to_owned(), "a".to_owned())]);
+    histogram.observe_with_exemplar(750_000_000, vec![("trace_id".to_owned(), "b".to_owned())]);
+    histogram.observe(1_500_000_000);
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        concat!(
+            "# HELP latency Latency.\n",
+            "# TYPE latency histogram\n",
+            "latency_sum 2.75\n",
+            "latency_count 3\n",
+            "latency_bucket{le=\"1.0\"} 2 # {trace_id=\"b\"} 0.75\n",
+            "latency_bucket{le=\"2.0\"} 3\n",
+            "latency_bucket{le=\"+Inf\"} 3\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[test]
+fn exemplars_recorded_while_encoding() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let mut registry = <Registry>::default();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    std::thread::scope(|scope| {
+        for thread in 0..4 {
+            let histogram = &histogram;
+
+            scope.spawn(move || {
+                for i in 0..1000 {
+                    histogram.observe_with_exemplar(i, vec![("thread", thread.to_string())]);
+                }
+            });
+        }
+
+        for _ in 0..100 {
+            encode(&mut vec![], &registry).unwrap();
+        }
+    });
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    assert_eq!(histogram.count(), 4000);
+    assert!(String::from_utf8(buffer)
+        .unwrap()
+        .contains("latency_bucket{le=\"1.0\"} 4000 # {thread=\""));
+}
+
+#[test]
+fn exemplars_kept_while_encoding() {
+    let histogram = TimeHistogram::new([1.0].into_iter())
+        .with_exemplar_sampling(ExemplarSampling::Max(Duration::MAX));
+    let mut registry = <Registry>::default();
+
+    histogram.observe_with_exemplar(750_000_000, vec![("trace_id", "slow")]);
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            for i in 0..1000 {
+                histogram.observe_with_exemplar(i, vec![("trace_id", "fast")]);
+            }
+        });
+
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..100 {
+                    let mut buffer = vec![];
+                    encode(&mut buffer, &registry).unwrap();
+
+                    assert!(String::from_utf8(buffer)
+                        .unwrap()
+                        .contains(" # {trace_id=\"slow\"} 0.75\n"));
+                }
+            });
+        }
+    });
+}
+
+#[test]
+fn exemplar_sampling() {
+    fn exemplar(histogram: &TimeHistogram) -> String {
+        let mut registry = <Registry>::default();
+        registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+        let mut buffer = vec![];
+        encode(&mut buffer, &registry).unwrap();
+
+        let encoded = String::from_utf8(buffer).unwrap();
+        let (_value, exemplar) = encoded.split_once(" # ").unwrap();
+
+        exemplar.lines().next().unwrap().to_owned()
+    }
+
+    let sampled = |sampling| {
+        let histogram = TimeHistogram::new([1.0].into_iter()).with_exemplar_sampling(sampling);
+
+        histogram.observe_with_exemplar(500_000_000, vec![("trace_id", "a")]);
+        histogram.observe_with_exemplar(250_000_000, vec![("trace_id", "b")]);
+        histogram.observe_with_exemplar(750_000_000, vec![("trace_id", "c")]);
+
+        exemplar(&histogram)
+    };
+
+    assert_eq!(sampled(ExemplarSampling::Last), "{trace_id=\"c\"} 0.75");
+    assert_eq!(
+        sampled(ExemplarSampling::Probabilistic(1.0)),
+        "{trace_id=\"c\"} 0.75"
+    );
+    assert_eq!(
+        sampled(ExemplarSampling::Probabilistic(0.0)),
+        "{trace_id=\"a\"} 0.5"
+    );
+    assert_eq!(
+        sampled(ExemplarSampling::Min(Duration::MAX)),
+        "{trace_id=\"b\"} 0.25"
+    );
+    assert_eq!(
+        sampled(ExemplarSampling::Max(Duration::MAX)),
+        "{trace_id=\"c\"} 0.75"
+    );
+    assert_eq!(
+        sampled(ExemplarSampling::Min(Duration::ZERO)),
+        "{trace_id=\"c\"} 0.75"
+    );
+}
+
+#[test]
+fn timer_stop_and_record_with_exemplar() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let mut registry = <Registry>::default();
+    let clock = ManualClock::new();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    let timer = histogram.start_timer_with_clock(clock.clone());
+    clock.advance(Duration::from_millis(250));
+    let duration = timer.stop_and_record_with_exemplar(vec![("trace_id", "a")]);
+
+    assert_eq!(duration, Duration::from_millis(250));
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    assert_eq!(
+        String::from_utf8(buffer).unwrap(),
+        concat!(
+            "# HELP latency Latency.\n",
+            "# TYPE latency histogram\n",
+            "latency_sum 0.25\n",
+            "latency_count 1\n",
+            "latency_bucket{le=\"1.0\"} 1 # {trace_id=\"a\"} 0.25\n",
+            "latency_bucket{le=\"+Inf\"} 1\n",
+            "# EOF\n",
+        ),
+    );
+}
+
 #[test]
 fn timer_stop_and_record() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let timer = histogram.start_timer_with_clock(clock.clone());
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 0);
+    assert_eq!(duration, Duration::from_millis(0));
 
     assert_eq!(histogram.snapshot().count(), 1);
 }
 
 #[test]
 fn timer_stop_and_discard() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let timer = histogram.start_timer_with_clock(clock.clone());
     let duration = timer.stop_and_discard();
 
-    assert_duration(duration, 0);
+    assert_eq!(duration, Duration::from_millis(0));
 
     assert_eq!(histogram.snapshot().count(), 0)
 }
 
+#[test]
+fn timer_also_record() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let global = TimeHistogram::new([1.0].into_iter());
+    let clock = ManualClock::new();
+
+    let timer = histogram
+        .start_timer_with_clock(clock.clone())
+        .also_record(&global);
+    clock.advance(Duration::from_millis(250));
+    drop(timer);
+
+    let timer = histogram
+        .start_timer_with_clock(clock.clone())
+        .also_record(&global);
+    clock.advance(Duration::from_millis(500));
+    timer.stop_and_record_with_exemplar(vec![("trace_id", "a")]);
+
+    histogram
+        .start_timer_with_clock(clock.clone())
+        .also_record(&global)
+        .stop_and_discard();
+
+    assert_eq!(histogram.snapshot(), global.snapshot());
+    assert_eq!(global.snapshot().count(), 2);
+    assert_eq!(global.snapshot().sum(), 0.75);
+}
+
 #[test]
 fn timer_pause_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 10);
+    assert_eq!(duration, Duration::from_millis(10));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -67,18 +278,19 @@ fn timer_pause_stop() {
 
 #[test]
 fn timer_pause_resume_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
     timer.resume();
-    sleep(Duration::from_millis(40));
+    clock.advance(Duration::from_millis(40));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 50);
+    assert_eq!(duration, Duration::from_millis(50));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -90,16 +302,17 @@ fn timer_pause_resume_stop() {
 
 #[test]
 fn timer_resume_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.resume();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 30);
+    assert_eq!(duration, Duration::from_millis(30));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -111,18 +324,19 @@ fn timer_resume_stop() {
 
 #[test]
 fn timer_pause_pause_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
     timer.pause();
-    sleep(Duration::from_millis(40));
+    clock.advance(Duration::from_millis(40));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 10);
+    assert_eq!(duration, Duration::from_millis(10));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -134,20 +348,21 @@ fn timer_pause_pause_stop() {
 
 #[test]
 fn timer_pause_resume_pause_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
     timer.resume();
-    sleep(Duration::from_millis(40));
+    clock.advance(Duration::from_millis(40));
     timer.pause();
-    sleep(Duration::from_millis(80));
+    clock.advance(Duration::from_millis(80));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 10 + 40);
+    assert_eq!(duration, Duration::from_millis(10 + 40));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -159,22 +374,23 @@ fn timer_pause_resume_pause_stop() {
 
 #[test]
 fn timer_pause_resume_pause_resume_stop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 20));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 20));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
     timer.resume();
-    sleep(Duration::from_millis(40));
+    clock.advance(Duration::from_millis(40));
     timer.pause();
-    sleep(Duration::from_millis(80));
+    clock.advance(Duration::from_millis(80));
     timer.resume();
-    sleep(Duration::from_millis(120));
+    clock.advance(Duration::from_millis(120));
 
     let duration = timer.stop_and_record();
 
-    assert_duration(duration, 10 + 40 + 120);
+    assert_eq!(duration, Duration::from_millis(10 + 40 + 120));
 
     let snapshot = histogram.snapshot();
     let buckets = snapshot.buckets();
@@ -184,16 +400,77 @@ fn timer_pause_resume_pause_resume_stop() {
     assert_eq!(buckets[18].1, 0);
 }
 
+#[test]
+fn timer_lap_while_paused() {
+    let first = TimeHistogram::new([1.0].into_iter());
+    let second = TimeHistogram::new([1.0].into_iter());
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
+
+    clock.advance(Duration::from_millis(10));
+    timer.pause();
+    clock.advance(Duration::from_millis(20));
+
+    assert_eq!(timer.lap(&first), Duration::from_millis(10));
+
+    clock.advance(Duration::from_millis(40));
+    timer.resume();
+    clock.advance(Duration::from_millis(80));
+
+    assert_eq!(timer.lap(&second), Duration::from_millis(80));
+
+    clock.advance(Duration::from_millis(160));
+
+    assert_eq!(timer.stop_and_record(), Duration::from_millis(160));
+    assert_eq!(first.snapshot().count(), 1);
+    assert_eq!(second.snapshot().count(), 1);
+    assert_eq!(histogram.snapshot().count(), 1);
+}
+
+#[test]
+fn timer_pause_guard_early_return() {
+    fn downstream(
+        timer: &mut HistogramTimer<ManualClock>,
+        clock: &ManualClock,
+        fail: bool,
+    ) -> Result<(), ()> {
+        let _paused = timer.pause_guard();
+        clock.advance(Duration::from_millis(500));
+
+        if fail {
+            return Err(());
+        }
+
+        Ok(())
+    }
+
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
+
+    assert!(downstream(&mut timer, &clock, true).is_err());
+    clock.advance(Duration::from_millis(100));
+
+    // A guard doesn't resume a timer which was already paused.
+    timer.pause();
+    assert!(downstream(&mut timer, &clock, false).is_ok());
+    clock.advance(Duration::from_millis(100));
+
+    assert_eq!(timer.stop_and_record(), Duration::from_millis(100));
+}
+
 #[test]
 fn timer_resume_drop() {
-    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
-    let mut timer = histogram.start_timer();
+    let histogram = TimeHistogram::new(linear_buckets(0.005, 0.01, 12));
+    let clock = ManualClock::new();
+    let mut timer = histogram.start_timer_with_clock(clock.clone());
 
-    sleep(Duration::from_millis(10));
+    clock.advance(Duration::from_millis(10));
     timer.pause();
-    sleep(Duration::from_millis(20));
+    clock.advance(Duration::from_millis(20));
     timer.resume();
-    sleep(Duration::from_millis(40));
+    clock.advance(Duration::from_millis(40));
     drop(timer);
 
     let snapshot = histogram.snapshot();
@@ -217,3 +494,470 @@ fn assert_duration(duration: Duration, ms: u128) {
         "duration {duration_ms} should be at most {max_ms}"
     );
 }
+
+#[test]
+fn native_bucket_bounds_are_inclusive() {
+    let histogram = NativeHistogram::new(0);
+    let mut registry = <Registry>::default();
+
+    registry.register("values", "Values", Box::new(histogram.clone()));
+
+    histogram.observe(-1.0);
+    histogram.observe(0.0);
+    histogram.observe(1.0);
+    histogram.observe(2.0);
+    histogram.observe(2.5);
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    let buckets: Vec<_> = String::from_utf8(buffer)
+        .unwrap()
+        .lines()
+        .filter(|line| line.starts_with("values_bucket"))
+        .map(ToOwned::to_owned)
+        .collect();
+
+    assert_eq!(
+        buckets,
+        [
+            "values_bucket{le=\"-0.5\"} 1",
+            "values_bucket{le=\"5.421010862427522e-20\"} 2",
+            "values_bucket{le=\"1.0\"} 3",
+            "values_bucket{le=\"2.0\"} 4",
+            "values_bucket{le=\"4.0\"} 5",
+            "values_bucket{le=\"+Inf\"} 5",
+        ],
+    );
+}
+
+#[test]
+fn native_growth_factor() {
+    assert_eq!(NativeHistogram::with_growth_factor(1.1).schema(), 3);
+    assert_eq!(NativeHistogram::with_growth_factor(2.0).schema(), 0);
+    assert_eq!(NativeHistogram::with_growth_factor(1.0001).schema(), 8);
+}
+
+#[test]
+fn sharded_encodes_like_unsharded() {
+    let histogram = TimeHistogram::new(exponential_buckets(0.001, 2.0, 12));
+    let sharded = ShardedTimeHistogram::with_shards(exponential_buckets(0.001, 2.0, 12), 3);
+
+    let mut registry = <Registry>::default();
+    let mut sharded_registry = <Registry>::default();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+    sharded_registry.register("latency", "Latency", Box::new(sharded.clone()));
+
+    std::thread::scope(|scope| {
+        for i in 0..6u64 {
+            let (histogram, sharded) = (&histogram, &sharded);
+
+            scope.spawn(move || {
+                for j in 0..100 {
+                    histogram.observe(i * j * 100_000);
+                    sharded.observe(i * j * 100_000);
+                }
+            });
+        }
+    });
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+    let mut sharded_buffer = vec![];
+    encode(&mut sharded_buffer, &sharded_registry).unwrap();
+
+    assert_eq!(
+        String::from_utf8(sharded_buffer).unwrap(),
+        String::from_utf8(buffer).unwrap(),
+    );
+}
+
+#[test]
+fn time_future_idle() {
+    let histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
+    let idle_histogram = TimeHistogram::new(linear_buckets(0.01, 0.01, 12));
+
+    let mut future = pin!(histogram.time_future(yield_once()));
+    let mut idle_future = pin!(idle_histogram.time_future(yield_once()).exclude_idle());
+    let mut cx = Context::from_waker(Waker::noop());
+
+    assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+    assert_eq!(idle_future.as_mut().poll(&mut cx), Poll::Pending);
+
+    sleep(Duration::from_millis(20));
+
+    assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(()));
+    assert_eq!(idle_future.as_mut().poll(&mut cx), Poll::Ready(()));
+
+    assert_duration(Duration::from_secs_f64(histogram.snapshot().sum()), 20);
+    assert_duration(Duration::from_secs_f64(idle_histogram.snapshot().sum()), 0);
+}
+
+#[test]
+fn timer_coarse_time_source() {
+    let histogram =
+        TimeHistogram::new(linear_buckets(0.01, 0.01, 12)).with_time_source(TimeSource::Coarse);
+
+    // Tick the coarse clock at least once before timing anything.
+    histogram.start_timer().stop_and_discard();
+    sleep(Duration::from_millis(5));
+
+    let timer = histogram.start_timer();
+    sleep(Duration::from_millis(30));
+    let duration = timer.stop_and_record();
+
+    assert!(
+        duration >= Duration::from_millis(20) && duration < Duration::from_millis(60),
+        "duration {duration:?} should be about 30ms",
+    );
+    assert_eq!(histogram.snapshot().count(), 1);
+}
+
+#[test]
+fn rolling_window_expires_slices() {
+    let clock = ManualClock::new();
+    let histogram = RollingTimeHistogram::with_clock(
+        linear_buckets(0.1, 0.1, 3),
+        Duration::from_secs(3),
+        3,
+        clock.clone(),
+    );
+
+    histogram.observe_secs(0.05);
+    clock.advance(Duration::from_secs(1));
+    histogram.observe_secs(0.15);
+    histogram.observe_secs(0.15);
+    clock.advance(Duration::from_secs(1));
+    histogram.observe_secs(0.25);
+
+    let window = histogram.window_snapshot();
+
+    assert_eq!(window.count(), 4);
+    assert_eq!(
+        window.buckets(),
+        [(0.1, 1), (0.2, 2), (0.30000000000000004, 1), (f64::MAX, 0)],
+    );
+
+    clock.advance(Duration::from_secs(1));
+    histogram.observe_secs(0.5);
+
+    let window = histogram.window_snapshot();
+
+    assert_eq!(window.count(), 4);
+    assert_eq!(
+        window.buckets(),
+        [(0.1, 0), (0.2, 2), (0.30000000000000004, 1), (f64::MAX, 1)],
+    );
+
+    clock.advance(Duration::from_secs(10));
+
+    assert_eq!(histogram.window_snapshot().count(), 0);
+    assert_eq!(histogram.snapshot().count(), 5);
+    assert_eq!(histogram.snapshot().sum(), 1.1);
+}
+
+async fn yield_once() {
+    let mut yielded = false;
+
+    poll_fn(|cx| {
+        if yielded {
+            Poll::Ready(())
+        } else {
+            yielded = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[test]
+fn snapshot_arithmetic_requires_same_buckets() {
+    let a = TimeHistogram::new([0.1, 1.0].into_iter());
+    let b = TimeHistogram::new([0.1, 2.0].into_iter());
+    let c = TimeHistogram::new([0.1].into_iter());
+
+    assert_eq!(a.snapshot().diff(&b.snapshot()), None);
+    assert_eq!(a.snapshot().merge(&c.snapshot()), None);
+
+    a.observe_secs(0.5);
+
+    let snapshot = a.snapshot();
+
+    assert_eq!(snapshot.diff(&snapshot).unwrap().count(), 0);
+    assert_eq!(
+        TimeHistogram::new([0.1, 1.0].into_iter())
+            .snapshot()
+            .diff(&snapshot),
+        Some(TimeHistogram::new([0.1, 1.0].into_iter()).snapshot()),
+    );
+    assert_eq!(snapshot.merge(&snapshot).unwrap().count(), 2);
+}
+
+#[test]
+fn min_max() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let sharded = ShardedTimeHistogram::with_shards([1.0].into_iter(), 4);
+    let clock = ManualClock::new();
+    let rolling = RollingTimeHistogram::with_clock(
+        [1.0].into_iter(),
+        Duration::from_secs(2),
+        2,
+        clock.clone(),
+    );
+
+    assert_eq!(sharded.snapshot().min(), None);
+    assert_eq!(rolling.window_snapshot().max(), None);
+
+    for secs in [0.25, 3.0, 0.5] {
+        histogram.observe_secs(secs);
+        sharded.observe_secs(secs);
+        rolling.observe_secs(secs);
+    }
+
+    clock.advance(Duration::from_secs(2));
+    rolling.observe_secs(1.5);
+
+    let merged = histogram.snapshot().merge(&sharded.snapshot()).unwrap();
+
+    assert_eq!((merged.min(), merged.max()), (Some(0.25), Some(3.0)));
+    assert_eq!(merged.diff(&histogram.snapshot()).unwrap().min(), None);
+    assert_eq!(rolling.snapshot().max(), Some(3.0));
+    assert_eq!(
+        (
+            rolling.window_snapshot().min(),
+            rolling.window_snapshot().max()
+        ),
+        (Some(1.5), Some(1.5)),
+    );
+
+    histogram.reset();
+
+    assert_eq!(histogram.snapshot().max(), None);
+}
+
+#[test]
+fn gauge_histogram_goes_down() {
+    let histogram = GaugeHistogram::new([1.0, 2.0].into_iter());
+
+    histogram.observe(0.5);
+    histogram.observe(1.5);
+    histogram.remove(0.5);
+    histogram.remove(0.5);
+
+    let snapshot = histogram.snapshot();
+
+    assert_eq!(snapshot.count(), 0);
+    assert_eq!(snapshot.sum(), 1.0);
+    assert_eq!(snapshot.buckets(), [(1.0, 0), (2.0, 1), (f64::MAX, 0)]);
+
+    histogram.reset();
+
+    assert_eq!(histogram.snapshot().buckets()[1], (2.0, 0));
+}
+
+#[test]
+fn fixed_encodes_like_unsharded() {
+    let fixed = FixedTimeHistogram::new([0.1, 0.2, 0.5]);
+    let histogram = TimeHistogram::new([0.1, 0.2, 0.5].into_iter());
+    let mut fixed_registry = <Registry>::default();
+    let mut registry = <Registry>::default();
+
+    for nanos in [50_000_000, 150_000_000, 200_000_000, 800_000_000] {
+        fixed.observe(nanos);
+        histogram.observe(nanos);
+    }
+
+    assert_eq!(fixed.snapshot(), histogram.snapshot());
+
+    fixed_registry.register("latency", "Latency", Box::new(fixed));
+    registry.register("latency", "Latency", Box::new(histogram));
+
+    let mut fixed_buffer = vec![];
+    let mut buffer = vec![];
+
+    encode(&mut fixed_buffer, &fixed_registry).unwrap();
+    encode(&mut buffer, &registry).unwrap();
+
+    assert_eq!(
+        String::from_utf8(fixed_buffer).unwrap(),
+        String::from_utf8(buffer).unwrap(),
+    );
+}
+
+#[cfg(feature = "hdr")]
+#[test]
+fn hdr_extreme_values() {
+    use prometools::histogram::HdrTimeHistogram;
+
+    for precision in [1, 7, 14] {
+        let histogram = HdrTimeHistogram::with_precision([1.0].into_iter(), precision);
+
+        histogram.observe(0);
+        histogram.observe(1);
+        histogram.observe(u64::MAX);
+
+        assert_eq!(histogram.quantile(0.0), Some(Duration::ZERO));
+        assert_eq!(histogram.quantile(0.5), Some(Duration::from_nanos(1)));
+        assert_eq!(
+            histogram.quantile(1.0),
+            Some(Duration::from_nanos(u64::MAX)),
+        );
+        assert_eq!(histogram.snapshot().buckets(), [(1.0, 2), (f64::MAX, 1)],);
+    }
+}
+
+#[test]
+fn adaptive_merges_buckets() {
+    let histogram = AdaptiveTimeHistogram::with_max_buckets(4, 3);
+
+    assert_eq!(histogram.upper_bounds(), None);
+
+    histogram.observe_secs(0.01);
+    histogram.observe_secs(0.015);
+    histogram.observe_secs(0.1);
+
+    assert_eq!(histogram.snapshot().buckets(), [(f64::MAX, 3)]);
+
+    histogram.observe_secs(0.15);
+
+    assert_eq!(histogram.upper_bounds().unwrap(), [0.02, 0.5, f64::MAX]);
+
+    histogram.observe_secs(1.0);
+
+    let snapshot = histogram.snapshot();
+
+    assert_eq!(snapshot.count(), 5);
+    assert_eq!(snapshot.min(), Some(0.01));
+    assert_eq!(snapshot.max(), Some(1.0));
+    assert_eq!(snapshot.buckets(), [(0.02, 2), (0.5, 2), (f64::MAX, 1)]);
+}
+
+#[test]
+fn thresholds_count_bound_as_within() {
+    let histogram = TimeHistogram::new([1.0].into_iter())
+        .with_threshold(
+            Duration::from_millis(100),
+            "within_fast_total",
+            "slow_total",
+        )
+        .with_threshold(
+            Duration::from_millis(300),
+            "within_slo_total",
+            "breached_slo_total",
+        );
+    let mut registry = <Registry>::default();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    histogram.observe(100_000_000);
+    histogram.observe_n(200_000_000, 2);
+    histogram.observe(300_000_001);
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    assert!(String::from_utf8(buffer).unwrap().ends_with(concat!(
+        "latency_within_fast_total 1\n",
+        "latency_slow_total 3\n",
+        "latency_within_slo_total 3\n",
+        "latency_breached_slo_total 1\n",
+        "# EOF\n",
+    )));
+
+    histogram.reset();
+
+    let mut buffer = vec![];
+    encode(&mut buffer, &registry).unwrap();
+
+    assert!(String::from_utf8(buffer).unwrap().ends_with(concat!(
+        "latency_within_fast_total 0\n",
+        "latency_slow_total 0\n",
+        "latency_within_slo_total 0\n",
+        "latency_breached_slo_total 0\n",
+        "# EOF\n",
+    )));
+}
+
+#[test]
+fn sum_does_not_overflow() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+
+    histogram.observe_n(u64::MAX, 4);
+    histogram.observe(1);
+
+    let expected = (u64::MAX as f64) * 4.0 * 1E-9;
+
+    assert_eq!(histogram.snapshot().count(), 5);
+    assert!((histogram.snapshot().sum() - expected).abs() < expected * 1E-15);
+}
+
+#[test]
+fn sum_never_goes_backwards() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                for _ in 0..10_000 {
+                    histogram.observe(u64::MAX / 3);
+                }
+            });
+        }
+
+        let mut previous = 0.0;
+
+        for _ in 0..10_000 {
+            let sum = histogram.snapshot().sum();
+
+            assert!(sum >= previous, "{sum} < {previous}");
+            previous = sum;
+        }
+    });
+}
+
+#[test]
+fn sharded_sum_does_not_overflow() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let sharded = ShardedTimeHistogram::with_shards([1.0].into_iter(), 4);
+    let single = ShardedTimeHistogram::with_shards([1.0].into_iter(), 1);
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            scope.spawn(|| {
+                histogram.observe(u64::MAX);
+                sharded.observe(u64::MAX);
+            });
+        }
+    });
+
+    single.observe_duration(Duration::MAX);
+    single.observe_duration(Duration::MAX);
+
+    let expected = (u64::MAX as f64) * 2.0 * 1E-9;
+
+    assert_eq!(sharded.snapshot().sum(), histogram.snapshot().sum());
+    assert_eq!(single.snapshot().sum(), expected);
+}
+
+#[test]
+fn rolling_sum_does_not_overflow() {
+    let clock = ManualClock::new();
+    let rolling = RollingTimeHistogram::with_clock(
+        [1.0].into_iter(),
+        Duration::from_secs(2),
+        2,
+        clock.clone(),
+    );
+
+    rolling.observe(u64::MAX);
+    rolling.observe(u64::MAX);
+    clock.advance(Duration::from_secs(1));
+    rolling.observe(u64::MAX);
+
+    let expected = (u64::MAX as f64) * 3.0 * 1E-9;
+
+    assert_eq!(rolling.window_snapshot().sum(), expected);
+    assert_eq!(rolling.snapshot().sum(), expected);
+}
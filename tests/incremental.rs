@@ -0,0 +1,141 @@
+mod common;
+
+use common::encode_to_string_with;
+use prometheus_client::metrics::{counter::Counter, family::Family, gauge::Gauge};
+use prometheus_client::registry::Registry;
+use prometools::encoding::incremental::IncrementalEncoder;
+use std::time::Duration;
+
+fn encode_incrementally(encoder: &mut IncrementalEncoder, registry: &Registry) -> (bool, String) {
+    let mut full = false;
+    let serialized = encode_to_string_with(registry, |writer, registry| {
+        full = encoder.encode(writer, registry)?;
+        Ok(())
+    });
+
+    (full, serialized)
+}
+
+#[test]
+fn only_changed_series_are_encoded() {
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+    let errors = Counter::<u64>::default();
+    let temperature = Gauge::<u64>::default();
+
+    registry.register("requests", "Requests", Box::new(requests.clone()));
+    registry.register("errors", "Errors", Box::new(errors.clone()));
+    registry.register("temperature", "Temperature", Box::new(temperature.clone()));
+
+    requests.get_or_create(&vec![("method", "GET")]).inc();
+
+    let mut encoder = IncrementalEncoder::new(Duration::MAX);
+
+    assert_eq!(
+        encode_incrementally(&mut encoder, &registry),
+        (
+            true,
+            concat!(
+                "# HELP requests Requests.\n",
+                "# TYPE requests counter\n",
+                "requests_total{method=\"GET\"} 1\n",
+                "# HELP errors Errors.\n",
+                "# TYPE errors counter\n",
+                "errors_total 0\n",
+                "# HELP temperature Temperature.\n",
+                "# TYPE temperature gauge\n",
+                "temperature 0\n",
+                "# EOF\n",
+            )
+            .to_string(),
+        ),
+    );
+    assert_eq!(
+        encode_incrementally(&mut encoder, &registry),
+        (false, "# EOF\n".to_string()),
+    );
+
+    errors.inc();
+    temperature.set(21);
+
+    assert_eq!(
+        encode_incrementally(&mut encoder, &registry),
+        (
+            false,
+            concat!(
+                "# HELP errors Errors.\n",
+                "# TYPE errors counter\n",
+                "errors_total 1\n",
+                "# HELP temperature Temperature.\n",
+                "# TYPE temperature gauge\n",
+                "temperature 21\n",
+                "# EOF\n",
+            )
+            .to_string(),
+        ),
+    );
+}
+
+#[test]
+fn reappearing_series_are_encoded() {
+    let mut registry = <Registry>::default();
+    let mut empty_registry = <Registry>::default();
+    let requests = Counter::<u64>::default();
+
+    registry.register("requests", "Requests", Box::new(requests.clone()));
+    empty_registry.register("errors", "Errors", Box::new(Counter::<u64>::default()));
+
+    let mut encoder = IncrementalEncoder::new(Duration::MAX);
+
+    encode_incrementally(&mut encoder, &registry);
+
+    assert_eq!(
+        encode_incrementally(&mut encoder, &empty_registry),
+        (
+            false,
+            concat!(
+                "# HELP errors Errors.\n",
+                "# TYPE errors counter\n",
+                "errors_total 0\n",
+                "# EOF\n",
+            )
+            .to_string(),
+        ),
+    );
+    assert_eq!(
+        encode_incrementally(&mut encoder, &registry),
+        (
+            false,
+            concat!(
+                "# HELP requests Requests.\n",
+                "# TYPE requests counter\n",
+                "requests_total 0\n",
+                "# EOF\n",
+            )
+            .to_string(),
+        ),
+    );
+}
+
+#[test]
+fn resync_encodes_all_series() {
+    let mut registry = <Registry>::default();
+
+    registry.register("requests", "Requests", Box::new(Counter::<u64>::default()));
+
+    let mut encoder = IncrementalEncoder::new(Duration::MAX);
+    let (_full, all) = encode_incrementally(&mut encoder, &registry);
+
+    encoder.resync();
+
+    assert_eq!(
+        encode_incrementally(&mut encoder, &registry),
+        (true, all.clone())
+    );
+
+    let mut encoder = IncrementalEncoder::new(Duration::ZERO);
+
+    encode_incrementally(&mut encoder, &registry);
+
+    assert_eq!(encode_incrementally(&mut encoder, &registry), (true, all));
+}
@@ -0,0 +1,48 @@
+mod common;
+
+use common::encode_to_string_with;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::registry::Registry;
+use prometools::encoding::json::encode;
+use prometools::histogram::TimeHistogram;
+use std::sync::atomic::AtomicU64;
+
+#[test]
+fn non_finite_values_and_escaping() {
+    let mut registry = <Registry>::default();
+    let ratio = Gauge::<f64, AtomicU64>::default();
+
+    registry.register("ratio", "A \"ratio\"\\", Box::new(ratio.clone()));
+
+    ratio.set(f64::NAN);
+
+    assert_eq!(
+        encode_to_string_with(&registry, encode),
+        concat!(
+            r#"[{"name":"ratio","type":"gauge","help":"A \"ratio\"\\.","#,
+            r#""samples":[{"name":"ratio","labels":{},"value":"NaN"}]}]"#,
+        ),
+    );
+}
+
+#[test]
+fn histogram_with_exemplar() {
+    let mut registry = <Registry>::default();
+    let histogram = TimeHistogram::new([1.0].into_iter());
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    histogram.observe_with_exemplar(500_000_000, vec![("trace_id", "3fa9")]);
+
+    assert_eq!(
+        encode_to_string_with(&registry, encode),
+        concat!(
+            r#"[{"name":"latency","type":"histogram","help":"Latency.","samples":["#,
+            r#"{"name":"latency_sum","labels":{},"value":0.5},"#,
+            r#"{"name":"latency_count","labels":{},"value":1},"#,
+            r#"{"name":"latency_bucket","labels":{"le":"1.0"},"value":1,"#,
+            r#""exemplar":{"labels":{"trace_id":"3fa9"},"value":0.5}},"#,
+            r#"{"name":"latency_bucket","labels":{"le":"+Inf"},"value":1}]}]"#,
+        ),
+    );
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use common::encode_to_string_with;
+use prometheus_client::metrics::gauge::Gauge;
+use prometheus_client::registry::Registry;
+use prometools::encoding::line_protocol::encode;
+use std::sync::atomic::AtomicU64;
+
+#[test]
+fn non_finite_values_are_skipped() {
+    let mut registry = <Registry>::default();
+    let ratio = Gauge::<f64, AtomicU64>::default();
+    let temperature = Gauge::<f64, AtomicU64>::default();
+
+    registry.register("ratio", "Ratio", Box::new(ratio.clone()));
+    registry.register("temperature", "Temperature", Box::new(temperature.clone()));
+
+    ratio.set(f64::NAN);
+    temperature.set(21.5);
+
+    assert_eq!(
+        encode_to_string_with(&registry, encode),
+        "temperature value=21.5\n"
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_families_as_tags() {
+    use prometheus_client::metrics::counter::Counter;
+    use prometools::serde::Family;
+    use serde::Serialize;
+
+    #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    struct Labels {
+        region: &'static str,
+        host: Option<&'static str>,
+        method: Method,
+    }
+
+    #[derive(Clone, Eq, Hash, PartialEq, Serialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Method {
+        Get,
+    }
+
+    let mut registry = <Registry>::default();
+    let requests = <Family<Labels, Counter>>::default();
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+
+    requests
+        .get_or_create(&Labels {
+            region: "eu west",
+            host: None,
+            method: Method::Get,
+        })
+        .inc();
+
+    assert_eq!(
+        encode_to_string_with(&registry, encode),
+        "requests,method=get,region=eu\\ west total=1\n",
+    );
+}
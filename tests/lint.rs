@@ -0,0 +1,107 @@
+use prometools::lint::{check_text, Diagnostic, DiagnosticKind};
+
+#[test]
+fn valid() {
+    let text = concat!(
+        "# HELP http_requests Number of requests.\n",
+        "# TYPE http_requests counter\n",
+        "http_requests_total{method=\"GET\",path=\"/\\\"quoted\\\"\"} 1\n",
+        "http_requests_total{method=\"POST\",path=\"/\"} 2\n",
+        "# HELP latency_seconds Latency.\n",
+        "# TYPE latency_seconds histogram\n",
+        "# UNIT latency_seconds seconds\n",
+        "latency_seconds_sum 1.5\n",
+        "latency_seconds_count 2\n",
+        "latency_seconds_bucket{le=\"1.0\"} 1\n",
+        "latency_seconds_bucket{le=\"+Inf\"} 2\n",
+        "# EOF\n",
+    );
+
+    assert_eq!(check_text(text), []);
+}
+
+#[test]
+fn duplicate_series() {
+    let text = concat!(
+        "# TYPE build gauge\n",
+        "build{version=\"1\",mode=\"debug\"} 1\n",
+        "build{mode=\"debug\",version=\"1\"} 1\n",
+        "# EOF\n",
+    );
+
+    assert_eq!(
+        check_text(text),
+        [Diagnostic {
+            line: 3,
+            kind: DiagnosticKind::DuplicateSeries("build{mode=\"debug\",version=\"1\"}".to_owned()),
+        }],
+    );
+}
+
+#[test]
+fn invalid_names() {
+    let text = concat!(
+        "# TYPE up gauge\n",
+        "up{0day=\"1\",job=\"a\",job=\"b\"} 1\n",
+        "down 0\n",
+        "# TYPE 1up gauge\n",
+    );
+
+    assert_eq!(
+        check_text(text),
+        [
+            Diagnostic {
+                line: 2,
+                kind: DiagnosticKind::InvalidLabelName("0day".to_owned()),
+            },
+            Diagnostic {
+                line: 2,
+                kind: DiagnosticKind::DuplicateLabel("job".to_owned()),
+            },
+            Diagnostic {
+                line: 3,
+                kind: DiagnosticKind::UnexpectedSample("down".to_owned()),
+            },
+            Diagnostic {
+                line: 4,
+                kind: DiagnosticKind::InvalidMetricName("1up".to_owned()),
+            },
+            Diagnostic {
+                line: 4,
+                kind: DiagnosticKind::MissingEof,
+            },
+        ],
+    );
+}
+
+#[test]
+fn histogram_suffixes() {
+    let text = concat!(
+        "# TYPE size histogram\n",
+        "size_total 1\n",
+        "size_bucket 1\n",
+        "size_count x\n",
+        "# EOF\n",
+    );
+
+    assert_eq!(
+        check_text(text),
+        [
+            Diagnostic {
+                line: 2,
+                kind: DiagnosticKind::InvalidSuffix {
+                    family: "size".to_owned(),
+                    sample: "size_total".to_owned(),
+                },
+            },
+            Diagnostic {
+                line: 3,
+                kind: DiagnosticKind::MissingBucketBound("size_bucket".to_owned()),
+            },
+            Diagnostic {
+                line: 4,
+                kind: DiagnosticKind::InvalidValue("x".to_owned()),
+            },
+        ],
+    );
+}
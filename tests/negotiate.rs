@@ -0,0 +1,37 @@
+use prometools::encoding::{negotiate, Format};
+
+#[test]
+fn protobuf() {
+    let accept = concat!(
+        "application/vnd.google.protobuf;proto=io.prometheus.client.MetricFamily;",
+        "encoding=delimited;q=0.7,",
+        "text/plain;version=0.0.4;q=0.3",
+    );
+
+    assert_eq!(negotiate(Some(accept)), Format::Protobuf);
+    assert_eq!(
+        negotiate(Some("application/vnd.google.protobuf;proto=other.Message")),
+        Format::TextLegacy,
+    );
+}
+
+#[test]
+fn quality() {
+    assert_eq!(
+        negotiate(Some(
+            "text/plain;q=0.5, application/openmetrics-protobuf;version=1.0.0"
+        )),
+        Format::OpenMetricsProtobuf,
+    );
+    assert_eq!(
+        negotiate(Some(
+            "application/openmetrics-text, application/openmetrics-protobuf"
+        )),
+        Format::OpenMetricsText,
+    );
+    assert_eq!(
+        negotiate(Some("application/openmetrics-text;q=0")),
+        Format::TextLegacy,
+    );
+    assert_eq!(negotiate(Some("application/json")), Format::TextLegacy);
+}
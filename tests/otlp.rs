@@ -0,0 +1,99 @@
+#![cfg(feature = "otlp")]
+
+mod common;
+
+use common::{decode, field, Field};
+use prometheus_client::metrics::counter::Counter;
+use prometheus_client::registry::Registry;
+use prometools::histogram::TimeHistogram;
+use prometools::otlp::OtlpExporter;
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn counters_and_histograms() {
+    let mut registry = <Registry>::default();
+    let requests = <Counter>::default();
+    let latency = TimeHistogram::new([0.1, 1.0].into_iter());
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+    registry.register("latency", "Request latency", Box::new(latency.clone()));
+
+    requests.inc_by(3);
+    latency.observe_with_exemplar(
+        50_000_000,
+        vec![("trace_id", "0af7651916cd43dd8448eb211c80319c")],
+    );
+    latency.observe_secs(0.5);
+    latency.observe_secs(0.75);
+
+    let exporter = OtlpExporter::new("http://localhost:4318/v1/metrics")
+        .unwrap()
+        .with_resource_attribute("service.name", "web");
+    let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let request = decode(&exporter.export_request(&registry, now).unwrap());
+    let resource_metrics = field(&request, 1).message();
+    let resource = field(&resource_metrics, 1).message();
+    let attribute = field(&resource, 1).message();
+
+    assert_eq!(field(&attribute, 1).string(), "service.name");
+
+    let scope_metrics = field(&resource_metrics, 2).message();
+
+    assert_eq!(
+        field(&field(&scope_metrics, 1).message(), 1).string(),
+        "prometools"
+    );
+
+    let metrics: Vec<_> = scope_metrics
+        .iter()
+        .filter(|(n, _)| *n == 2)
+        .map(|(_, metric)| metric.message())
+        .collect();
+
+    assert_eq!(metrics.len(), 2);
+
+    let requests = &metrics[0];
+    let sum = field(requests, 7).message();
+    let point = field(&sum, 1).message();
+
+    assert_eq!(field(requests, 1).string(), "requests");
+    assert_eq!(field(&sum, 2), &Field::Varint(2));
+    assert_eq!(field(&sum, 3), &Field::Varint(1));
+    assert_eq!(field(&point, 3).fixed64(), 1_700_000_000_000_000_000);
+    assert_eq!(field(&point, 4).double(), 3.0);
+
+    let latency = &metrics[1];
+    let histogram = field(latency, 9).message();
+    let point = field(&histogram, 1).message();
+
+    assert_eq!(field(latency, 1).string(), "latency");
+    assert_eq!(field(&point, 4).fixed64(), 3);
+    assert_eq!(field(&point, 5).double(), 1.3);
+
+    let Field::Bytes(counts) = field(&point, 6) else {
+        panic!("bucket counts aren't packed");
+    };
+    let counts: Vec<_> = counts
+        .chunks(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+
+    assert_eq!(counts, [1, 2, 0]);
+
+    let Field::Bytes(bounds) = field(&point, 7) else {
+        panic!("explicit bounds aren't packed");
+    };
+
+    assert_eq!(bounds.len(), 16);
+
+    let exemplar = field(&point, 8).message();
+
+    assert_eq!(field(&exemplar, 3).double(), 0.05);
+    assert_eq!(
+        field(&exemplar, 5),
+        &Field::Bytes(vec![
+            0x0a, 0xf7, 0x65, 0x19, 0x16, 0xcd, 0x43, 0xdd, 0x84, 0x48, 0xeb, 0x21, 0x1c, 0x80,
+            0x31, 0x9c,
+        ]),
+    );
+}
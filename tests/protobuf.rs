@@ -0,0 +1,265 @@
+mod common;
+
+use common::{decode, decode_delimited, Field};
+use prometheus_client::metrics::{counter::Counter, family::Family, gauge::Gauge, info::Info};
+use prometheus_client::registry::Registry;
+use prometools::encoding::{negotiate, openmetrics_protobuf, protobuf, Format};
+use prometools::histogram::{NativeHistogram, NativeHistograms, TimeHistogram};
+
+fn encode_families(registry: &Registry) -> Vec<Vec<(u64, Field)>> {
+    let mut serialized = vec![];
+
+    protobuf::encode(&mut serialized, registry).unwrap();
+
+    decode_delimited(&serialized)
+}
+
+fn encode_metric_set(registry: &Registry) -> Vec<Vec<(u64, Field)>> {
+    let mut serialized = vec![];
+
+    openmetrics_protobuf::encode(&mut serialized, registry).unwrap();
+
+    decode(&serialized)
+        .iter()
+        .map(|(field, family)| {
+            assert_eq!(*field, 1);
+            family.message()
+        })
+        .collect()
+}
+
+#[test]
+fn counters_and_gauges() {
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(String, String)>, Counter>::default();
+    let temperature = <Gauge>::default();
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+    registry.register("temperature", "Temperature", Box::new(temperature.clone()));
+
+    requests
+        .get_or_create(&vec![("path".to_owned(), "/a b".to_owned())])
+        .inc_by(3);
+    temperature.set(21);
+
+    let families = encode_families(&registry);
+
+    assert_eq!(families.len(), 2);
+
+    let requests = &families[0];
+
+    assert_eq!(requests[0], (1, Field::Bytes(b"requests_total".to_vec())));
+    assert_eq!(requests[1].1.string(), "Number of requests.");
+    assert_eq!(requests[2], (3, Field::Varint(0)));
+
+    let metric = requests[3].1.message();
+    let label = metric[0].1.message();
+
+    assert_eq!(label[0].1.string(), "path");
+    assert_eq!(label[1].1.string(), "/a b");
+    assert_eq!(metric[1].0, 3);
+    assert_eq!(metric[1].1.message(), [(1, Field::Double(3.0))]);
+
+    let temperature = &families[1];
+
+    assert_eq!(temperature[0].1.string(), "temperature");
+    assert_eq!(temperature[2], (3, Field::Varint(1)));
+    assert_eq!(
+        temperature[3].1.message(),
+        [(2, Field::Bytes(vec![0x09, 0, 0, 0, 0, 0, 0, 0x35, 0x40]))],
+    );
+}
+
+#[test]
+fn histogram_with_exemplar() {
+    let mut registry = <Registry>::default();
+    let histogram = TimeHistogram::new([1.0].into_iter()).with_created();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    histogram.observe_with_exemplar(500_000_000, vec![("trace_id", "3fa9")]);
+    histogram.observe(2_000_000_000);
+
+    let families = encode_families(&registry);
+
+    assert_eq!(families.len(), 1);
+
+    let family = &families[0];
+
+    assert_eq!(family[0].1.string(), "latency");
+    assert_eq!(family[2], (3, Field::Varint(4)));
+
+    let metric = family[3].1.message();
+
+    assert_eq!(metric.len(), 1);
+    assert_eq!(metric[0].0, 7);
+
+    let histogram = metric[0].1.message();
+    let fields: Vec<_> = histogram.iter().map(|(field, _)| *field).collect();
+
+    assert_eq!(fields, [2, 1, 3, 3, 15]);
+    assert_eq!(histogram[0].1, Field::Double(2.5));
+    assert_eq!(histogram[1].1, Field::Varint(2));
+
+    let bucket = histogram[2].1.message();
+
+    assert_eq!(bucket[0], (1, Field::Varint(1)));
+    assert_eq!(bucket[1], (2, Field::Double(1.0)));
+
+    let exemplar = bucket[2].1.message();
+
+    assert_eq!(exemplar[0].1.message()[1].1.string(), "3fa9");
+    assert_eq!(exemplar[1], (2, Field::Double(0.5)));
+
+    let inf = histogram[3].1.message();
+
+    assert_eq!(inf[0], (1, Field::Varint(2)));
+    assert_eq!(inf[1], (2, Field::Double(f64::INFINITY)));
+}
+
+#[test]
+fn negotiated_native_histograms() {
+    let mut registry = <Registry>::default();
+    let latency = Family::<Vec<(String, String)>, NativeHistogram>::new_with_constructor(|| {
+        NativeHistogram::new(0)
+    });
+
+    let mut natives = NativeHistograms::default();
+
+    registry.register("latency", "Latency", Box::new(latency.clone()));
+
+    let histogram = latency
+        .get_or_create(&vec![("path".to_owned(), "/".to_owned())])
+        .clone();
+
+    histogram.observe(0.3);
+    histogram.observe(3.0);
+    natives.register("latency", &[("path", "/")], histogram);
+
+    let format = negotiate(Some(protobuf::CONTENT_TYPE));
+
+    assert_eq!(format, Format::Protobuf);
+
+    let (body, _content_type) = format.encode_with_native(&registry, &natives).unwrap();
+    let families = decode_delimited(&body);
+
+    assert_eq!(families.len(), 1);
+
+    let family = &families[0];
+
+    assert_eq!(family[0].1.string(), "latency");
+    assert_eq!(family[2], (3, Field::Varint(4)));
+
+    let metric = family[3].1.message();
+
+    assert_eq!(metric[0].1.message()[1].1.string(), "/");
+    assert_eq!(metric[1].0, 7);
+
+    let histogram = metric[1].1.message();
+    let fields: Vec<_> = histogram.iter().map(|(field, _)| *field).collect();
+
+    // Count, sum, schema, zero threshold, zero count, a positive span and
+    // its deltas, without classic buckets.
+    assert_eq!(fields, [1, 2, 5, 6, 7, 12, 13]);
+    assert_eq!(histogram[0].1, Field::Varint(2));
+    assert_eq!(histogram[1].1, Field::Double(3.3));
+    assert_eq!(histogram[2].1, Field::Varint(0));
+
+    // Histograms which aren't listed keep their classic buckets.
+    let (body, _content_type) = format.encode(&registry).unwrap();
+    let families = decode_delimited(&body);
+    let histogram = families[0][3].1.message()[1].1.message();
+    let fields: Vec<_> = histogram.iter().map(|(field, _)| *field).collect();
+
+    assert_eq!(fields, [2, 1, 3, 3, 3]);
+}
+
+#[test]
+fn openmetrics_counters_and_info() {
+    let mut registry = <Registry>::default();
+    let requests = <Counter>::default();
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+    registry.register(
+        "build",
+        "Build information",
+        Box::new(Info::new(vec![("version", "1.0")])),
+    );
+
+    requests.inc();
+
+    let families = encode_metric_set(&registry);
+
+    assert_eq!(families.len(), 2);
+
+    let requests = &families[0];
+
+    assert_eq!(requests[0].1.string(), "requests");
+    assert_eq!(requests[1], (2, Field::Varint(2)));
+    assert_eq!(requests[2].1.string(), "Number of requests.");
+
+    let point = requests[3].1.message()[0].1.message();
+
+    assert_eq!(point[0].0, 3);
+    assert_eq!(point[0].1.message(), [(1, Field::Double(1.0))]);
+
+    let build = &families[1];
+
+    assert_eq!(build[0].1.string(), "build");
+    assert_eq!(build[1], (2, Field::Varint(4)));
+
+    let metric = build[3].1.message();
+
+    assert_eq!(metric.len(), 1);
+
+    let info = metric[0].1.message()[0].1.message();
+    let label = info[0].1.message();
+
+    assert_eq!(label[0].1.string(), "version");
+    assert_eq!(label[1].1.string(), "1.0");
+}
+
+#[test]
+fn openmetrics_histogram_with_exemplar() {
+    let mut registry = <Registry>::default();
+    let histogram = TimeHistogram::new([1.0].into_iter()).with_created();
+
+    registry.register("latency", "Latency", Box::new(histogram.clone()));
+
+    histogram.observe_with_exemplar(500_000_000, vec![("trace_id", "3fa9")]);
+    histogram.observe(2_000_000_000);
+
+    let families = encode_metric_set(&registry);
+
+    assert_eq!(families.len(), 1);
+
+    let family = &families[0];
+
+    assert_eq!(family[0].1.string(), "latency");
+    assert_eq!(family[1], (2, Field::Varint(5)));
+
+    let point = family[3].1.message()[0].1.message();
+
+    assert_eq!(point[0].0, 4);
+
+    let histogram = point[0].1.message();
+    let fields: Vec<_> = histogram.iter().map(|(field, _)| *field).collect();
+
+    assert_eq!(fields, [1, 3, 5, 5, 4]);
+    assert_eq!(histogram[0].1, Field::Double(2.5));
+    assert_eq!(histogram[1].1, Field::Varint(2));
+
+    let bucket = histogram[2].1.message();
+
+    assert_eq!(bucket[0], (1, Field::Varint(1)));
+    assert_eq!(bucket[1], (2, Field::Double(1.0)));
+
+    let exemplar = bucket[2].1.message();
+
+    assert_eq!(exemplar[0], (1, Field::Double(0.5)));
+    assert_eq!(exemplar[1].1.message()[1].1.string(), "3fa9");
+
+    let created = histogram[4].1.message();
+
+    assert!(matches!(created[0], (1, Field::Varint(secs)) if secs > 0));
+}
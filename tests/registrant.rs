@@ -0,0 +1,72 @@
+#![cfg(feature = "derive")]
+
+mod common;
+
+use common::encode_to_string;
+use prometheus_client::{metrics::counter::Counter, registry::Registry};
+use prometools::{histogram::TimeHistogram, registrant::Registrant};
+
+#[test]
+fn help_from_first_doc_paragraph() {
+    #[derive(Registrant)]
+    struct Metrics {
+        /// Time spent
+        ///   handling requests.
+        ///
+        /// Only the first paragraph is used as help text.
+        r#latency: TimeHistogram,
+    }
+
+    let metrics = Metrics {
+        latency: TimeHistogram::new([1.0].into_iter()),
+    };
+
+    let mut registry = <Registry>::default();
+    metrics.register(&mut registry);
+
+    assert!(encode_to_string(&registry).starts_with(concat!(
+        "# HELP latency Time spent handling requests.\n",
+        "# TYPE latency histogram\n",
+    )));
+}
+
+#[test]
+fn generic_and_nested_prefix() {
+    #[derive(Registrant)]
+    #[registrant(prefix = "inner")]
+    struct Metrics<T: Clone> {
+        /// Number of events.
+        events: Counter,
+        #[registrant(skip)]
+        _marker: T,
+    }
+
+    let metrics = Metrics {
+        events: Counter::default(),
+        _marker: (),
+    };
+
+    let mut registry = <Registry>::default();
+    metrics.register(registry.sub_registry_with_prefix("outer"));
+
+    assert_eq!(
+        encode_to_string(&registry),
+        concat!(
+            "# HELP outer_inner_events Number of events.\n",
+            "# TYPE outer_inner_events counter\n",
+            "outer_inner_events_total 0\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[test]
+fn empty() {
+    #[derive(Registrant)]
+    struct Metrics {}
+
+    let mut registry = <Registry>::default();
+    Metrics {}.register(&mut registry);
+
+    assert_eq!(encode_to_string(&registry), "# EOF\n");
+}
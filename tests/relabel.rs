@@ -0,0 +1,187 @@
+#![cfg(feature = "relabel")]
+
+use prometheus_client::metrics::{counter::Counter, family::Family, histogram::Histogram};
+use prometheus_client::registry::Registry;
+use prometools::encoding::relabel::Relabel;
+
+fn relabel_counter(relabel: Relabel, labels: Vec<(&'static str, &'static str)>) -> String {
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(&str, &str)>, Counter>::default();
+    let mut serialized = vec![];
+
+    registry.register("requests", "Requests", Box::new(requests.clone()));
+    requests.get_or_create(&labels).inc();
+    relabel.encode(&mut serialized, &registry).unwrap();
+
+    let serialized = String::from_utf8(serialized).unwrap();
+
+    serialized.lines().nth(2).unwrap().to_string()
+}
+
+#[test]
+fn rules_are_applied_in_order() {
+    let relabel = Relabel::default()
+        .add_label("env", "prod")
+        .rename_label("env", "environment")
+        .replace_value("environment", "prod", "production")
+        .unwrap();
+
+    assert_eq!(
+        relabel_counter(relabel, vec![("method", "GET")]),
+        "requests_total{method=\"GET\",environment=\"production\"} 1",
+    );
+}
+
+#[test]
+fn renaming_replaces_existing_label() {
+    let relabel = Relabel::default().rename_label("a", "b");
+
+    assert_eq!(
+        relabel_counter(relabel, vec![("b", "1"), ("a", "2")]),
+        "requests_total{b=\"2\"} 1",
+    );
+}
+
+#[test]
+fn replacing_with_regex() {
+    let relabel = Relabel::default()
+        .replace_value("path", r"/users/[0-9]+(/.*)?", "/users/:id$1")
+        .unwrap()
+        .replace_value("host", r"(?:[a-z]+\.)*(internal|local)", "")
+        .unwrap()
+        .replace_value("region", "^$", "unknown")
+        .unwrap()
+        .replace_value("method", "GET|HEAD", "$$read")
+        .unwrap()
+        .replace_value("user", r"(\w+)@(\w+)", "${2}_$1")
+        .unwrap();
+
+    assert_eq!(
+        relabel_counter(
+            relabel,
+            vec![
+                ("path", "/users/42/posts"),
+                ("host", "db.eu.internal"),
+                ("method", "HEAD"),
+                ("user", "alice@example"),
+            ],
+        ),
+        concat!(
+            "requests_total{path=\"/users/:id/posts\",method=\"$read\",",
+            "user=\"example_alice\",region=\"unknown\"} 1",
+        ),
+    );
+}
+
+#[test]
+fn regex_must_match_whole_value() {
+    let relabel = Relabel::default()
+        .replace_value("path", "/api", "api")
+        .unwrap();
+
+    assert_eq!(
+        relabel_counter(relabel, vec![("path", "/api/v1")]),
+        "requests_total{path=\"/api/v1\"} 1",
+    );
+}
+
+#[test]
+fn dropping_all_labels() {
+    let relabel = Relabel::default().drop_label("method");
+
+    assert_eq!(
+        relabel_counter(relabel, vec![("method", "GET")]),
+        "requests_total 1",
+    );
+}
+
+#[test]
+fn histogram_buckets_keep_their_bounds() {
+    let mut registry = <Registry>::default();
+    let latency = Histogram::new([0.5].into_iter());
+    let mut serialized = vec![];
+
+    registry.register("latency", "Latency", Box::new(latency));
+    Relabel::default()
+        .add_label("service", "api")
+        .encode(&mut serialized, &registry)
+        .unwrap();
+
+    assert_eq!(
+        String::from_utf8(serialized).unwrap(),
+        concat!(
+            "# HELP latency Latency.\n",
+            "# TYPE latency histogram\n",
+            "latency_sum{service=\"api\"} 0.0\n",
+            "latency_count{service=\"api\"} 0\n",
+            "latency_bucket{le=\"0.5\",service=\"api\"} 0\n",
+            "latency_bucket{le=\"+Inf\",service=\"api\"} 0\n",
+            "# EOF\n",
+        ),
+    );
+}
+
+#[test]
+fn invalid_regex_is_an_error() {
+    let err = Relabel::default()
+        .replace_value("path", "(api", "")
+        .unwrap_err();
+
+    assert!(err
+        .to_string()
+        .starts_with("invalid relabeling regex \"(api\": "));
+}
+
+#[test]
+fn alternations_are_anchored_as_a_whole() {
+    let relabel = Relabel::default()
+        .replace_value("path", "(?i)/api|/health", "matched")
+        .unwrap();
+
+    assert_eq!(
+        relabel_counter(relabel.clone(), vec![("path", "/API")]),
+        "requests_total{path=\"matched\"} 1",
+    );
+    assert_eq!(
+        relabel_counter(relabel, vec![("path", "/api/health")]),
+        "requests_total{path=\"/api/health\"} 1",
+    );
+}
+
+#[test]
+fn non_greedy_repetitions() {
+    let relabel = Relabel::default()
+        .replace_value("host", r"(.+?)\.?(\d*)", "$1")
+        .unwrap();
+
+    assert_eq!(
+        relabel_counter(relabel, vec![("host", "api42")]),
+        "requests_total{host=\"api\"} 1",
+    );
+}
+
+#[test]
+fn matching_is_linear_in_value_length() {
+    // Both patterns would take exponential time or recurse once per
+    // character with a backtracking matcher.
+    let relabel = Relabel::default()
+        .replace_value("a", "(a+)+b", "matched")
+        .unwrap()
+        .replace_value("path", "/(.*)", "$1")
+        .unwrap();
+    let a = "a".repeat(10_000);
+    let path = format!("/{}", "x".repeat(100_000));
+    let line = format!("requests_total{{a=\"{a}\",path=\"{path}\"}} 1\n");
+
+    let relabeled = std::thread::Builder::new()
+        .stack_size(64 * 1024)
+        .spawn(move || relabel.relabel(&line))
+        .unwrap()
+        .join()
+        .unwrap();
+
+    assert_eq!(
+        relabeled,
+        format!("requests_total{{a=\"{a}\",path=\"{}\"}} 1\n", &path[1..]),
+    );
+}
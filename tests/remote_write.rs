@@ -0,0 +1,279 @@
+#![cfg(feature = "remote-write")]
+
+mod common;
+
+use common::{decode, field, Field};
+use prometheus_client::metrics::{counter::Counter, family::Family};
+use prometheus_client::registry::Registry;
+use prometools::histogram::TimeHistogram;
+use prometools::remote_write::{write_request, Error, RemoteWrite};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+type Labels = Vec<(String, String)>;
+
+/// A decoded `TimeSeries` message, with values and their timestamps.
+#[derive(Debug)]
+struct TimeSeries {
+    labels: Labels,
+    samples: Vec<(f64, u64)>,
+    exemplars: Vec<(Labels, f64, u64)>,
+}
+
+impl TimeSeries {
+    fn decode(message: &Field) -> Self {
+        let mut series = TimeSeries {
+            labels: vec![],
+            samples: vec![],
+            exemplars: vec![],
+        };
+
+        for (number, value) in message.message() {
+            match number {
+                1 => series.labels.push(label_pair(&value)),
+                2 => {
+                    let sample = value.message();
+                    series
+                        .samples
+                        .push((field(&sample, 1).double(), field(&sample, 2).varint()));
+                }
+                3 => {
+                    let exemplar = value.message();
+                    let labels = exemplar
+                        .iter()
+                        .filter(|(number, _)| *number == 1)
+                        .map(|(_, label)| label_pair(label))
+                        .collect();
+
+                    series.exemplars.push((
+                        labels,
+                        field(&exemplar, 2).double(),
+                        field(&exemplar, 3).varint(),
+                    ));
+                }
+                number => panic!("unexpected field {number}"),
+            }
+        }
+
+        series
+    }
+
+    fn label(&self, name: &str) -> Option<&str> {
+        self.labels
+            .iter()
+            .find(|(label, _value)| label == name)
+            .map(|(_label, value)| &**value)
+    }
+}
+
+fn label_pair(field: &Field) -> (String, String) {
+    let label = field.message();
+
+    assert_eq!(label[0].0, 1);
+    assert_eq!(label[1].0, 2);
+
+    (
+        label[0].1.string().to_owned(),
+        label[1].1.string().to_owned(),
+    )
+}
+
+fn millis_since_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+/// Receives a request and responds with `status`.
+fn receive(listener: TcpListener, status: &str) -> (Vec<String>, Vec<u8>) {
+    let (stream, _) = listener.accept().unwrap();
+    let mut reader = BufReader::new(stream);
+    let mut head = vec![];
+
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+
+        if line == "\r\n" {
+            break;
+        }
+
+        head.push(line.trim_end().to_owned());
+    }
+
+    let len: usize = head
+        .iter()
+        .find_map(|header| header.strip_prefix("Content-Length: "))
+        .unwrap()
+        .parse()
+        .unwrap();
+    let mut body = vec![0; len];
+
+    reader.read_exact(&mut body).unwrap();
+    reader
+        .get_mut()
+        .write_all(format!("HTTP/1.1 {status}\r\n\r\n").as_bytes())
+        .unwrap();
+
+    (head, body)
+}
+
+#[test]
+fn push() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let mut registry = <Registry>::default();
+    let requests = Family::<Vec<(String, String)>, Counter>::default();
+    let latency = TimeHistogram::new([1.0].into_iter());
+
+    registry.register("requests", "Number of requests", Box::new(requests.clone()));
+    registry.register("latency", "Latency", Box::new(latency.clone()));
+
+    for i in 0..100 {
+        requests
+            .get_or_create(&vec![("path".to_owned(), format!("/{i}"))])
+            .inc_by(i);
+    }
+
+    latency.observe_with_exemplar(500_000_000, vec![("trace_id", "3fa9")]);
+
+    let receiver = thread::spawn(move || receive(listener, "204 No Content"));
+    let before = millis_since_epoch();
+
+    RemoteWrite::new(&format!("http://{addr}/api/v1/write"))
+        .unwrap()
+        .with_header("X-Scope-OrgID", "tenant")
+        .push(&registry)
+        .unwrap();
+
+    let after = millis_since_epoch();
+    let (head, body) = receiver.join().unwrap();
+
+    assert_eq!(head[0], "POST /api/v1/write HTTP/1.1");
+    assert!(head.contains(&"Content-Encoding: snappy".to_owned()));
+    assert!(head.contains(&"X-Scope-OrgID: tenant".to_owned()));
+
+    let request = snap::raw::Decoder::new().decompress_vec(&body).unwrap();
+
+    assert!(body.len() < request.len());
+    assert_eq!(request.len(), write_request(&registry).unwrap().len());
+
+    let series: Vec<_> = decode(&request)
+        .iter()
+        .map(|(number, field)| {
+            assert_eq!(*number, 1);
+            TimeSeries::decode(field)
+        })
+        .collect();
+
+    // 100 counters, and the sum, count and two buckets of the histogram.
+    assert_eq!(series.len(), 104);
+
+    let counter = series
+        .iter()
+        .find(|series| series.label("path") == Some("/7"))
+        .unwrap();
+
+    assert_eq!(
+        counter.labels,
+        [
+            ("__name__".to_owned(), "requests_total".to_owned()),
+            ("path".to_owned(), "/7".to_owned()),
+        ],
+    );
+    assert_eq!(counter.samples.len(), 1);
+    assert_eq!(counter.samples[0].0, 7.0);
+    assert!((before..=after).contains(&counter.samples[0].1));
+    assert!(counter.exemplars.is_empty());
+
+    let bucket = series
+        .iter()
+        .find(|series| series.label("le") == Some("1.0"))
+        .unwrap();
+
+    assert_eq!(
+        bucket.labels,
+        [
+            ("__name__".to_owned(), "latency_bucket".to_owned()),
+            ("le".to_owned(), "1.0".to_owned()),
+        ],
+    );
+    assert_eq!(bucket.samples.len(), 1);
+    assert_eq!(bucket.samples[0].0, 1.0);
+    assert!((before..=after).contains(&bucket.samples[0].1));
+
+    let [(labels, value, timestamp)] = &bucket.exemplars[..] else {
+        panic!("expected one exemplar: {:?}", bucket.exemplars);
+    };
+
+    assert_eq!(labels, &[("trace_id".to_owned(), "3fa9".to_owned())]);
+    assert_eq!(*value, 0.5);
+    assert!((before..=after).contains(timestamp));
+}
+
+#[test]
+fn error_status() {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let receiver = thread::spawn(move || receive(listener, "400 Bad Request"));
+
+    let err = RemoteWrite::new(&format!("http://{addr}"))
+        .unwrap()
+        .push(&<Registry>::default())
+        .unwrap_err();
+
+    receiver.join().unwrap();
+
+    assert!(matches!(err, Error::Status(400)));
+}
+
+#[test]
+fn urls() {
+    assert!(RemoteWrite::new("https://localhost/api/v1/write").is_ok());
+    assert!(matches!(
+        RemoteWrite::new("ftp://localhost"),
+        Err(Error::InvalidUrl(_)),
+    ));
+    assert!(matches!(
+        RemoteWrite::new("localhost:9009"),
+        Err(Error::InvalidUrl(_)),
+    ));
+
+    let listener = TcpListener::bind("[::1]:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let receiver = thread::spawn(move || receive(listener, "204 No Content"));
+
+    RemoteWrite::new(&format!("http://{addr}/api/v1/write"))
+        .unwrap()
+        .push(&<Registry>::default())
+        .unwrap();
+
+    let (head, _body) = receiver.join().unwrap();
+
+    assert_eq!(head[0], "POST /api/v1/write HTTP/1.1");
+}
+
+#[test]
+fn invalid_headers() {
+    let remote_write = RemoteWrite::new("http://localhost:9009").unwrap();
+
+    for (name, value) in [
+        ("X-Scope-OrgID", "tenant\r\nX-Injected: 1"),
+        ("X-Scope-OrgID\r\nX-Injected", "1"),
+        ("X Scope", "tenant"),
+        ("", "tenant"),
+    ] {
+        let err = remote_write
+            .clone()
+            .with_header(name, value)
+            .push(&<Registry>::default())
+            .unwrap_err();
+
+        assert!(matches!(err, Error::InvalidHeader(_)), "{err}");
+    }
+}
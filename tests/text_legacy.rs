@@ -0,0 +1,60 @@
+use prometools::encoding::text_legacy::convert;
+
+#[test]
+fn histogram() {
+    let text = concat!(
+        "# HELP latency_seconds Latency.\n",
+        "# TYPE latency_seconds histogram\n",
+        "# UNIT latency_seconds seconds\n",
+        "latency_seconds_sum 1.5\n",
+        "latency_seconds_count 2\n",
+        "latency_seconds_bucket{le=\"1.0\"} 1 # {trace_id=\"a # b\"} 0.5\n",
+        "latency_seconds_bucket{le=\"+Inf\"} 2\n",
+        "latency_seconds_created 1700000000.0\n",
+        "# EOF\n",
+    );
+
+    assert_eq!(
+        convert(text),
+        concat!(
+            "# HELP latency_seconds Latency.\n",
+            "# TYPE latency_seconds histogram\n",
+            "latency_seconds_sum 1.5\n",
+            "latency_seconds_count 2\n",
+            "latency_seconds_bucket{le=\"1.0\"} 1\n",
+            "latency_seconds_bucket{le=\"+Inf\"} 2\n",
+        ),
+    );
+}
+
+#[test]
+fn counters_and_unknown() {
+    let text = concat!(
+        "# HELP http_requests Number of requests.\n",
+        "# TYPE http_requests counter\n",
+        "http_requests_total{path=\"/a b} # c\"} 1\n",
+        "http_requests_created{path=\"/a b} # c\"} 1700000000.0\n",
+        "# HELP responses Number of responses.\n",
+        "# TYPE responses counter\n",
+        "responses 3\n",
+        "# HELP upstream Value reported upstream.\n",
+        "# TYPE upstream unknown\n",
+        "upstream 1.5 1700000000.25\n",
+        "# EOF\n",
+    );
+
+    assert_eq!(
+        convert(text),
+        concat!(
+            "# HELP http_requests_total Number of requests.\n",
+            "# TYPE http_requests_total counter\n",
+            "http_requests_total{path=\"/a b} # c\"} 1\n",
+            "# HELP responses Number of responses.\n",
+            "# TYPE responses counter\n",
+            "responses 3\n",
+            "# HELP upstream Value reported upstream.\n",
+            "# TYPE upstream untyped\n",
+            "upstream 1.5 1700000000250\n",
+        ),
+    );
+}
@@ -0,0 +1,114 @@
+#![cfg(feature = "derive")]
+
+use prometools::histogram::{DropPolicy, TimeHistogram};
+use prometools::timed;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::LazyLock;
+use std::task::{Context, Poll, Waker};
+
+static EARLY: LazyLock<TimeHistogram> = LazyLock::new(|| TimeHistogram::new([1.0].into_iter()));
+static ASYNC: LazyLock<TimeHistogram> = LazyLock::new(|| TimeHistogram::new([1.0].into_iter()));
+
+#[timed(histogram = EARLY)]
+fn early_return(n: u32) -> u32 {
+    if n == 0 {
+        return 0;
+    }
+
+    n * 2
+}
+
+#[test]
+fn records_early_returns() {
+    assert_eq!(early_return(0), 0);
+    assert_eq!(early_return(2), 4);
+    assert_eq!(EARLY.snapshot().count(), 2);
+}
+
+#[timed(histogram = ASYNC)]
+async fn checked(n: u32) -> Option<u32> {
+    let n = n.checked_sub(1)?;
+    Some(n)
+}
+
+#[test]
+fn records_async_question_mark() {
+    let mut cx = Context::from_waker(Waker::noop());
+
+    let future = checked(0);
+    assert_eq!(ASYNC.snapshot().count(), 0);
+    assert_eq!(pin!(future).poll(&mut cx), Poll::Ready(None));
+    assert_eq!(pin!(checked(1)).poll(&mut cx), Poll::Ready(Some(0)));
+
+    assert_eq!(ASYNC.snapshot().count(), 2);
+}
+
+struct Service {
+    latency: TimeHistogram,
+}
+
+impl Service {
+    #[timed(histogram = self.latency)]
+    fn handle(&self) -> &'static str {
+        "ok"
+    }
+
+    #[timed(histogram = self.latency)]
+    async fn handle_async(&self) -> &'static str {
+        self.handle()
+    }
+}
+
+#[test]
+fn histogram_from_self() {
+    let service = Service {
+        latency: TimeHistogram::new([1.0].into_iter()),
+    };
+
+    let mut cx = Context::from_waker(Waker::noop());
+
+    assert_eq!(service.handle(), "ok");
+    assert_eq!(
+        pin!(service.handle_async()).poll(&mut cx),
+        Poll::Ready("ok")
+    );
+    assert_eq!(service.latency.snapshot().count(), 3);
+}
+
+static CANCELLED: LazyLock<TimeHistogram> =
+    LazyLock::new(|| TimeHistogram::new([1.0].into_iter()).with_drop_policy(DropPolicy::Discard));
+
+#[timed(histogram = CANCELLED)]
+async fn pending_once(fail: bool) -> Result<(), std::fmt::Error> {
+    if fail {
+        Err(std::fmt::Error)?;
+    }
+
+    let mut polled = false;
+    poll_fn(|_| {
+        if polled {
+            Poll::Ready(())
+        } else {
+            polled = true;
+            Poll::Pending
+        }
+    })
+    .await;
+
+    Ok(())
+}
+
+#[test]
+fn async_drop_policy() {
+    let mut cx = Context::from_waker(Waker::noop());
+
+    assert!(pin!(pending_once(false)).poll(&mut cx).is_pending());
+    assert_eq!(CANCELLED.snapshot().count(), 0);
+
+    assert_eq!(
+        pin!(pending_once(true)).poll(&mut cx),
+        Poll::Ready(Err(std::fmt::Error)),
+    );
+    assert_eq!(CANCELLED.snapshot().count(), 1);
+}
@@ -0,0 +1,48 @@
+#![cfg(feature = "tracing")]
+
+use prometools::histogram::TimeHistogram;
+use prometools::tracing::{SpanExt, TimerLayer};
+use std::thread;
+use std::time::Duration;
+use tracing_subscriber::layer::SubscriberExt;
+
+#[test]
+fn timers_are_recorded_when_spans_close() {
+    let histogram = TimeHistogram::new([0.001, 1.0].into_iter());
+    let other = TimeHistogram::new([1.0].into_iter());
+    let subscriber = tracing_subscriber::registry().with(TimerLayer::new());
+
+    tracing::subscriber::with_default(subscriber, || {
+        let span = tracing::info_span!("request");
+        let handle = span.clone();
+
+        assert!(span.attach_timer(histogram.start_timer()).is_ok());
+        assert!(span.attach_timer(other.start_timer()).is_ok());
+
+        span.in_scope(|| thread::sleep(Duration::from_millis(5)));
+        drop(span);
+
+        // The span is still open through its other handle.
+        assert_eq!(histogram.snapshot().count(), 0);
+
+        drop(handle);
+    });
+
+    let snapshot = histogram.snapshot();
+
+    assert_eq!(snapshot.count(), 1);
+    assert_eq!(snapshot.buckets()[0], (0.001, 0));
+    assert!(snapshot.sum() >= 0.005);
+    assert_eq!(other.snapshot().count(), 1);
+}
+
+#[test]
+fn timers_are_returned_without_registry() {
+    let histogram = TimeHistogram::new([1.0].into_iter());
+    let span = tracing::info_span!("request");
+    let timer = span.attach_timer(histogram.start_timer()).err().unwrap();
+
+    timer.stop_and_discard();
+
+    assert_eq!(histogram.snapshot().count(), 0);
+}